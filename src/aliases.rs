@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+use std::fs;
+
+// Display names for machine-style headers, read from a TOML file of
+// `original = "Display name"` pairs
+#[derive(Debug, Default)]
+pub struct Aliases {
+    names: HashMap<String, String>,
+}
+
+impl Aliases {
+    pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("failed to read aliases from {}: {}", path, e))?;
+        let names = toml::from_str(&content)
+            .map_err(|e| format!("invalid aliases file {}: {}", path, e))?;
+        Ok(Self { names })
+    }
+
+    pub fn rename_headers(&self, headers: &mut [String]) {
+        for name in headers {
+            if let Some(display) = self.names.get(name.as_str()) {
+                *name = display.clone();
+            }
+        }
+    }
+
+    // The name a column is shown under, so options accept either name
+    pub fn display<'a>(&'a self, name: &'a str) -> &'a str {
+        self.names
+            .get(name)
+            .map_or(name, |display| display.as_str())
+    }
+
+    pub fn original<'a>(&'a self, name: &'a str) -> &'a str {
+        self.names
+            .iter()
+            .find(|(_, display)| display.as_str() == name)
+            .map_or(name, |(original, _)| original.as_str())
+    }
+
+    // Rewrites the column of a `column=pattern` filter to its display name
+    pub fn filter_expr(&self, expr: &str) -> String {
+        match expr.split_once('=') {
+            Some((name, pattern)) if self.names.contains_key(name.trim()) => {
+                format!("{}={}", self.display(name.trim()), pattern)
+            }
+            _ => expr.to_string(),
+        }
+    }
+}
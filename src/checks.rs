@@ -0,0 +1,331 @@
+use crate::aliases::Aliases;
+use crate::config::ColorScheme;
+use crate::expr::{Expr, Value};
+use crate::sort::{compare_keys, sort_key};
+use crate::stats::column_names;
+use crate::store::Field;
+use crate::{column_index, detect_data_type_cached, new_table, paint, styled};
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Attribute, Cell};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Property {
+    // Every value is at least the one before it
+    Sorted,
+    // Every value is strictly beyond the one before it
+    Monotonic,
+}
+
+// An ordering property from `--assert`, like `sorted:timestamp asc`
+#[derive(Debug, Clone)]
+pub struct Assertion {
+    spec: String,
+    property: Property,
+    column: String,
+    descending: bool,
+}
+
+impl Assertion {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (kind, rest) = spec
+            .split_once(':')
+            .ok_or_else(|| format!("expected KIND:COLUMN [asc|desc], got '{}'", spec))?;
+        let property = match kind.trim() {
+            "sorted" => Property::Sorted,
+            "monotonic" => Property::Monotonic,
+            other => {
+                return Err(format!(
+                    "unknown assertion '{}', expected sorted or monotonic",
+                    other
+                ))
+            }
+        };
+        let rest = rest.trim();
+        let (column, descending) = match rest.rsplit_once(' ') {
+            Some((column, "asc")) => (column.trim_end(), false),
+            Some((column, "desc")) => (column.trim_end(), true),
+            _ => (rest, false),
+        };
+        if column.is_empty() {
+            return Err(format!("'{}' needs a column name", spec));
+        }
+        Ok(Assertion {
+            spec: spec.trim().to_string(),
+            property,
+            column: column.to_string(),
+            descending,
+        })
+    }
+
+    // The first record that breaks the property and the one before it
+    fn first_violation(&self, records: &[Vec<Field>], column: usize) -> Option<(usize, usize)> {
+        let mut previous: Option<(usize, _)> = None;
+        for (idx, record) in records.iter().enumerate() {
+            let val = record.get(column).map_or("", |val| val.as_str());
+            let key = sort_key(val);
+            if let Some((prev_idx, prev_key)) = &previous {
+                let ord = compare_keys(prev_key, &key, self.descending);
+                let broken = match self.property {
+                    Property::Sorted => ord == Ordering::Greater,
+                    Property::Monotonic => ord != Ordering::Less,
+                };
+                if broken {
+                    return Some((*prev_idx, idx));
+                }
+            }
+            previous = Some((idx, key));
+        }
+        None
+    }
+}
+
+// A whitelist from `--allowed`, like `status=OPEN,CLOSED`. A trailing
+// comma allows empty cells.
+#[derive(Debug, Clone)]
+pub struct Allowed {
+    column: String,
+    values: Vec<String>,
+}
+
+impl Allowed {
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let (column, values) = spec
+            .split_once('=')
+            .ok_or_else(|| format!("expected COLUMN=VALUE,VALUE,..., got '{}'", spec))?;
+        if column.trim().is_empty() {
+            return Err(format!("'{}' needs a column name", spec));
+        }
+        Ok(Allowed {
+            column: column.trim().to_string(),
+            values: values.split(',').map(str::to_string).collect(),
+        })
+    }
+
+    fn permits(&self, val: &str) -> bool {
+        self.values.iter().any(|allowed| allowed == val)
+    }
+}
+
+// A condition from `--rule` that every row must meet, like `end >= start`
+#[derive(Debug, Clone)]
+pub struct Rule {
+    text: String,
+    expr: Expr,
+}
+
+impl Rule {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let expr = Expr::parse(text)?;
+        if !expr.is_condition() {
+            return Err(format!(
+                "'{}' must compare values, like `total == price * qty`",
+                text
+            ));
+        }
+        Ok(Rule {
+            text: text.trim().to_string(),
+            expr,
+        })
+    }
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CheckOptions {
+    #[arg(long = "assert", value_name = "CHECK", value_parser = Assertion::parse, conflicts_with = "report")]
+    pub assertions: Vec<Assertion>,
+
+    #[arg(long, value_name = "COLUMN=VALUES", value_parser = Allowed::parse, conflicts_with = "report")]
+    pub allowed: Vec<Allowed>,
+
+    #[arg(long = "rule", value_name = "EXPR", value_parser = Rule::parse, conflicts_with = "report")]
+    pub rules: Vec<Rule>,
+}
+
+impl CheckOptions {
+    pub fn is_empty(&self) -> bool {
+        self.assertions.is_empty() && self.allowed.is_empty() && self.rules.is_empty()
+    }
+}
+
+// Distinct offending values named in a whitelist's summary line, and rows
+// named in a rule's
+const MAX_LISTED: usize = 5;
+
+fn listed<T>(items: impl ExactSizeIterator<Item = T>, show: impl Fn(T) -> String) -> String {
+    let more = if items.len() > MAX_LISTED {
+        ", ..."
+    } else {
+        ""
+    };
+    let shown: Vec<String> = items.take(MAX_LISTED).map(show).collect();
+    format!("{}{}", shown.join(", "), more)
+}
+
+fn row_count(rows: usize) -> String {
+    match rows {
+        1 => "1 row".to_string(),
+        _ => format!("{} rows", rows),
+    }
+}
+
+// Runs every check, printing the rows that break a whitelist or a rule
+// with the offending cells highlighted, then one line per check. Row
+// numbers match `--show-row-numbers`.
+pub fn run(
+    checks: &CheckOptions,
+    headers: Option<&[String]>,
+    records: &[Vec<Field>],
+    aliases: &Aliases,
+    scheme: &ColorScheme,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let styled = styled();
+    let mark = |text: &str, hex: &str| {
+        if styled {
+            paint(text, ColorScheme::hex_to_color(hex))
+        } else {
+            text.to_string()
+        }
+    };
+    let ok = mark("ok  ", &scheme.diff.added);
+    let fail = mark("FAIL", &scheme.diff.removed);
+    let mut lines = Vec::new();
+    let mut failed = 0;
+    let mut index_of = |name: &str| column_index(headers, aliases.display(name));
+
+    for assertion in &checks.assertions {
+        let column = index_of(&assertion.column)?;
+        match assertion.first_violation(records, column) {
+            None => lines.push(format!("{} {}", ok, assertion.spec)),
+            Some((before, after)) => {
+                failed += 1;
+                let val = |idx: usize| records[idx].get(column).map_or("", |val| val.as_str());
+                lines.push(format!(
+                    "{} {}: row {} has '{}' after '{}' in row {}",
+                    fail,
+                    assertion.spec,
+                    after + 1,
+                    val(after),
+                    val(before),
+                    before + 1
+                ));
+            }
+        }
+    }
+
+    let whitelisted: Vec<(usize, &Allowed)> = checks
+        .allowed
+        .iter()
+        .map(|allowed| Ok((index_of(&allowed.column)?, allowed)))
+        .collect::<Result<_, Box<dyn std::error::Error>>>()?;
+    let mut rules: Vec<(Expr, Vec<usize>)> = Vec::new();
+    for rule in &checks.rules {
+        let mut expr = rule.expr.clone();
+        expr.resolve(&mut index_of)?;
+        expr.prepare(records);
+        let columns = expr.columns();
+        rules.push((expr, columns));
+    }
+
+    let mut offending: Vec<HashMap<&str, usize>> = vec![HashMap::new(); whitelisted.len()];
+    let mut breaking: Vec<Vec<usize>> = vec![Vec::new(); rules.len()];
+    let removed = ColorScheme::hex_to_color(&scheme.diff.removed);
+    let mut table = new_table();
+    table.load_preset(UTF8_FULL);
+    let mut header = vec![Cell::new("row").fg(scheme.header_color())];
+    header.extend(
+        column_names(headers, records)
+            .iter()
+            .map(|name| Cell::new(name).fg(scheme.header_color())),
+    );
+    table.set_header(header);
+
+    for (idx, record) in records.iter().enumerate() {
+        let mut bad = vec![false; record.len()];
+        let mut flag = |column: usize| {
+            if let Some(flag) = bad.get_mut(column) {
+                *flag = true;
+            }
+        };
+        for ((column, allowed), counts) in whitelisted.iter().zip(&mut offending) {
+            let val = record.get(*column).map_or("", |val| val.as_str());
+            if !allowed.permits(val) {
+                *counts.entry(val).or_insert(0) += 1;
+                flag(*column);
+            }
+        }
+        for ((expr, columns), rows) in rules.iter().zip(&mut breaking) {
+            // Rows missing one of the values are skipped
+            if matches!(expr.eval(record), Value::Bool(false) | Value::Invalid) {
+                rows.push(idx);
+                columns.iter().for_each(|&column| flag(column));
+            }
+        }
+        if !bad.contains(&true) {
+            continue;
+        }
+        let mut row = vec![Cell::new(idx + 1).fg(scheme.header_color())];
+        row.extend(record.iter().zip(&bad).map(|(val, &bad)| {
+            if bad {
+                Cell::new(val).fg(removed).add_attribute(Attribute::Bold)
+            } else {
+                Cell::new(val).fg(scheme.cell_color(&detect_data_type_cached(val)))
+            }
+        }));
+        table.add_row(row);
+    }
+    if !table.is_empty() {
+        println!("{}", table);
+    }
+
+    for ((_, allowed), counts) in whitelisted.iter().zip(offending) {
+        let spec = format!("{}={}", allowed.column, allowed.values.join(","));
+        if counts.is_empty() {
+            lines.push(format!("{} {}", ok, spec));
+            continue;
+        }
+        failed += 1;
+        let mut counts: Vec<(&str, usize)> = counts.into_iter().collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+        let rows: usize = counts.iter().map(|(_, count)| count).sum();
+        let values = listed(counts.iter(), |(val, count)| {
+            let val = if val.is_empty() { "(empty)" } else { val };
+            format!("'{}' x{}", val, count)
+        });
+        lines.push(format!(
+            "{} {}: {} with other values ({})",
+            fail,
+            spec,
+            row_count(rows),
+            values
+        ));
+    }
+
+    for (rule, rows) in checks.rules.iter().zip(breaking) {
+        if rows.is_empty() {
+            lines.push(format!("{} {}", ok, rule.text));
+            continue;
+        }
+        failed += 1;
+        let numbers = listed(rows.iter(), |idx| (idx + 1).to_string());
+        let verb = if rows.len() == 1 { "breaks" } else { "break" };
+        lines.push(format!(
+            "{} {}: {} {} it ({})",
+            fail,
+            rule.text,
+            row_count(rows.len()),
+            verb,
+            numbers
+        ));
+    }
+
+    for line in lines {
+        println!("{}", line);
+    }
+    let total = checks.assertions.len() + checks.allowed.len() + checks.rules.len();
+    if failed > 0 {
+        return Err(format!("{} of {} checks failed", failed, total).into());
+    }
+    Ok(())
+}
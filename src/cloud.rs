@@ -0,0 +1,134 @@
+use crate::remote::Range;
+use aws_sdk_s3::error::DisplayErrorContext;
+use aws_sdk_s3::primitives::ByteStream;
+use bytes::Bytes;
+use google_cloud_storage::client::Storage;
+use google_cloud_storage::model_ext::ReadRange;
+use google_cloud_storage::read_object::ReadObjectResponse;
+use std::io::{self, Read};
+use tokio::runtime::Runtime;
+
+// A client for S3 or Google Cloud Storage, with a runtime of its own for
+// the SDK's requests. Credentials, regions and endpoints are found the
+// way the SDKs always look for them: for S3 the environment (with
+// AWS_PROFILE and AWS_ENDPOINT_URL), `~/.aws/config` and instance roles,
+// for Cloud Storage the application default credentials.
+pub struct Store {
+    runtime: Runtime,
+    client: Client,
+}
+
+enum Client {
+    S3(aws_sdk_s3::Client),
+    Gcs(Box<Storage>),
+}
+
+// The body of an object, read as it comes in
+pub struct Object<'a> {
+    runtime: &'a Runtime,
+    body: Body,
+    // What is left of the chunk that came in last
+    chunk: Bytes,
+    // The size of the whole object, not only of the range read
+    pub size: u64,
+}
+
+enum Body {
+    S3(ByteStream),
+    Gcs(ReadObjectResponse),
+}
+
+fn runtime() -> io::Result<Runtime> {
+    tokio::runtime::Builder::new_current_thread().enable_all().build()
+}
+
+impl Store {
+    pub fn s3() -> io::Result<Self> {
+        let runtime = runtime()?;
+        let config = runtime.block_on(aws_config::load_defaults(aws_config::BehaviorVersion::latest()));
+        let mut s3 = aws_sdk_s3::config::Builder::from(&config);
+        // The S3-compatible stores AWS_ENDPOINT_URL points at mostly take
+        // the bucket in the path rather than in the host name
+        if config.endpoint_url().is_some() {
+            s3 = s3.force_path_style(true);
+        }
+        let client = Client::S3(aws_sdk_s3::Client::from_conf(s3.build()));
+        Ok(Store { runtime, client })
+    }
+
+    pub fn gcs() -> io::Result<Self> {
+        let runtime = runtime()?;
+        let storage = runtime
+            .block_on(Storage::builder().build())
+            .map_err(|e| io::Error::other(format!("no Cloud Storage client: {}", e)))?;
+        Ok(Store {
+            runtime,
+            client: Client::Gcs(Box::new(storage)),
+        })
+    }
+
+    pub fn read(&self, bucket: &str, object: &str, range: Range) -> io::Result<Object<'_>> {
+        let (body, size) = match &self.client {
+            Client::S3(client) => {
+                let range = match range {
+                    Range::All => None,
+                    Range::Head(bytes) => Some(format!("bytes=0-{}", bytes.saturating_sub(1))),
+                    Range::Tail(bytes) => Some(format!("bytes=-{}", bytes)),
+                };
+                let request = client.get_object().bucket(bucket).key(object).set_range(range);
+                let output = self
+                    .runtime
+                    .block_on(request.send())
+                    .map_err(|e| io::Error::other(DisplayErrorContext(e).to_string()))?;
+                // `bytes FIRST-LAST/SIZE` for a range
+                let size = output
+                    .content_range()
+                    .and_then(|range| range.rsplit_once('/')?.1.parse().ok())
+                    .or_else(|| output.content_length().map(|len| len as u64))
+                    .unwrap_or(0);
+                (Body::S3(output.body), size)
+            }
+            Client::Gcs(storage) => {
+                let range = match range {
+                    Range::All => ReadRange::all(),
+                    Range::Head(bytes) => ReadRange::head(bytes),
+                    Range::Tail(bytes) => ReadRange::tail(bytes),
+                };
+                let request = storage
+                    .read_object(format!("projects/_/buckets/{}", bucket), object)
+                    .set_read_range(range);
+                let response = self.runtime.block_on(request.send()).map_err(io::Error::other)?;
+                let size = response.object().size as u64;
+                (Body::Gcs(response), size)
+            }
+        };
+        Ok(Object {
+            runtime: &self.runtime,
+            body,
+            chunk: Bytes::new(),
+            size,
+        })
+    }
+}
+
+impl Read for Object<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.chunk.is_empty() {
+            let next = match &mut self.body {
+                Body::S3(body) => self.runtime.block_on(body.try_next()).map_err(io::Error::other)?,
+                Body::Gcs(response) => self
+                    .runtime
+                    .block_on(response.next())
+                    .transpose()
+                    .map_err(io::Error::other)?,
+            };
+            match next {
+                Some(chunk) => self.chunk = chunk,
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len());
+        buf[..len].copy_from_slice(&self.chunk.split_to(len));
+        Ok(len)
+    }
+}
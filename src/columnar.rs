@@ -0,0 +1,89 @@
+use crate::store::Interner;
+use crate::CsvData;
+use bytes::Bytes;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::Field;
+
+// Days since 1970-01-01 to (year, month, day), after Howard Hinnant's
+// `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn format_date(days: i64) -> String {
+    let (year, month, day) = civil_from_days(days);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+// `units` per second, e.g. 1000 for milliseconds
+fn format_timestamp(value: i64, units: i64) -> String {
+    let seconds = value.div_euclid(units);
+    let fraction = value.rem_euclid(units);
+    let time = seconds.rem_euclid(86_400);
+    let mut text = format!(
+        "{} {:02}:{:02}:{:02}",
+        format_date(seconds.div_euclid(86_400)),
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    );
+    if fraction != 0 {
+        let digits = units.ilog10() as usize;
+        text.push_str(&format!(".{:0width$}", fraction, width = digits));
+    }
+    text
+}
+
+// Every value is written the way type detection reads it back, so the
+// schema's ints, floats, booleans, dates and timestamps get the colors of
+// the matching `DataType`
+fn field_text(field: &Field) -> String {
+    match field {
+        Field::Null => String::new(),
+        Field::Str(s) => s.clone(),
+        Field::Bytes(bytes) => match std::str::from_utf8(bytes.data()) {
+            Ok(s) => s.to_string(),
+            Err(_) => bytes.data().iter().map(|b| format!("{:02x}", b)).collect(),
+        },
+        Field::Float(f) => f.to_string(),
+        Field::Double(f) => f.to_string(),
+        Field::Date(days) => format_date(i64::from(*days)),
+        Field::TimestampMillis(ms) => format_timestamp(*ms, 1_000),
+        Field::TimestampMicros(us) => format_timestamp(*us, 1_000_000),
+        other => other.to_string(),
+    }
+}
+
+pub fn read_parquet(bytes: Vec<u8>) -> Result<CsvData, Box<dyn std::error::Error>> {
+    let reader = SerializedFileReader::new(Bytes::from(bytes))?;
+    let headers = reader
+        .metadata()
+        .file_metadata()
+        .schema_descr()
+        .root_schema()
+        .get_fields()
+        .iter()
+        .map(|field| field.name().to_string())
+        .collect();
+
+    let mut records = Vec::new();
+    let mut interner = Interner::default();
+    for row in reader.get_row_iter(None)? {
+        let row = row?;
+        let texts: Vec<String> = row
+            .get_column_iter()
+            .map(|(_, field)| field_text(field))
+            .collect();
+        records.push(interner.record(texts));
+    }
+    Ok((Some(headers), records))
+}
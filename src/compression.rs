@@ -0,0 +1,56 @@
+use crate::source::Source;
+use bzip2::read::MultiBzDecoder;
+use flate2::read::MultiGzDecoder;
+use std::io::{self, Read};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    fn from_magic(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Some(Compression::Gzip)
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Some(Compression::Zstd)
+        } else if bytes.starts_with(b"BZh") {
+            Some(Compression::Bzip2)
+        } else {
+            None
+        }
+    }
+
+    fn from_extension(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            "bz2" => Some(Compression::Bzip2),
+            _ => None,
+        }
+    }
+
+    // The magic bytes win, so a misnamed file still opens
+    pub fn detect(bytes: &[u8], path: Option<&str>) -> Option<Self> {
+        Self::from_magic(bytes).or_else(|| path.and_then(Self::from_extension))
+    }
+}
+
+pub fn decompress(bytes: Source, path: Option<&str>) -> io::Result<Source> {
+    let Some(compression) = Compression::detect(&bytes, path) else {
+        return Ok(bytes);
+    };
+
+    let mut output = Vec::new();
+    // Logs are often appended to as several concatenated streams
+    match compression {
+        Compression::Gzip => MultiGzDecoder::new(&bytes[..]).read_to_end(&mut output)?,
+        Compression::Zstd => zstd::stream::read::Decoder::new(&bytes[..])?.read_to_end(&mut output)?,
+        Compression::Bzip2 => MultiBzDecoder::new(&bytes[..]).read_to_end(&mut output)?,
+    };
+    Ok(Source::Owned(output))
+}
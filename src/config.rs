@@ -1,17 +1,226 @@
+use crate::themes;
 use serde::Deserialize;
-use std::fs;
-use std::path::Path;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::{env, fs};
 
 type HexColor = String;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct ColorScheme {
     pub data_types: DataTypeColors,
     pub header: HexColor,
     pub pager: Option<PagerConfig>,
+    #[serde(default)]
+    pub column_groups: Option<Vec<ColumnGroupConfig>>,
+    #[serde(default)]
+    pub diff: DiffColors,
+    #[serde(default = "default_status_bar")]
+    pub status_bar: HexColor,
+    // What HTML and xlsx output draw the table on, which the other colors
+    // are picked to be read on
+    #[serde(default = "default_background")]
+    pub background: HexColor,
+    #[serde(default)]
+    pub rules: Vec<FormatRule>,
+    #[serde(default)]
+    pub truncate: TruncateConfig,
+    #[serde(default)]
+    pub custom_types: Vec<CustomType>,
+    #[serde(default)]
+    pub nulls: NullConfig,
+    #[serde(default)]
+    pub booleans: BooleanConfig,
+    #[serde(default)]
+    pub numbers: NumberConfig,
+    #[serde(default)]
+    pub type_icons: TypeIcons,
 }
 
-#[derive(Debug, Deserialize)]
+fn default_status_bar() -> HexColor {
+    "#45475A".to_string()
+}
+
+fn default_background() -> HexColor {
+    "#1E1E2E".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ColumnGroupConfig {
+    pub label: String,
+    pub columns: Vec<String>,
+}
+
+// A type of value of its own, such as `{ name = "sku", pattern =
+// "^SKU-\\d+$", color = "#FF00FF" }`, recognized before the built-in ones
+#[derive(Debug, Clone, Deserialize)]
+pub struct CustomType {
+    pub name: String,
+    pub pattern: String,
+    pub color: HexColor,
+}
+
+// How the files read write their numbers: the decimal mark, and what
+// goes between groups of thousands, if anything
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct NumberConfig {
+    pub decimal: char,
+    pub thousands: Option<char>,
+}
+
+impl Default for NumberConfig {
+    fn default() -> Self {
+        NumberConfig {
+            decimal: '.',
+            thousands: None,
+        }
+    }
+}
+
+impl NumberConfig {
+    // A `--number-format` sample such as `1,234.56`, `1.234,56`, `1 234`
+    // or `0,5`. Of two marks the last is the decimal one. A single mark is
+    // the decimal one, unless it repeats or is a space; one followed by
+    // three digits could be either and needs a sample with decimals.
+    pub fn from_sample(sample: &str) -> Result<Self, String> {
+        let sample = sample.trim();
+        let invalid = || {
+            format!(
+                "'{}' is not a number format, expected a sample such as 1,234.56, 1.234,56 or 1 234",
+                sample
+            )
+        };
+        let marks: Vec<char> = sample.chars().filter(|c| !c.is_ascii_digit()).collect();
+        if !sample.starts_with(|c: char| c.is_ascii_digit())
+            || marks.iter().any(|&c| c.is_alphanumeric() || matches!(c, '-' | '+'))
+        {
+            return Err(invalid());
+        }
+        let Some(&last) = marks.last() else {
+            return Ok(NumberConfig::default());
+        };
+        let (groups, decimal) = match marks.iter().position(|&c| c == last) {
+            Some(first) if first == marks.len() - 1 && first > 0 => (&marks[..first], Some(last)),
+            _ => (&marks[..], None),
+        };
+        if groups.iter().any(|&c| c != groups[0]) {
+            return Err(invalid());
+        }
+        match decimal {
+            Some(decimal) => Ok(NumberConfig {
+                decimal,
+                thousands: Some(groups[0]),
+            }),
+            None if groups.len() > 1 || last.is_whitespace() => Ok(NumberConfig {
+                decimal: if last == '.' { ',' } else { '.' },
+                thousands: Some(last),
+            }),
+            None if sample.rsplit(last).next().is_some_and(|digits| digits.len() == 3) => Err(format!(
+                "in '{}' the '{}' could be a decimal or a thousands mark, add decimals (1{}234{}5)",
+                sample,
+                last,
+                last,
+                if last == '.' { ',' } else { '.' }
+            )),
+            None => Ok(NumberConfig {
+                decimal: last,
+                thousands: None,
+            }),
+        }
+    }
+}
+
+// Values that stand for a missing one, like `NA` or `null`, and what is
+// shown in place of every missing value
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NullConfig {
+    pub tokens: Vec<String>,
+    pub placeholder: Option<String>,
+}
+
+// The spellings of true and false, in any case
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BooleanConfig {
+    #[serde(rename = "true")]
+    pub truthy: Vec<String>,
+    #[serde(rename = "false")]
+    pub falsy: Vec<String>,
+}
+
+impl Default for BooleanConfig {
+    fn default() -> Self {
+        let words = |words: &[&str]| words.iter().map(|word| word.to_string()).collect();
+        BooleanConfig {
+            truthy: words(&["true", "yes", "y"]),
+            falsy: words(&["false", "no", "n"]),
+        }
+    }
+}
+
+// How long values are cut to fit `max_width`, for all columns or by name
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct TruncateConfig {
+    pub max_width: Option<usize>,
+    pub mode: TruncateMode,
+    pub columns: BTreeMap<String, TruncateMode>,
+}
+
+// Where a value that is too wide loses its characters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TruncateMode {
+    // Keeps the end, for paths and IDs
+    Start,
+    Middle,
+    #[default]
+    End,
+}
+
+impl TruncateMode {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "start" => Some(TruncateMode::Start),
+            "middle" => Some(TruncateMode::Middle),
+            "end" => Some(TruncateMode::End),
+            _ => None,
+        }
+    }
+}
+
+// Styles cells whose value passes every test the rule sets, such as
+// `{ column = "amount", gt = 1000, color = "#FF0000", bold = true }`.
+// Without a column it looks at every column.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct FormatRule {
+    pub column: Option<String>,
+    pub gt: Option<f64>,
+    pub ge: Option<f64>,
+    pub lt: Option<f64>,
+    pub le: Option<f64>,
+    pub eq: Option<RuleValue>,
+    pub ne: Option<RuleValue>,
+    // A regex the cell's text has to match
+    pub matches: Option<String>,
+    pub color: Option<HexColor>,
+    pub bold: bool,
+}
+
+// What `eq` and `ne` compare with: numbers by value, anything else by text
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum RuleValue {
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
 pub struct DataTypeColors {
     pub text: HexColor,
     pub date: HexColor,
@@ -19,59 +228,462 @@ pub struct DataTypeColors {
     pub int_number: HexColor,
     pub boolean: HexColor,
     pub empty: HexColor,
+    // Themes from before currencies were told apart show them as decimals
+    #[serde(default)]
+    pub currency: Option<HexColor>,
+    // And links as text
+    #[serde(default)]
+    pub url: Option<HexColor>,
+    // And email addresses
+    #[serde(default)]
+    pub email: Option<HexColor>,
+    // And UUIDs
+    #[serde(default)]
+    pub uuid: Option<HexColor>,
+    // And IP addresses and CIDR blocks
+    #[serde(default)]
+    pub network: Option<HexColor>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DiffColors {
+    pub added: HexColor,
+    pub removed: HexColor,
+    pub changed: HexColor,
 }
 
-#[derive(Debug, Deserialize)]
+impl Default for DiffColors {
+    fn default() -> Self {
+        DiffColors {
+            added: "#A6E3A1".to_string(),
+            removed: "#F38BA8".to_string(),
+            changed: "#F9E2AF".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(default)]
 pub struct PagerConfig {
     pub scroll_single_line: usize,
     pub scroll_multi_line: usize,
+    #[serde(default)]
+    pub header: HeaderStyle,
+    #[serde(default = "default_compact_header_width")]
+    pub compact_header_width: usize,
+    // Narrow the widest columns so the table fits the terminal's width
+    #[serde(default = "default_fit_columns")]
+    pub fit_columns: bool,
+}
+
+impl Default for PagerConfig {
+    fn default() -> Self {
+        PagerConfig {
+            scroll_single_line: 1,
+            scroll_multi_line: 10,
+            header: HeaderStyle::Full,
+            compact_header_width: default_compact_header_width(),
+            fit_columns: default_fit_columns(),
+        }
+    }
+}
+
+fn default_compact_header_width() -> usize {
+    6
+}
+
+fn default_fit_columns() -> bool {
+    true
+}
+
+// The glyphs put before each header name to tell its column's type
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TypeIcons {
+    #[default]
+    Off,
+    Unicode,
+    // For terminals and fonts without the symbols
+    Ascii,
+}
+
+impl TypeIcons {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(TypeIcons::Off),
+            "unicode" => Some(TypeIcons::Unicode),
+            "ascii" => Some(TypeIcons::Ascii),
+            _ => None,
+        }
+    }
+}
+
+// How the pager's table layout shows the header row
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeaderStyle {
+    #[default]
+    Full,
+    // Each name cut to its first `compact_header_width` characters
+    Compact,
+    Hidden,
+}
+
+impl HeaderStyle {
+    // The style `H` switches to
+    pub fn next(self) -> Self {
+        match self {
+            HeaderStyle::Full => HeaderStyle::Compact,
+            HeaderStyle::Compact => HeaderStyle::Hidden,
+            HeaderStyle::Hidden => HeaderStyle::Full,
+        }
+    }
+
+    // A header name as this style shows it
+    pub fn name(self, name: &str, compact_width: usize) -> String {
+        match self {
+            HeaderStyle::Compact => name.chars().take(compact_width.max(1)).collect(),
+            _ => name.to_string(),
+        }
+    }
+}
+
+impl Default for DataTypeColors {
+    fn default() -> Self {
+        DataTypeColors {
+            text: "#BACEDF".to_string(),
+            date: "#FAB387".to_string(),
+            float_number: "#89B4FA".to_string(),
+            int_number: "#A6E3A1".to_string(),
+            boolean: "#F9E2AF".to_string(),
+            empty: "#585B70".to_string(),
+            currency: Some("#94E2D5".to_string()),
+            url: Some("#74C7EC".to_string()),
+            email: Some("#F5C2E7".to_string()),
+            uuid: Some("#6C7086".to_string()),
+            network: Some("#B4BEFE".to_string()),
+        }
+    }
 }
 
 impl Default for ColorScheme {
     fn default() -> Self {
         ColorScheme {
-            data_types: DataTypeColors {
-                text: "#BACEDF".to_string(),
-                date: "#FAB387".to_string(),
-                float_number: "#89B4FA".to_string(),
-                int_number: "#A6E3A1".to_string(),
-                boolean: "#F9E2AF".to_string(),
-                empty: "#585B70".to_string(),
-            },
+            data_types: DataTypeColors::default(),
             header: "#CBB6F7".to_string(),
-            pager: Some(PagerConfig {
-                scroll_single_line: 1,
-                scroll_multi_line: 10,
-            }),
+            pager: Some(PagerConfig::default()),
+            column_groups: None,
+            diff: DiffColors::default(),
+            status_bar: default_status_bar(),
+            background: default_background(),
+            rules: Vec::new(),
+            truncate: TruncateConfig::default(),
+            custom_types: Vec::new(),
+            nulls: NullConfig::default(),
+            booleans: BooleanConfig::default(),
+            numbers: NumberConfig::default(),
+            type_icons: TypeIcons::Off,
+        }
+    }
+}
+
+pub fn is_hex_color(val: &str) -> bool {
+    val.len() == 7 && val.starts_with('#') && val[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl ColorScheme {
+    // Every color a config or theme sets has to be #RRGGBB
+    pub fn check_colors(&self) -> Result<(), String> {
+        let types = &self.data_types;
+        let named = [
+            ("header", Some(&self.header)),
+            ("status_bar", Some(&self.status_bar)),
+            ("background", Some(&self.background)),
+            ("data_types.text", Some(&types.text)),
+            ("data_types.date", Some(&types.date)),
+            ("data_types.float_number", Some(&types.float_number)),
+            ("data_types.int_number", Some(&types.int_number)),
+            ("data_types.boolean", Some(&types.boolean)),
+            ("data_types.empty", Some(&types.empty)),
+            ("data_types.currency", types.currency.as_ref()),
+            ("data_types.url", types.url.as_ref()),
+            ("data_types.email", types.email.as_ref()),
+            ("data_types.uuid", types.uuid.as_ref()),
+            ("data_types.network", types.network.as_ref()),
+            ("diff.added", Some(&self.diff.added)),
+            ("diff.removed", Some(&self.diff.removed)),
+            ("diff.changed", Some(&self.diff.changed)),
+        ];
+        let rules = self.rules.iter().enumerate();
+        let custom_types = self.custom_types.iter().enumerate();
+        let mut colors = named
+            .into_iter()
+            .map(|(key, color)| (key.to_string(), color))
+            .chain(rules.map(|(idx, rule)| (format!("rules[{}].color", idx), rule.color.as_ref())))
+            .chain(custom_types.map(|(idx, custom)| (format!("custom_types[{}].color", idx), Some(&custom.color))));
+        match colors.find(|(_, color)| color.is_some_and(|color| !is_hex_color(color))) {
+            Some((key, Some(color))) => Err(format!("'{}' expects a #RRGGBB color, got '{}'", key, color)),
+            _ => Ok(()),
+        }
+    }
+
+    // Applies a `key=value` override such as `data_types.int_number=#00FF00`.
+    // A leading `color.` is accepted for readability (`color.header=#FFAA00`).
+    pub fn apply_override(&mut self, assignment: &str) -> Result<(), String> {
+        let (key, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| format!("invalid override '{}', expected KEY=VALUE", assignment))?;
+        let key = key.trim();
+        let value = value.trim();
+        let key = key.strip_prefix("color.").unwrap_or(key);
+
+        match key {
+            "truncate.max_width" => {
+                let width = value
+                    .parse()
+                    .map_err(|_| format!("'{}' expects a number, got '{}'", key, value))?;
+                self.truncate.max_width = Some(width);
+                return Ok(());
+            }
+            "truncate.mode" => {
+                self.truncate.mode = TruncateMode::parse(value).ok_or_else(|| {
+                    format!("'{}' expects start, middle or end, got '{}'", key, value)
+                })?;
+                return Ok(());
+            }
+            // Comma-separated, and an empty value clears the list
+            "nulls.tokens" => {
+                self.nulls.tokens = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|token| !token.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                return Ok(());
+            }
+            "booleans.true" | "booleans.false" => {
+                let words = value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|word| !word.is_empty())
+                    .map(str::to_string)
+                    .collect();
+                if key == "booleans.true" {
+                    self.booleans.truthy = words;
+                } else {
+                    self.booleans.falsy = words;
+                }
+                return Ok(());
+            }
+            "numbers.decimal" | "numbers.thousands" => {
+                let mut chars = value.chars();
+                let mark = match (chars.next(), chars.next()) {
+                    (Some(mark), None) => Some(mark),
+                    (None, _) if key == "numbers.thousands" => None,
+                    _ => return Err(format!("'{}' expects a single character, got '{}'", key, value)),
+                };
+                match mark {
+                    Some(mark) if key == "numbers.decimal" => self.numbers.decimal = mark,
+                    thousands => self.numbers.thousands = thousands,
+                }
+                return Ok(());
+            }
+            "type_icons" => {
+                self.type_icons = TypeIcons::parse(value).ok_or_else(|| {
+                    format!("'{}' expects off, unicode or ascii, got '{}'", key, value)
+                })?;
+                return Ok(());
+            }
+            "nulls.placeholder" => {
+                self.nulls.placeholder = (!value.is_empty()).then(|| value.to_string());
+                return Ok(());
+            }
+            _ => {}
         }
+
+        if let Some(field) = key.strip_prefix("pager.") {
+            let pager = self.pager.get_or_insert_with(PagerConfig::default);
+            if field == "header" {
+                pager.header = match value {
+                    "full" => HeaderStyle::Full,
+                    "compact" => HeaderStyle::Compact,
+                    "hidden" => HeaderStyle::Hidden,
+                    _ => {
+                        return Err(format!(
+                            "'{}' expects full, compact or hidden, got '{}'",
+                            key, value
+                        ))
+                    }
+                };
+                return Ok(());
+            }
+            if field == "fit_columns" {
+                pager.fit_columns = value
+                    .parse()
+                    .map_err(|_| format!("'{}' expects true or false, got '{}'", key, value))?;
+                return Ok(());
+            }
+            let number: usize = value
+                .parse()
+                .map_err(|_| format!("'{}' expects a number, got '{}'", key, value))?;
+            match field {
+                "scroll_single_line" => pager.scroll_single_line = number,
+                "scroll_multi_line" => pager.scroll_multi_line = number,
+                "compact_header_width" => pager.compact_header_width = number,
+                _ => return Err(format!("unknown config key '{}'", key)),
+            }
+            return Ok(());
+        }
+
+        if !is_hex_color(value) {
+            return Err(format!("'{}' expects a #RRGGBB color, got '{}'", key, value));
+        }
+        let slot = match key {
+            "header" => &mut self.header,
+            "status_bar" => &mut self.status_bar,
+            "background" => &mut self.background,
+            "data_types.text" => &mut self.data_types.text,
+            "data_types.date" => &mut self.data_types.date,
+            "data_types.float_number" => &mut self.data_types.float_number,
+            "data_types.int_number" => &mut self.data_types.int_number,
+            "data_types.boolean" => &mut self.data_types.boolean,
+            "data_types.empty" => &mut self.data_types.empty,
+            "data_types.currency" => self.data_types.currency.get_or_insert_with(String::new),
+            "data_types.url" => self.data_types.url.get_or_insert_with(String::new),
+            "data_types.email" => self.data_types.email.get_or_insert_with(String::new),
+            "data_types.uuid" => self.data_types.uuid.get_or_insert_with(String::new),
+            "data_types.network" => self.data_types.network.get_or_insert_with(String::new),
+            "diff.added" => &mut self.diff.added,
+            "diff.removed" => &mut self.diff.removed,
+            "diff.changed" => &mut self.diff.changed,
+            _ => return Err(format!("unknown config key '{}'", key)),
+        };
+        *slot = value.to_string();
+        Ok(())
     }
 }
 
 pub fn load_config(config_path: Option<&str>) -> ColorScheme {
+    load_themed_config(config_path, None)
+}
+
+// Like `load_config`, with a built-in theme in place of the one the file
+// names, if any. The colors the file sets still go on top of the theme.
+pub fn load_themed_config(config_path: Option<&str>, theme: Option<&str>) -> ColorScheme {
     let paths = match config_path {
-        Some(path) => vec![path],
-        None => vec!["~/.config/pcsv/config.toml"],
+        Some(path) => vec![PathBuf::from(expand_home(path))],
+        None => config_paths(),
     };
 
     for path in paths {
-        let expanded_path = expand_home(path);
-        if Path::new(&expanded_path).exists() {
-            if let Ok(content) = fs::read_to_string(&expanded_path) {
-                if let Ok(scheme) = toml::from_str::<ColorScheme>(&content) {
-                    return scheme;
+        if path.exists() {
+            if let Ok(content) = fs::read_to_string(&path) {
+                match parse_config(&content, theme) {
+                    Ok(scheme) => return scheme,
+                    Err(e) => eprintln!("warning: ignoring {}: {}", path.display(), e),
                 }
             }
         }
     }
 
-    ColorScheme::default()
+    theme.and_then(themes::theme).unwrap_or_default()
 }
 
-fn expand_home(path: &str) -> String {
-    if path.starts_with("~/") {
-        if let Some(home) = std::env::var_os("HOME") {
-            return format!("{}/{}", home.to_string_lossy(), &path[2..]);
+// Where a config file is looked for without `--config`, first to last:
+// a `.pcsv.toml` in the current directory or the nearest parent that has
+// one, `$XDG_CONFIG_HOME/pcsv/config.toml`, `%APPDATA%\pcsv\config.toml`
+// on Windows, and `~/.config/pcsv/config.toml`
+pub fn config_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Ok(cwd) = env::current_dir() {
+        if let Some(local) = cwd.ancestors().map(|dir| dir.join(".pcsv.toml")).find(|path| path.is_file()) {
+            paths.push(local);
+        }
+    }
+    for dir in user_config_dirs() {
+        let path = dir.join("config.toml");
+        if !paths.contains(&path) {
+            paths.push(path);
         }
     }
-    path.to_string()
+    paths
+}
+
+// The directory of the user's config and themes: the first of the
+// directories config files are looked for in that exists, or else the one
+// that would come first
+pub fn user_config_dir() -> Option<PathBuf> {
+    let dirs = user_config_dirs();
+    dirs.iter().find(|dir| dir.is_dir()).or(dirs.first()).cloned()
+}
+
+fn user_config_dirs() -> Vec<PathBuf> {
+    // Relative paths are ignored, as the XDG spec asks
+    let from_env = |name: &str| {
+        env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+            .map(|path| path.join("pcsv"))
+    };
+    let mut dirs = Vec::new();
+    dirs.extend(from_env("XDG_CONFIG_HOME"));
+    if cfg!(windows) {
+        dirs.extend(from_env("APPDATA"));
+    }
+    dirs.extend(home_dir().map(|home| home.join(".config").join("pcsv")));
+    dirs
+}
+
+// `$HOME`, or `%USERPROFILE%` where there is none, as on Windows
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .or_else(|| env::var_os("USERPROFILE").filter(|home| !home.is_empty()))
+        .map(PathBuf::from)
+}
+
+// A config file on top of its theme, or of pcsv's own colors without
+// one, so it only needs the options it changes
+fn parse_config(content: &str, theme: Option<&str>) -> Result<ColorScheme, String> {
+    let message = |e: toml::de::Error| e.message().trim_end().to_string();
+    let mut table: toml::Table = toml::from_str(content).map_err(message)?;
+    let named = table.remove("theme");
+    let theme = theme.or(named.as_ref().and_then(|name| name.as_str()));
+    let scheme: ColorScheme = match theme.and_then(themes::source) {
+        Some(source) => {
+            let mut merged: toml::Table = toml::from_str(&source)
+                .map_err(|e| format!("theme '{}': {}", theme.unwrap_or_default(), message(e)))?;
+            merge(&mut merged, table);
+            toml::Value::Table(merged).try_into().map_err(message)?
+        }
+        None => toml::Value::Table(table).try_into().map_err(message)?,
+    };
+    scheme.check_colors()?;
+    Ok(scheme)
+}
+
+// Puts the keys of `over` into `base`, going into tables both have
+fn merge(base: &mut toml::Table, over: toml::Table) {
+    for (key, value) in over {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(over)) => merge(base, over),
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+pub(crate) fn expand_home(path: &str) -> String {
+    let rest = match path.strip_prefix('~') {
+        Some("") => "",
+        Some(rest) if rest.starts_with('/') || (cfg!(windows) && rest.starts_with('\\')) => &rest[1..],
+        _ => return path.to_string(),
+    };
+    match home_dir() {
+        Some(home) if rest.is_empty() => home.to_string_lossy().into_owned(),
+        Some(home) => home.join(rest).to_string_lossy().into_owned(),
+        None => path.to_string(),
+    }
 }
@@ -0,0 +1,124 @@
+use crate::input::ReadOptions;
+use crate::store::Interner;
+use crate::{detect_builtin_type, plain_number, CsvData, DataType};
+use rusqlite::types::{Value, ValueRef};
+use rusqlite::{Connection, OpenFlags};
+
+fn value_text(value: ValueRef) -> String {
+    match value {
+        ValueRef::Null => String::new(),
+        ValueRef::Integer(i) => i.to_string(),
+        ValueRef::Real(f) => f.to_string(),
+        ValueRef::Text(text) => String::from_utf8_lossy(text).into_owned(),
+        ValueRef::Blob(blob) => format!("<{} bytes>", blob.len()),
+    }
+}
+
+fn table_names(conn: &Connection) -> rusqlite::Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY name",
+    )?;
+    let names = stmt.query_map([], |row| row.get(0))?;
+    names.collect()
+}
+
+// Runs `--query`, or selects everything from `--table`. A database with
+// a single table needs neither.
+pub fn read_sqlite(
+    path: &str,
+    options: &ReadOptions,
+) -> Result<CsvData, Box<dyn std::error::Error>> {
+    if path == "-" {
+        return Err("SQLite databases can't be read from standard input".into());
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+
+    let sql = match (&options.query, &options.table) {
+        (Some(query), _) => query.clone(),
+        (None, Some(table)) => format!("SELECT * FROM \"{}\"", table.replace('"', "\"\"")),
+        (None, None) => {
+            let names = table_names(&conn)?;
+            match names.as_slice() {
+                [name] => format!("SELECT * FROM \"{}\"", name.replace('"', "\"\"")),
+                [] => return Err(format!("{} has no tables", path).into()),
+                _ => {
+                    return Err(format!(
+                        "{} has several tables, choose one with --table: {}",
+                        path,
+                        names.join(", ")
+                    )
+                    .into())
+                }
+            }
+        }
+    };
+
+    query(&conn, &sql).map_err(|e| format!("{}: {}", path, e).into())
+}
+
+// Runs `--query` over the records of any other file, loaded into an
+// in-memory database as the table `t`. Numbers are stored as numbers so
+// they sum and sort as such, and empty cells as NULL.
+pub fn query_records(data: CsvData, sql: &str) -> Result<CsvData, Box<dyn std::error::Error>> {
+    let (headers, records) = data;
+    let headers = headers.unwrap_or_else(|| {
+        let width = records.iter().map(|record| record.len()).max().unwrap_or(0);
+        (1..=width).map(|idx| format!("column{}", idx)).collect()
+    });
+    let mut conn = Connection::open_in_memory()?;
+    let columns: Vec<String> = headers
+        .iter()
+        .map(|name| format!("\"{}\"", name.replace('"', "\"\"")))
+        .collect();
+    conn.execute(&format!("CREATE TABLE t ({})", columns.join(", ")), [])
+        .map_err(|e| format!("can't load the file as table t: {}", e))?;
+    let insert = format!(
+        "INSERT INTO t VALUES ({})",
+        vec!["?"; headers.len()].join(", ")
+    );
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(&insert)?;
+        for record in &records {
+            let values = (0..headers.len()).map(|idx| {
+                record.get(idx).map_or(Value::Null, |val| sql_value(val))
+            });
+            stmt.execute(rusqlite::params_from_iter(values))?;
+        }
+    }
+    tx.commit()?;
+    query(&conn, sql).map_err(|e| format!("--query: {}", e).into())
+}
+
+fn sql_value(val: &str) -> Value {
+    match detect_builtin_type(val) {
+        DataType::Empty => Value::Null,
+        DataType::IntNumber => plain_number(val)
+            .parse()
+            .map_or_else(|_| Value::Text(val.to_string()), Value::Integer),
+        DataType::FloatNumber => plain_number(val)
+            .parse()
+            .map_or_else(|_| Value::Text(val.to_string()), Value::Real),
+        _ => Value::Text(val.to_string()),
+    }
+}
+
+fn query(conn: &Connection, sql: &str) -> rusqlite::Result<CsvData> {
+    let mut stmt = conn.prepare(sql)?;
+    let headers: Vec<String> = stmt
+        .column_names()
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    let width = headers.len();
+    let mut rows = stmt.query([])?;
+    let mut records = Vec::new();
+    let mut interner = Interner::default();
+    while let Some(row) = rows.next()? {
+        let record = (0..width)
+            .map(|idx| row.get_ref(idx).map(value_text))
+            .collect::<rusqlite::Result<Vec<String>>>()?;
+        records.push(interner.record(record));
+    }
+    Ok((Some(headers), records))
+}
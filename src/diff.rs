@@ -0,0 +1,355 @@
+use crate::config::ColorScheme;
+use crate::input::ReadOptions;
+use crate::stats::column_names;
+use crate::store::Field;
+use crate::{detect_data_type_cached, new_table, paint, plain_number, read_csv_data, styled};
+use clap::Args;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, Color};
+use std::collections::HashMap;
+
+#[derive(Args)]
+pub struct DiffArgs {
+    pub old: String,
+
+    pub new: String,
+
+    #[arg(short, long)]
+    pub key: Option<String>,
+
+    #[arg(long)]
+    pub all: bool,
+
+    #[arg(long, value_name = "COLUMNS", value_delimiter = ',')]
+    pub ignore_cols: Vec<String>,
+
+    #[arg(long, value_name = "DELTA")]
+    pub numeric_tolerance: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+// Words, runs of whitespace and single punctuation characters, so a
+// changed day in `2024-01-15` shows up as just `15`
+fn tokens(val: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = val.char_indices().peekable();
+    while let Some((idx, c)) = chars.next() {
+        let same_class = |next: char| {
+            (c.is_alphanumeric() && next.is_alphanumeric())
+                || (c.is_whitespace() && next.is_whitespace())
+        };
+        match chars.peek() {
+            Some(&(_, next)) if same_class(next) => {}
+            _ => {
+                let end = idx + c.len_utf8();
+                tokens.push(&val[start..end]);
+                start = end;
+            }
+        }
+    }
+    tokens
+}
+
+// Cells longer than this (in tokens, multiplied) are diffed as one block
+const MAX_DIFF_CELLS: usize = 250_000;
+
+// A token-level longest common subsequence diff
+fn diff_segments<'a>(old: &'a str, new: &'a str) -> Vec<Segment<'a>> {
+    let a = tokens(old);
+    let b = tokens(new);
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (mid_a, mid_b) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut segments: Vec<Segment> = a[..prefix].iter().map(|t| Segment::Same(t)).collect();
+    if mid_a.len() * mid_b.len() > MAX_DIFF_CELLS {
+        segments.extend(mid_a.iter().map(|t| Segment::Removed(t)));
+        segments.extend(mid_b.iter().map(|t| Segment::Added(t)));
+    } else {
+        // lengths[i][j] is the LCS length of mid_a[i..] and mid_b[j..]
+        let mut lengths = vec![vec![0usize; mid_b.len() + 1]; mid_a.len() + 1];
+        for i in (0..mid_a.len()).rev() {
+            for j in (0..mid_b.len()).rev() {
+                lengths[i][j] = if mid_a[i] == mid_b[j] {
+                    lengths[i + 1][j + 1] + 1
+                } else {
+                    lengths[i + 1][j].max(lengths[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < mid_a.len() || j < mid_b.len() {
+            if i < mid_a.len() && j < mid_b.len() && mid_a[i] == mid_b[j] {
+                segments.push(Segment::Same(mid_a[i]));
+                i += 1;
+                j += 1;
+            } else if i < mid_a.len()
+                && (j == mid_b.len() || lengths[i + 1][j] >= lengths[i][j + 1])
+            {
+                segments.push(Segment::Removed(mid_a[i]));
+                i += 1;
+            } else {
+                segments.push(Segment::Added(mid_b[j]));
+                j += 1;
+            }
+        }
+    }
+    segments.extend(a[a.len() - suffix..].iter().map(|t| Segment::Same(t)));
+    segments
+}
+
+// Marks what changed inside a cell: removed text struck through in the
+// removed color, added text in bold in the added color. Without styling,
+// git's word-diff markers `[-old-]{+new+}` are used instead.
+fn inline_diff(old: &str, new: &str, scheme: &ColorScheme, styled: bool) -> String {
+    let removed = ColorScheme::hex_to_color(&scheme.diff.removed);
+    let added = ColorScheme::hex_to_color(&scheme.diff.added);
+    let mut out = String::new();
+    let mut segments = diff_segments(old, new).into_iter().peekable();
+    while let Some(segment) = segments.next() {
+        match segment {
+            Segment::Same(text) => out.push_str(text),
+            Segment::Removed(text) | Segment::Added(text) => {
+                // Merge runs of the same kind into one marked span
+                let mut span = text.to_string();
+                let is_removed = matches!(segment, Segment::Removed(_));
+                while let Some(next) = segments.peek() {
+                    match next {
+                        Segment::Removed(t) if is_removed => span.push_str(t),
+                        Segment::Added(t) if !is_removed => span.push_str(t),
+                        _ => break,
+                    }
+                    segments.next();
+                }
+                out.push_str(&match (styled, is_removed) {
+                    (true, true) => format!("\x1b[9m{}\x1b[29m", paint(&span, removed)),
+                    (true, false) => format!("\x1b[1m{}\x1b[22m", paint(&span, added)),
+                    (false, true) => format!("[-{}-]", span),
+                    (false, false) => format!("{{+{}+}}", span),
+                });
+            }
+        }
+    }
+    out
+}
+
+enum RowChange<'a> {
+    Same(&'a [Field]),
+    Changed(&'a [Field], &'a [Field]),
+    Added(&'a [Field]),
+    Removed(&'a [Field]),
+}
+
+type RowPair<'a> = (Option<&'a [Field]>, Option<&'a [Field]>);
+
+// Pairs up old and new rows, by key when given, otherwise by position.
+// Rows only in the old file come last.
+fn pair_rows<'a>(
+    old: &'a [Vec<Field>],
+    new: &'a [Vec<Field>],
+    key: Option<(usize, usize)>,
+) -> Vec<RowPair<'a>> {
+    let Some((old_key, new_key)) = key else {
+        return (0..old.len().max(new.len()))
+            .map(|idx| {
+                (
+                    old.get(idx).map(|r| r.as_slice()),
+                    new.get(idx).map(|r| r.as_slice()),
+                )
+            })
+            .collect();
+    };
+
+    let key_of = |record: &'a Vec<Field>, idx: usize| record.get(idx).map_or("", |v| v.as_str());
+    let mut by_key: HashMap<&str, &[Field]> = HashMap::new();
+    for record in old {
+        by_key.entry(key_of(record, old_key)).or_insert(record);
+    }
+    let mut pairs = Vec::new();
+    for record in new {
+        pairs.push((
+            by_key.remove(key_of(record, new_key)),
+            Some(record.as_slice()),
+        ));
+    }
+    // Keep the old file's order for rows that are gone
+    for record in old {
+        if by_key.remove(key_of(record, old_key)).is_some() {
+            pairs.push((Some(record.as_slice()), None));
+        }
+    }
+    pairs
+}
+
+// Numbers closer than the tolerance count as equal
+fn cells_equal(a: &str, b: &str, tolerance: Option<f64>) -> bool {
+    if a == b {
+        return true;
+    }
+    match (tolerance, plain_number(a).trim().parse::<f64>(), plain_number(b).trim().parse::<f64>()) {
+        (Some(tolerance), Ok(x), Ok(y)) => (x - y).abs() <= tolerance,
+        _ => false,
+    }
+}
+
+fn lookup(record: &[Field], idx: Option<usize>) -> &str {
+    idx.and_then(|idx| record.get(idx))
+        .map_or("", |v| v.as_str())
+}
+
+pub fn run(
+    args: &DiffArgs,
+    options: &ReadOptions,
+    scheme: &ColorScheme,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (old_headers, old_records) = read_csv_data(&args.old, options)?;
+    let (new_headers, new_records) = read_csv_data(&args.new, options)?;
+    let old_names = column_names(old_headers.as_deref(), &old_records);
+    let new_names = column_names(new_headers.as_deref(), &new_records);
+
+    if args
+        .numeric_tolerance
+        .is_some_and(|tolerance| tolerance.is_nan() || tolerance < 0.0)
+    {
+        return Err("--numeric-tolerance must not be negative".into());
+    }
+    for name in &args.ignore_cols {
+        if !old_names.contains(name) && !new_names.contains(name) {
+            return Err(format!("column '{}' not found", name).into());
+        }
+    }
+
+    // Columns of both files, matched by name, in the new file's order
+    let mut columns: Vec<&str> = new_names.iter().map(|n| n.as_str()).collect();
+    for name in &old_names {
+        if !columns.contains(&name.as_str()) {
+            columns.push(name);
+        }
+    }
+    columns.retain(|name| !args.ignore_cols.iter().any(|ignored| ignored == name));
+    let old_idx: Vec<Option<usize>> = columns
+        .iter()
+        .map(|name| old_names.iter().position(|n| n == name))
+        .collect();
+    let new_idx: Vec<Option<usize>> = columns
+        .iter()
+        .map(|name| new_names.iter().position(|n| n == name))
+        .collect();
+
+    let key = match &args.key {
+        Some(name) => {
+            let column = columns.iter().position(|col| col == name);
+            match column.map(|idx| (old_idx[idx], new_idx[idx])) {
+                Some((Some(old), Some(new))) => Some((old, new)),
+                _ => return Err(format!("key column '{}' must exist in both files", name).into()),
+            }
+        }
+        None => None,
+    };
+
+    let changes: Vec<RowChange> = pair_rows(&old_records, &new_records, key)
+        .into_iter()
+        .filter_map(|pair| match pair {
+            (Some(old), Some(new)) => {
+                let differs = (0..columns.len()).any(|col| {
+                    !cells_equal(
+                        lookup(old, old_idx[col]),
+                        lookup(new, new_idx[col]),
+                        args.numeric_tolerance,
+                    )
+                });
+                Some(if differs {
+                    RowChange::Changed(old, new)
+                } else {
+                    RowChange::Same(new)
+                })
+            }
+            (None, Some(new)) => Some(RowChange::Added(new)),
+            (Some(old), None) => Some(RowChange::Removed(old)),
+            (None, None) => None,
+        })
+        .collect();
+
+    let styled = styled();
+    let typed = |val: &str| Cell::new(val).fg(scheme.cell_color(&detect_data_type_cached(val)));
+    // Whole rows that were added or removed are shown in a single color
+    let solid = |record: &[Field], indices: &[Option<usize>], color: Color| -> Vec<Cell> {
+        indices
+            .iter()
+            .map(|&idx| Cell::new(lookup(record, idx)).fg(color))
+            .collect()
+    };
+
+    let mut table = new_table();
+    table.load_preset(UTF8_FULL);
+    let mut header = vec![Cell::new("")];
+    header.extend(
+        columns
+            .iter()
+            .map(|name| Cell::new(name).fg(scheme.header_color())),
+    );
+    table.set_header(header);
+
+    let added_color = ColorScheme::hex_to_color(&scheme.diff.added);
+    let removed_color = ColorScheme::hex_to_color(&scheme.diff.removed);
+    let changed_color = ColorScheme::hex_to_color(&scheme.diff.changed);
+    let (mut changed, mut added, mut removed) = (0, 0, 0);
+    for change in &changes {
+        let (marker, color, cells) = match change {
+            RowChange::Same(_) if !args.all => continue,
+            RowChange::Same(record) => {
+                let cells = new_idx
+                    .iter()
+                    .map(|&idx| typed(lookup(record, idx)))
+                    .collect();
+                (" ", scheme.header_color(), cells)
+            }
+            RowChange::Changed(old, new) => {
+                changed += 1;
+                let cells = (0..columns.len())
+                    .map(|col| {
+                        let (before, after) =
+                            (lookup(old, old_idx[col]), lookup(new, new_idx[col]));
+                        if cells_equal(before, after, args.numeric_tolerance) {
+                            typed(after)
+                        } else {
+                            Cell::new(inline_diff(before, after, scheme, styled))
+                        }
+                    })
+                    .collect();
+                ("~", changed_color, cells)
+            }
+            RowChange::Added(record) => {
+                added += 1;
+                ("+", added_color, solid(record, &new_idx, added_color))
+            }
+            RowChange::Removed(record) => {
+                removed += 1;
+                ("-", removed_color, solid(record, &old_idx, removed_color))
+            }
+        };
+        let mut row = vec![Cell::new(marker).fg(color)];
+        row.extend(cells);
+        table.add_row(row);
+    }
+
+    if changed + added + removed == 0 && !args.all {
+        println!("No differences");
+        return Ok(());
+    }
+    println!("{}", table);
+    println!("{} changed, {} added, {} removed", changed, added, removed);
+    Ok(())
+}
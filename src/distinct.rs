@@ -0,0 +1,150 @@
+use crate::config::ColorScheme;
+use crate::input::ReadOptions;
+use crate::sort::{compare_keys, sort_key};
+use crate::{column_index, detect_data_type_cached, new_table, read_csv_data, DataType};
+use clap::ValueEnum;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, Table};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(clap::Args)]
+pub struct DistinctArgs {
+    pub input: String,
+
+    #[arg(long = "column", value_name = "COLUMN", required = true)]
+    pub columns: Vec<String>,
+
+    #[arg(short, long, value_enum, default_value_t = DistinctFormat::Table)]
+    pub output: DistinctFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DistinctFormat {
+    Table,
+    Json,
+    Csv,
+}
+
+#[derive(Serialize)]
+struct DistinctValue {
+    value: String,
+    count: usize,
+}
+
+#[derive(Serialize)]
+struct ColumnValues {
+    column: String,
+    values: Vec<DistinctValue>,
+}
+
+// Every value of a column once, sorted the way `--sort` would sort them,
+// with empty cells last
+fn distinct_values<'a>(values: impl Iterator<Item = &'a str>) -> Vec<DistinctValue> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for val in values {
+        *counts.entry(val).or_insert(0) += 1;
+    }
+    let mut keyed: Vec<_> = counts
+        .into_iter()
+        .map(|(value, count)| (sort_key(value), value, count))
+        .collect();
+    keyed.sort_by(|a, b| compare_keys(&a.0, &b.0, false).then_with(|| a.1.cmp(b.1)));
+    keyed
+        .into_iter()
+        .map(|(_, value, count)| DistinctValue {
+            value: value.to_string(),
+            count,
+        })
+        .collect()
+}
+
+fn create_table(columns: &[ColumnValues], scheme: &ColorScheme) -> Table {
+    // The column name is only worth a column of its own for several
+    let named = columns.len() > 1;
+    let mut table = new_table();
+    table.load_preset(UTF8_FULL);
+    let header: &[&str] = if named {
+        &["Column", "Value", "Count"]
+    } else {
+        &["Value", "Count"]
+    };
+    table.set_header(
+        header
+            .iter()
+            .map(|name| Cell::new(name).fg(scheme.header_color())),
+    );
+
+    for column in columns {
+        for entry in &column.values {
+            let shown = if entry.value.trim().is_empty() {
+                "(empty)"
+            } else {
+                entry.value.as_str()
+            };
+            let mut row = Vec::with_capacity(3);
+            if named {
+                row.push(Cell::new(&column.column).fg(scheme.header_color()));
+            }
+            row.push(
+                Cell::new(shown).fg(scheme.cell_color(&detect_data_type_cached(&entry.value))),
+            );
+            row.push(Cell::new(entry.count).fg(scheme.cell_color(&DataType::IntNumber)));
+            table.add_row(row);
+        }
+    }
+    table
+}
+
+fn write_csv(columns: &[ColumnValues]) -> Result<(), Box<dyn std::error::Error>> {
+    let named = columns.len() > 1;
+    let mut wtr = csv::Writer::from_writer(std::io::stdout().lock());
+    if named {
+        wtr.write_record(["column", "value", "count"])?;
+    } else {
+        wtr.write_record(["value", "count"])?;
+    }
+    for column in columns {
+        for entry in &column.values {
+            let count = entry.count.to_string();
+            if named {
+                wtr.write_record([column.column.as_str(), &entry.value, &count])?;
+            } else {
+                wtr.write_record([entry.value.as_str(), &count])?;
+            }
+        }
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// Prints the distinct values of each requested column with how often they
+// occur, e.g. to build a filter, an enum schema or an `--allowed` list
+pub fn run(
+    args: &DistinctArgs,
+    options: &ReadOptions,
+    scheme: &ColorScheme,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (headers, records) = read_csv_data(&args.input, options)?;
+    let columns = args
+        .columns
+        .iter()
+        .map(|name| {
+            let column = column_index(headers.as_deref(), name)?;
+            let values = records
+                .iter()
+                .map(|record| record.get(column).map_or("", |val| val.as_str()));
+            Ok(ColumnValues {
+                column: name.clone(),
+                values: distinct_values(values),
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+
+    match args.output {
+        DistinctFormat::Table => println!("{}", create_table(&columns, scheme)),
+        DistinctFormat::Json => println!("{}", serde_json::to_string_pretty(&columns)?),
+        DistinctFormat::Csv => write_csv(&columns)?,
+    }
+    Ok(())
+}
@@ -0,0 +1,77 @@
+use crate::store::{Field, Interner};
+use crate::CsvData;
+use serde_json::Value;
+use std::collections::HashMap;
+
+fn cell_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        // Anything nested deeper stays as compact JSON
+        Value::Array(_) | Value::Object(_) => value.to_string(),
+    }
+}
+
+// Columns are the union of keys in the order they first appear. Nested
+// objects are flattened one level into `parent.child` columns.
+fn flatten(values: Vec<Value>) -> CsvData {
+    let mut columns: Vec<String> = Vec::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    let mut rows: Vec<Vec<(usize, String)>> = Vec::with_capacity(values.len());
+
+    let mut cell = |name: String, text: String, row: &mut Vec<(usize, String)>| {
+        let idx = *positions.entry(name.clone()).or_insert_with(|| {
+            columns.push(name);
+            columns.len() - 1
+        });
+        row.push((idx, text));
+    };
+
+    for value in values {
+        let mut row = Vec::new();
+        match value {
+            Value::Object(object) => {
+                for (key, value) in object {
+                    match value {
+                        Value::Object(nested) => {
+                            for (child, value) in nested {
+                                cell(format!("{}.{}", key, child), cell_text(&value), &mut row);
+                            }
+                        }
+                        value => cell(key, cell_text(&value), &mut row),
+                    }
+                }
+            }
+            other => cell("value".to_string(), cell_text(&other), &mut row),
+        }
+        rows.push(row);
+    }
+
+    let mut interner = Interner::default();
+    let records = rows
+        .into_iter()
+        .map(|cells| {
+            let mut record = vec![Field::default(); columns.len()];
+            for (idx, text) in cells {
+                record[idx] = interner.field(idx, &text);
+            }
+            record
+        })
+        .collect();
+    (Some(columns), records)
+}
+
+// Reads a JSON array of objects as well as JSON Lines (or any other
+// sequence of values). Each object becomes a row.
+pub fn read_json(content: &str) -> Result<CsvData, Box<dyn std::error::Error>> {
+    let mut values = Vec::new();
+    for value in serde_json::Deserializer::from_str(content).into_iter::<Value>() {
+        match value.map_err(|e| format!("invalid JSON: {}", e))? {
+            Value::Array(items) => values.extend(items),
+            value => values.push(value),
+        }
+    }
+    Ok(flatten(values))
+}
@@ -0,0 +1,224 @@
+use crate::source::Source;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encryption {
+    Age,
+    Gpg,
+}
+
+impl Encryption {
+    // Age files and armored OpenPGP messages start with a line saying so,
+    // binary OpenPGP messages with the packet holding their session key
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"age-encryption.org/") || bytes.starts_with(b"-----BEGIN AGE ENCRYPTED FILE-----") {
+            return Some(Encryption::Age);
+        }
+        if bytes.starts_with(b"-----BEGIN PGP MESSAGE-----") || starts_with_session_key(bytes) {
+            return Some(Encryption::Gpg);
+        }
+        None
+    }
+
+    // For names of files whose bytes aren't at hand
+    pub fn from_extension(path: &str) -> Option<Self> {
+        let ext = Path::new(path).extension()?.to_str()?.to_lowercase();
+        match ext.as_str() {
+            "age" => Some(Encryption::Age),
+            "gpg" | "pgp" => Some(Encryption::Gpg),
+            _ => None,
+        }
+    }
+}
+
+// An OpenPGP packet with a session key encrypted to a public key (tag 1,
+// version 3 or 6) or to a passphrase (tag 3, version 4 to 6), in the old
+// or the new packet format. The header byte has its top bit set, so ASCII
+// text can't start like that, and the version rules out most other text.
+fn starts_with_session_key(bytes: &[u8]) -> bool {
+    let Some(&header) = bytes.first() else {
+        return false;
+    };
+    let (tag, length_len) = match header & 0xc0 {
+        0xc0 => match bytes.get(1) {
+            Some(192..=223) => (header & 0x3f, 2),
+            Some(255) => (header & 0x3f, 5),
+            _ => (header & 0x3f, 1),
+        },
+        0x80 => ((header >> 2) & 0x0f, [1, 2, 4, 0][usize::from(header & 0x03)]),
+        _ => return false,
+    };
+    matches!((tag, bytes.get(1 + length_len)), (1, Some(3 | 6)) | (3, Some(4..=6)))
+}
+
+// What a file decrypted to, kept for as long as its encrypted bytes stay
+// the same. Reloading it in the pager then doesn't ask for its passphrase
+// again, on a screen the pager has taken over.
+struct Decrypted {
+    encrypted: Vec<u8>,
+    plain: Vec<u8>,
+}
+
+thread_local! {
+    static DECRYPTED: RefCell<HashMap<String, Decrypted>> = RefCell::new(HashMap::new());
+}
+
+// Decrypts in memory, age files in pcsv itself and OpenPGP ones with the
+// gpg command, which asks for the passphrase on the terminal. The plain
+// text is never written to a file.
+pub fn decrypt(bytes: Source, path: Option<&str>, identity: Option<&Path>) -> io::Result<Source> {
+    let Some(encryption) = Encryption::detect(&bytes) else {
+        return Ok(bytes);
+    };
+    let cached = path.and_then(|path| {
+        DECRYPTED.with_borrow(|decrypted| {
+            decrypted
+                .get(path)
+                .filter(|cached| cached.encrypted == *bytes)
+                .map(|cached| cached.plain.clone())
+        })
+    });
+    if let Some(plain) = cached {
+        return Ok(Source::Owned(plain));
+    }
+
+    let plain = match encryption {
+        Encryption::Age => decrypt_age(&bytes, path.unwrap_or("standard input"), identity)?,
+        Encryption::Gpg => decrypt_gpg(&bytes)?,
+    };
+    if let Some(path) = path {
+        let entry = Decrypted {
+            encrypted: bytes.to_vec(),
+            plain: plain.clone(),
+        };
+        DECRYPTED.with_borrow_mut(|decrypted| decrypted.insert(path.to_string(), entry));
+    }
+    Ok(Source::Owned(plain))
+}
+
+#[cfg(feature = "age")]
+thread_local! {
+    // The passphrase each age file was opened with, to open it again once
+    // it changed
+    static PASSPHRASES: RefCell<HashMap<String, age::secrecy::SecretString>> = RefCell::new(HashMap::new());
+}
+
+#[cfg(feature = "age")]
+fn decrypt_age(bytes: &[u8], name: &str, identity: Option<&Path>) -> io::Result<Vec<u8>> {
+    let decryptor = age::Decryptor::new(age::armor::ArmoredReader::new(bytes)).map_err(io::Error::other)?;
+    let mut reader = if decryptor.is_scrypt() {
+        let passphrase = match PASSPHRASES.with_borrow(|passphrases| passphrases.get(name).cloned()) {
+            Some(passphrase) => passphrase,
+            None => rpassword::prompt_password(format!("Passphrase for {}: ", name))?.into(),
+        };
+        let key = age::scrypt::Identity::new(passphrase.clone());
+        let reader = decryptor
+            .decrypt(std::iter::once(&key as &dyn age::Identity))
+            .map_err(io::Error::other)?;
+        PASSPHRASES.with_borrow_mut(|passphrases| passphrases.insert(name.to_string(), passphrase));
+        reader
+    } else {
+        let identity = identity.ok_or_else(|| {
+            io::Error::other("it is encrypted to a key; pass the key's identity file with --identity")
+        })?;
+        let keys = age::IdentityFile::from_file(identity.to_string_lossy().into_owned())?
+            .into_identities()
+            .map_err(io::Error::other)?;
+        decryptor
+            .decrypt(keys.iter().map(|key| key.as_ref() as &dyn age::Identity))
+            .map_err(io::Error::other)?
+    };
+    let mut plain = Vec::new();
+    reader.read_to_end(&mut plain)?;
+    Ok(plain)
+}
+
+#[cfg(not(feature = "age"))]
+fn decrypt_age(_bytes: &[u8], _name: &str, _identity: Option<&Path>) -> io::Result<Vec<u8>> {
+    Err(io::Error::other(
+        "reading age files needs pcsv built with the `age` feature",
+    ))
+}
+
+fn decrypt_gpg(bytes: &[u8]) -> io::Result<Vec<u8>> {
+    let mut command = Command::new("gpg");
+    command.args(["--decrypt", "--quiet"]);
+    run(&mut command, bytes).map_err(|e| match e.kind() {
+        ErrorKind::NotFound => io::Error::new(e.kind(), "decrypting needs gpg installed"),
+        _ => io::Error::new(e.kind(), format!("gpg: {}", e)),
+    })
+}
+
+// The program's output for the bytes on its input. Errors and prompts go
+// straight to the terminal.
+fn run(command: &mut Command, input: &[u8]) -> io::Result<Vec<u8>> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()?;
+    let (Some(mut stdin), Some(mut stdout)) = (child.stdin.take(), child.stdout.take()) else {
+        return Err(io::Error::other("no pipe to the decrypting program"));
+    };
+    // Written from another thread, so neither side waits on a full pipe
+    let mut output = Vec::new();
+    thread::scope(|scope| {
+        scope.spawn(move || {
+            // A program that gives up early closes its input
+            let _ = stdin.write_all(input);
+        });
+        stdout.read_to_end(&mut output)
+    })?;
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("decrypting failed ({})", status)));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_encryption_by_its_first_bytes() {
+        // Session keys for a passphrase, old packet format, and for a
+        // public key, new packet format
+        let (gpg, age) = (Some(Encryption::Gpg), Some(Encryption::Age));
+        assert_eq!(Encryption::detect(b"\x8c\x0d\x04\x09\x03\x02"), gpg);
+        assert_eq!(Encryption::detect(b"\xc1\x5e\x03\x1c\x2d"), gpg);
+        assert_eq!(Encryption::detect(b"-----BEGIN PGP MESSAGE-----"), gpg);
+        assert_eq!(Encryption::detect(b"age-encryption.org/v1"), age);
+        assert_eq!(Encryption::detect("é,ü\n1,2\n".as_bytes()), None);
+        assert_eq!(Encryption::detect(b"-----BEGIN PGP SIGNATURE-----\n"), None);
+    }
+
+    #[cfg(feature = "age")]
+    #[test]
+    fn keeps_what_a_file_decrypted_to() {
+        use age::secrecy::ExposeSecret;
+
+        let key = age::x25519::Identity::generate();
+        let dir = tempfile::tempdir().unwrap();
+        let identity = dir.path().join("key.txt");
+        std::fs::write(&identity, key.to_string().expose_secret()).unwrap();
+        let recipient = key.to_public();
+        let encryptor = age::Encryptor::with_recipients(std::iter::once(&recipient as _)).unwrap();
+        let mut encrypted = Vec::new();
+        let mut writer = encryptor.wrap_output(&mut encrypted).unwrap();
+        writer.write_all(b"id,name\n1,ann\n").unwrap();
+        writer.finish().unwrap();
+
+        let path = Some("people.csv.age");
+        let plain = decrypt(Source::Owned(encrypted.clone()), path, Some(&identity)).unwrap();
+        assert_eq!(&*plain, b"id,name\n1,ann\n");
+        // Read again without the key, as it is the same file
+        let again = decrypt(Source::Owned(encrypted), path, None).unwrap();
+        assert_eq!(&*again, b"id,name\n1,ann\n");
+    }
+}
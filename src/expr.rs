@@ -0,0 +1,470 @@
+use crate::sort::date_key;
+use crate::store::Field;
+use crate::{detect_builtin_type, parse_boolean, plain_amount, plain_number, DataType};
+use std::cmp::Ordering;
+
+// Arithmetic and comparisons over the cells of a row, like
+// `total == price * qty` or `end_date >= start_date`. Column names are
+// bare words, or wrapped in backticks when they contain spaces.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    // Filled in by `resolve` with the column's index
+    Column(String, usize),
+    Literal(String),
+    Negate(Box<Expr>),
+    Binary(Box<Expr>, Op, Box<Expr>),
+    // Filled in by `prepare` with what is subtracted from the value and
+    // what it is divided by then
+    Scaled(Scale, Box<Expr>, (f64, f64)),
+}
+
+// Functions that put a value in proportion to the same expression over
+// every row, so columns of different magnitudes can be compared
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scale {
+    // 0 for the smallest value, 1 for the largest
+    Normalize,
+    // Standard deviations from the mean
+    Zscore,
+}
+
+impl Scale {
+    fn named(name: &str) -> Option<Self> {
+        match name {
+            "normalize" => Some(Scale::Normalize),
+            "zscore" => Some(Scale::Zscore),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or,
+}
+
+impl Op {
+    fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge | Op::And | Op::Or
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Number(f64),
+    Date([u32; 6]),
+    Text(String),
+    Bool(bool),
+    Empty,
+    // Arithmetic on text, or comparing a number with a date
+    Invalid,
+}
+
+impl Value {
+    // Cells are typed the same way they are colored
+    pub fn from_cell(val: &str) -> Self {
+        match detect_builtin_type(val) {
+            DataType::Empty => Value::Empty,
+            DataType::IntNumber | DataType::FloatNumber => plain_number(val)
+                .parse()
+                .map(Value::Number)
+                .unwrap_or_else(|_| Value::Text(val.to_string())),
+            DataType::Currency => plain_amount(val)
+                .and_then(|amount| amount.parse().ok())
+                .map(Value::Number)
+                .unwrap_or_else(|| Value::Text(val.to_string())),
+            DataType::Date => date_key(val)
+                .map(Value::Date)
+                .unwrap_or_else(|| Value::Text(val.to_string())),
+            DataType::Boolean => Value::Bool(parse_boolean(val).unwrap_or(false)),
+            DataType::Text
+            | DataType::Url
+            | DataType::Email
+            | DataType::Uuid
+            | DataType::Network
+            | DataType::Custom(_) => Value::Text(val.to_string()),
+        }
+    }
+
+    // The value as a cell of a derived column. Numbers keep four decimals at
+    // most, and values that couldn't be computed are left empty.
+    pub fn to_cell(&self) -> String {
+        match self {
+            Value::Number(num) => {
+                let text = format!("{:.4}", num);
+                let text = text.trim_end_matches('0').trim_end_matches('.');
+                match text {
+                    "-0" => "0".to_string(),
+                    text => text.to_string(),
+                }
+            }
+            Value::Date([year, month, day, 0, 0, 0]) => format!("{:04}-{:02}-{:02}", year, month, day),
+            Value::Date([year, month, day, hour, minute, second]) => format!(
+                "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                year, month, day, hour, minute, second
+            ),
+            Value::Text(text) => text.clone(),
+            Value::Bool(val) => val.to_string(),
+            Value::Empty | Value::Invalid => String::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Name(String),
+    Literal(String),
+    Op(Op),
+    Open,
+    Close,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let two: String = chars.clone().take(2).collect();
+        let op = match two.as_str() {
+            "==" => Some((Op::Eq, 2)),
+            "!=" | "<>" => Some((Op::Ne, 2)),
+            "<=" => Some((Op::Le, 2)),
+            ">=" => Some((Op::Ge, 2)),
+            "&&" => Some((Op::And, 2)),
+            "||" => Some((Op::Or, 2)),
+            _ => match c {
+                '=' => Some((Op::Eq, 1)),
+                '<' => Some((Op::Lt, 1)),
+                '>' => Some((Op::Gt, 1)),
+                '+' => Some((Op::Add, 1)),
+                '-' => Some((Op::Sub, 1)),
+                '*' => Some((Op::Mul, 1)),
+                '/' => Some((Op::Div, 1)),
+                _ => None,
+            },
+        };
+        if let Some((op, len)) = op {
+            tokens.push(Token::Op(op));
+            for _ in 0..len {
+                chars.next();
+            }
+            continue;
+        }
+
+        chars.next();
+        match c {
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '\'' | '"' | '`' => {
+                let quoted: String = chars.by_ref().take_while(|&next| next != c).collect();
+                if c == '`' {
+                    tokens.push(Token::Name(quoted));
+                } else {
+                    tokens.push(Token::Literal(quoted));
+                }
+            }
+            _ if c.is_ascii_digit() || c == '.' => {
+                let mut number = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !(next.is_ascii_digit() || next == '.') {
+                        break;
+                    }
+                    number.push(next);
+                    chars.next();
+                }
+                tokens.push(Token::Literal(number));
+            }
+            _ if c.is_alphanumeric() || c == '_' => {
+                let mut word = c.to_string();
+                while let Some(&next) = chars.peek() {
+                    if !(next.is_alphanumeric() || matches!(next, '_' | '.')) {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                tokens.push(match word.as_str() {
+                    "and" => Token::Op(Op::And),
+                    "or" => Token::Op(Op::Or),
+                    "true" | "false" => Token::Literal(word),
+                    _ => Token::Name(word),
+                });
+            }
+            _ => return Err(format!("unexpected '{}' in '{}'", c, text)),
+        }
+    }
+    Ok(tokens)
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Name(text) | Token::Literal(text) => format!("'{}'", text),
+            Token::Op(_) => "operator".to_string(),
+            Token::Open => "'('".to_string(),
+            Token::Close => "')'".to_string(),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+// Loosest first
+const PRECEDENCE: [&[Op]; 5] = [
+    &[Op::Or],
+    &[Op::And],
+    &[Op::Eq, Op::Ne, Op::Lt, Op::Le, Op::Gt, Op::Ge],
+    &[Op::Add, Op::Sub],
+    &[Op::Mul, Op::Div],
+];
+
+impl Parser {
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+        let Some(ops) = PRECEDENCE.get(level) else {
+            return self.unary();
+        };
+        let mut lhs = self.binary(level + 1)?;
+        while let Some(Token::Op(op)) = self.tokens.get(self.pos) {
+            let op = *op;
+            if !ops.contains(&op) {
+                break;
+            }
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(Box::new(lhs), op, Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        match token {
+            Some(Token::Op(Op::Sub)) => Ok(Expr::Negate(Box::new(self.unary()?))),
+            Some(Token::Name(name)) if self.tokens.get(self.pos) == Some(&Token::Open) => {
+                let scale = Scale::named(&name).ok_or_else(|| {
+                    format!("unknown function '{}', expected normalize or zscore", name)
+                })?;
+                self.pos += 1;
+                let inner = self.binary(0)?;
+                if self.tokens.get(self.pos) != Some(&Token::Close) {
+                    return Err(format!("missing ')' after the argument of {}", name));
+                }
+                self.pos += 1;
+                Ok(Expr::Scaled(scale, Box::new(inner), (0.0, 0.0)))
+            }
+            Some(Token::Name(name)) => Ok(Expr::Column(name, 0)),
+            Some(Token::Literal(text)) => Ok(Expr::Literal(text)),
+            Some(Token::Open) => {
+                let inner = self.binary(0)?;
+                if self.tokens.get(self.pos) != Some(&Token::Close) {
+                    return Err("missing ')'".to_string());
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Some(token) => Err(format!("unexpected {}", token.describe())),
+            None => Err("the expression ends too early".to_string()),
+        }
+    }
+}
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            pos: 0,
+        };
+        let expr = parser.binary(0)?;
+        if parser.pos < parser.tokens.len() {
+            return Err(format!(
+                "unexpected {} in '{}'",
+                parser.tokens[parser.pos].describe(),
+                text
+            ));
+        }
+        Ok(expr)
+    }
+
+    // Whether the expression yields true or false rather than a value
+    pub fn is_condition(&self) -> bool {
+        matches!(self, Expr::Binary(_, op, _) if op.is_comparison())
+    }
+
+    // Looks up the index of every column the expression refers to
+    pub fn resolve<E>(
+        &mut self,
+        index_of: &mut impl FnMut(&str) -> Result<usize, E>,
+    ) -> Result<(), E> {
+        match self {
+            Expr::Column(name, index) => *index = index_of(name)?,
+            Expr::Literal(_) => {}
+            Expr::Negate(inner) | Expr::Scaled(_, inner, _) => inner.resolve(index_of)?,
+            Expr::Binary(lhs, _, rhs) => {
+                lhs.resolve(index_of)?;
+                rhs.resolve(index_of)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Works out what `normalize` and `zscore` scale by, over these records.
+    // Call after `resolve`.
+    pub fn prepare(&mut self, records: &[Vec<Field>]) {
+        match self {
+            Expr::Column(..) | Expr::Literal(_) => {}
+            Expr::Negate(inner) => inner.prepare(records),
+            Expr::Binary(lhs, _, rhs) => {
+                lhs.prepare(records);
+                rhs.prepare(records);
+            }
+            Expr::Scaled(scale, inner, by) => {
+                inner.prepare(records);
+                let numbers: Vec<f64> = records
+                    .iter()
+                    .filter_map(|record| match inner.eval(record) {
+                        Value::Number(num) => Some(num),
+                        _ => None,
+                    })
+                    .collect();
+                if numbers.is_empty() {
+                    return;
+                }
+                *by = match scale {
+                    Scale::Normalize => {
+                        let min = numbers.iter().copied().fold(f64::INFINITY, f64::min);
+                        let max = numbers.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                        (min, max - min)
+                    }
+                    Scale::Zscore => {
+                        let mean = numbers.iter().sum::<f64>() / numbers.len() as f64;
+                        let variance = numbers.iter().map(|num| (num - mean).powi(2)).sum::<f64>()
+                            / numbers.len() as f64;
+                        (mean, variance.sqrt())
+                    }
+                };
+            }
+        }
+    }
+
+    // Indices of the columns the expression reads
+    pub fn columns(&self) -> Vec<usize> {
+        match self {
+            Expr::Column(_, index) => vec![*index],
+            Expr::Literal(_) => Vec::new(),
+            Expr::Negate(inner) | Expr::Scaled(_, inner, _) => inner.columns(),
+            Expr::Binary(lhs, _, rhs) => {
+                let mut columns = lhs.columns();
+                columns.extend(rhs.columns());
+                columns
+            }
+        }
+    }
+
+    pub fn eval(&self, record: &[Field]) -> Value {
+        match self {
+            Expr::Column(_, index) => {
+                Value::from_cell(record.get(*index).map_or("", |val| val.as_str()))
+            }
+            Expr::Literal(text) => Value::from_cell(text),
+            Expr::Negate(inner) => match inner.eval(record) {
+                Value::Number(num) => Value::Number(-num),
+                Value::Empty => Value::Empty,
+                _ => Value::Invalid,
+            },
+            Expr::Binary(lhs, op, rhs) => apply(lhs.eval(record), *op, rhs.eval(record)),
+            Expr::Scaled(_, inner, (offset, divisor)) => match inner.eval(record) {
+                // Every value is the same
+                Value::Number(_) if *divisor == 0.0 => Value::Number(0.0),
+                Value::Number(num) => Value::Number((num - offset) / divisor),
+                Value::Empty => Value::Empty,
+                _ => Value::Invalid,
+            },
+        }
+    }
+}
+
+// A `--derive NAME=EXPR` column, computed for every row
+#[derive(Debug, Clone)]
+pub struct Derived {
+    pub name: String,
+    pub expr: Expr,
+}
+
+pub fn parse_derived(arg: &str) -> Result<Derived, String> {
+    let (name, text) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("invalid column '{}', expected NAME=EXPR", arg))?;
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(format!("'{}' needs a name before the '='", arg));
+    }
+    Ok(Derived {
+        name: name.to_string(),
+        expr: Expr::parse(text)?,
+    })
+}
+
+// Numbers this close count as equal, so `total == price * qty` holds
+// despite floating point rounding
+fn roughly_equal(a: f64, b: f64) -> bool {
+    (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.0)
+}
+
+fn apply(lhs: Value, op: Op, rhs: Value) -> Value {
+    match (op, lhs, rhs) {
+        (Op::And, Value::Bool(a), Value::Bool(b)) => Value::Bool(a && b),
+        (Op::Or, Value::Bool(a), Value::Bool(b)) => Value::Bool(a || b),
+        (_, Value::Invalid, _) | (_, _, Value::Invalid) => Value::Invalid,
+        // Missing data carries through, neither passing nor failing
+        (_, Value::Empty, _) | (_, _, Value::Empty) => Value::Empty,
+        (Op::Add, Value::Number(a), Value::Number(b)) => Value::Number(a + b),
+        (Op::Sub, Value::Number(a), Value::Number(b)) => Value::Number(a - b),
+        (Op::Mul, Value::Number(a), Value::Number(b)) => Value::Number(a * b),
+        (Op::Div, Value::Number(a), Value::Number(b)) if b != 0.0 => Value::Number(a / b),
+        (Op::Eq | Op::Ne | Op::Lt | Op::Le | Op::Gt | Op::Ge, a, b) => {
+            let ordering = match (&a, &b) {
+                (Value::Number(x), Value::Number(y)) if roughly_equal(*x, *y) => {
+                    Some(Ordering::Equal)
+                }
+                (Value::Number(x), Value::Number(y)) => x.partial_cmp(y),
+                (Value::Date(x), Value::Date(y)) => Some(x.cmp(y)),
+                (Value::Text(x), Value::Text(y)) => Some(x.cmp(y)),
+                (Value::Bool(x), Value::Bool(y)) => Some(x.cmp(y)),
+                _ => None,
+            };
+            match ordering {
+                Some(ordering) => Value::Bool(match op {
+                    Op::Eq => ordering == Ordering::Equal,
+                    Op::Ne => ordering != Ordering::Equal,
+                    Op::Lt => ordering == Ordering::Less,
+                    Op::Le => ordering != Ordering::Greater,
+                    Op::Gt => ordering == Ordering::Greater,
+                    _ => ordering != Ordering::Less,
+                }),
+                None => Value::Invalid,
+            }
+        }
+        _ => Value::Invalid,
+    }
+}
@@ -0,0 +1,48 @@
+use crate::store::Field;
+use regex::Regex;
+
+// A row filter either matches against every cell or, in the
+// `column=value` form, against a single named column. A regex matching
+// `=` across the row can write it as `\x3D`.
+pub struct RowFilter {
+    column: Option<usize>,
+    pattern: Regex,
+}
+
+impl RowFilter {
+    pub fn parse(expr: &str, headers: Option<&[String]>) -> Result<Self, String> {
+        let Some((name, value)) = expr.split_once('=') else {
+            return Ok(Self {
+                column: None,
+                pattern: compile_pattern(expr),
+            });
+        };
+        let name = name.trim();
+        let idx = headers
+            .and_then(|h| h.iter().position(|col| col == name))
+            .ok_or_else(|| format!("column '{}' not found", name))?;
+        Ok(Self {
+            column: Some(idx),
+            pattern: compile_pattern(value),
+        })
+    }
+
+    pub fn matches(&self, record: &[Field]) -> bool {
+        match self.column {
+            Some(idx) => record.get(idx).is_some_and(|val| self.pattern.is_match(val)),
+            None => record.iter().any(|val| self.pattern.is_match(val)),
+        }
+    }
+}
+
+fn compile_pattern(pattern: &str) -> Regex {
+    // Anything that isn't valid regex is treated as a plain substring
+    Regex::new(pattern).unwrap_or_else(|_| Regex::new(&regex::escape(pattern)).unwrap())
+}
+
+pub fn filter_records(records: Vec<Vec<Field>>, filter: &RowFilter) -> Vec<Vec<Field>> {
+    records
+        .into_iter()
+        .filter(|record| filter.matches(record))
+        .collect()
+}
@@ -0,0 +1,209 @@
+use crate::compression::Compression;
+use crate::encryption::Encryption;
+use crate::input::{self, InputFormat, ReadOptions};
+use crate::source::{self, Source};
+use crate::store::{Field, Text};
+use crate::{parse_csv, parse_records, CsvData};
+use std::borrow::Cow;
+use std::fs::{self, File};
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+// Bytes kept from just before where reading stopped, compared with the
+// file each time to notice it was rewritten rather than appended to
+const SEEN: usize = 256;
+
+// A file followed with --follow. It is read whole once, and after that
+// only the records appended since are parsed and added to the ones already
+// read. It is read from the start again when it shrank, was replaced or
+// changed before where reading stopped.
+pub struct Growing {
+    input: String,
+    options: ReadOptions,
+    delimiter: u8,
+    headers: Option<Vec<String>>,
+    records: Vec<Vec<Field>>,
+    // How far the file was read into records, which line that is on, and
+    // the bytes before it
+    read: u64,
+    line: u64,
+    seen: Vec<u8>,
+    identity: Option<(u64, u64)>,
+    // The line of a last record still being written, left for later
+    partial: Option<u64>,
+}
+
+impl Growing {
+    // The whole file, if it can be followed this way: a local CSV or TSV
+    // file in UTF-8, neither compressed nor encrypted, without --query.
+    // Reading it afterwards leaves warnings out.
+    pub fn open(input: &str, options: &ReadOptions) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        if input == "-" || source::is_remote(input) || options.query.is_some() {
+            return Ok(None);
+        }
+        let identity = identity(&fs::metadata(input)?);
+        let bytes = source::open(input)?;
+        let delimiter = match options.format.unwrap_or_else(|| InputFormat::detect(input, &bytes)) {
+            InputFormat::Csv => b',',
+            InputFormat::Tsv => b'\t',
+            _ => return Ok(None),
+        };
+        if Compression::detect(&bytes, Some(input)).is_some() || Encryption::detect(&bytes).is_some() {
+            return Ok(None);
+        }
+        let range = match input::decode(&bytes, input, options)? {
+            Cow::Borrowed(text) => {
+                let start = text.as_ptr() as usize - bytes.as_ptr() as usize;
+                start..start + text.len()
+            }
+            Cow::Owned(_) => return Ok(None),
+        };
+        let offset = range.start;
+        let text = Arc::new(Text::new(bytes, range)?);
+        let ((headers, records), stop) = parse_csv(&text, delimiter, options, true)?;
+
+        let mut growing = Growing {
+            input: input.to_string(),
+            options: ReadOptions {
+                quiet: true,
+                ..options.clone()
+            },
+            delimiter,
+            headers,
+            records,
+            read: offset as u64,
+            line: 1,
+            seen: Vec::new(),
+            identity,
+            partial: stop.partial,
+        };
+        // Until the header has its line ending, the file is read whole
+        if text.as_str().contains('\n') {
+            growing.advance(&text.as_str().as_bytes()[..stop.end]);
+        }
+        Ok(Some(growing))
+    }
+
+    pub fn data(&self) -> CsvData {
+        (self.headers.clone(), self.records.clone())
+    }
+
+    pub fn partial(&self) -> Option<u64> {
+        self.partial
+    }
+
+    // The records with the ones appended since the last time
+    pub fn refresh(&mut self) -> Result<CsvData, Box<dyn std::error::Error>> {
+        let metadata = fs::metadata(&self.input)?;
+        if self.seen.is_empty() || identity(&metadata) != self.identity || metadata.len() < self.read {
+            return self.reopen();
+        }
+        if metadata.len() == self.read {
+            return Ok(self.data());
+        }
+
+        let mut file = File::open(&self.input)?;
+        file.seek(SeekFrom::Start(self.read - self.seen.len() as u64))?;
+        let mut bytes = Vec::new();
+        file.read_to_end(&mut bytes)?;
+        if !bytes.starts_with(&self.seen) {
+            return self.reopen();
+        }
+        let from = self.seen.len();
+        // A character cut off at the end is part of a partial record, and
+        // read with the rest of it
+        let end = match std::str::from_utf8(&bytes[from..]) {
+            Ok(_) => bytes.len(),
+            Err(e) if e.error_len().is_none() => from + e.valid_up_to(),
+            Err(_) => return self.reopen(),
+        };
+        let text = Arc::new(Text::new(Source::Owned(bytes), from..end)?);
+        let (records, stop) = parse_records(
+            &text,
+            0,
+            self.line,
+            self.headers.as_deref(),
+            self.delimiter,
+            &self.options,
+            true,
+        )?;
+        self.advance(&text.as_str().as_bytes()[..stop.end]);
+        self.records.extend(records);
+        self.partial = stop.partial;
+        Ok(self.data())
+    }
+
+    fn reopen(&mut self) -> Result<CsvData, Box<dyn std::error::Error>> {
+        *self = Growing::open(&self.input, &self.options)?
+            .ok_or_else(|| format!("{} is no longer a plain CSV file that can be followed", self.input))?;
+        Ok(self.data())
+    }
+
+    // Moves past bytes read into records
+    fn advance(&mut self, bytes: &[u8]) {
+        self.read += bytes.len() as u64;
+        self.line += bytes.iter().filter(|&&byte| byte == b'\n').count() as u64;
+        self.seen.extend_from_slice(bytes);
+        let keep = self.seen.len().saturating_sub(SEEN);
+        self.seen.drain(..keep);
+    }
+}
+
+// The device and inode of a file, which change when it is replaced
+#[cfg(unix)]
+fn identity(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn identity(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn rows(data: &CsvData) -> Vec<Vec<String>> {
+        data.1.iter().map(|record| record.iter().map(Field::to_string).collect()).collect()
+    }
+
+    #[test]
+    fn reads_what_was_appended() {
+        let mut file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        write!(file, "id,name\n1,ann\n2,b").unwrap();
+        let path = file.path().to_str().unwrap().to_string();
+        let options = ReadOptions::from_args(&[]);
+        let mut growing = Growing::open(&path, &options).unwrap().unwrap();
+        // The last line isn't finished yet
+        assert_eq!(rows(&growing.data()), [["1", "ann"]]);
+        assert_eq!(growing.partial(), Some(3));
+
+        write!(file, "ob\n3,\"cy\nd\"\n4,").unwrap();
+        let data = growing.refresh().unwrap();
+        assert_eq!(data.0.as_deref().unwrap(), ["id", "name"]);
+        assert_eq!(rows(&data), [["1", "ann"], ["2", "bob"], ["3", "cy\nd"]]);
+        assert_eq!(growing.line, 6);
+        assert_eq!(growing.partial(), Some(6));
+
+        writeln!(file, "dan").unwrap();
+        assert_eq!(rows(&growing.refresh().unwrap()).len(), 4);
+        assert_eq!(growing.partial(), None);
+    }
+
+    #[test]
+    fn reads_a_rewritten_file_again() {
+        let mut file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        write!(file, "id,name\n1,ann\n2,bob\n").unwrap();
+        let path = file.path().to_str().unwrap().to_string();
+        let options = ReadOptions::from_args(&[]);
+        let mut growing = Growing::open(&path, &options).unwrap().unwrap();
+
+        fs::write(&path, "id,name\n7,eve\n2,bob\n8,fay\n").unwrap();
+        assert_eq!(rows(&growing.refresh().unwrap()), [["7", "eve"], ["2", "bob"], ["8", "fay"]]);
+        fs::write(&path, "id,name\n9,gus\n").unwrap();
+        assert_eq!(rows(&growing.refresh().unwrap()), [["9", "gus"]]);
+    }
+}
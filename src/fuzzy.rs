@@ -0,0 +1,158 @@
+use crate::config::ColorScheme;
+use crate::input::ReadOptions;
+use crate::{column_index, detect_data_type_cached, new_table, read_csv_data, DataType};
+use clap::ValueEnum;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::Cell;
+use std::collections::HashMap;
+
+#[derive(clap::Args)]
+pub struct FuzzyArgs {
+    pub input: String,
+
+    #[arg(long)]
+    pub column: String,
+
+    #[arg(short, long, default_value_t = 0.85)]
+    pub threshold: f64,
+
+    #[arg(long, value_enum, default_value_t = Metric::JaroWinkler)]
+    pub metric: Metric,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum Metric {
+    JaroWinkler,
+    Levenshtein,
+}
+
+impl Metric {
+    fn similarity(self, a: &str, b: &str) -> f64 {
+        match self {
+            Metric::JaroWinkler => strsim::jaro_winkler(a, b),
+            Metric::Levenshtein => strsim::normalized_levenshtein(a, b),
+        }
+    }
+}
+
+struct Cluster {
+    // (value, occurrences), most frequent first
+    members: Vec<(String, usize)>,
+}
+
+impl Cluster {
+    fn total(&self) -> usize {
+        self.members.iter().map(|(_, count)| count).sum()
+    }
+}
+
+fn find(parents: &mut [usize], mut idx: usize) -> usize {
+    while parents[idx] != idx {
+        parents[idx] = parents[parents[idx]];
+        idx = parents[idx];
+    }
+    idx
+}
+
+fn cluster_values(values: &[String], threshold: f64, metric: Metric) -> Vec<Cluster> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for val in values {
+        if !val.trim().is_empty() {
+            *counts.entry(val.as_str()).or_insert(0) += 1;
+        }
+    }
+
+    let mut distinct: Vec<(&str, usize)> = counts.into_iter().collect();
+    distinct.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    let normalized: Vec<String> = distinct
+        .iter()
+        .map(|(val, _)| val.trim().to_lowercase())
+        .collect();
+
+    // Single-link clustering: any pair above the threshold joins their clusters
+    let mut parents: Vec<usize> = (0..distinct.len()).collect();
+    for i in 0..distinct.len() {
+        for j in (i + 1)..distinct.len() {
+            if metric.similarity(&normalized[i], &normalized[j]) >= threshold {
+                let (a, b) = (find(&mut parents, i), find(&mut parents, j));
+                if a != b {
+                    parents[b.max(a)] = a.min(b);
+                }
+            }
+        }
+    }
+
+    let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+    for idx in 0..distinct.len() {
+        let root = find(&mut parents, idx);
+        groups.entry(root).or_default().push(idx);
+    }
+
+    let mut clusters: Vec<Cluster> = groups
+        .into_values()
+        .filter(|members| members.len() > 1)
+        .map(|members| Cluster {
+            members: members
+                .into_iter()
+                .map(|idx| (distinct[idx].0.to_string(), distinct[idx].1))
+                .collect(),
+        })
+        .collect();
+    clusters.sort_by(|a, b| {
+        b.total()
+            .cmp(&a.total())
+            .then_with(|| a.members[0].0.cmp(&b.members[0].0))
+    });
+    clusters
+}
+
+pub fn run(
+    args: &FuzzyArgs,
+    options: &ReadOptions,
+    scheme: &ColorScheme,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if !(0.0..=1.0).contains(&args.threshold) {
+        return Err("--threshold must be between 0 and 1".into());
+    }
+
+    let (headers, records) = read_csv_data(&args.input, options)?;
+    let column = column_index(headers.as_deref(), &args.column)?;
+
+    let values: Vec<String> = records
+        .iter()
+        .filter_map(|record| record.get(column).map(|val| val.to_string()))
+        .collect();
+    let clusters = cluster_values(&values, args.threshold, args.metric);
+
+    if clusters.is_empty() {
+        println!("No near-duplicate values found in '{}'", args.column);
+        return Ok(());
+    }
+
+    let mut table = new_table();
+    table.load_preset(UTF8_FULL);
+    table.set_header(
+        ["Cluster", "Value", "Count", "Similarity"]
+            .iter()
+            .map(|name| Cell::new(name).fg(scheme.header_color())),
+    );
+
+    for (cluster_idx, cluster) in clusters.iter().enumerate() {
+        let canonical = cluster.members[0].0.trim().to_lowercase();
+        for (value, count) in &cluster.members {
+            let similarity = args
+                .metric
+                .similarity(&canonical, &value.trim().to_lowercase());
+            table.add_row(vec![
+                Cell::new(cluster_idx + 1).fg(scheme.header_color()),
+                Cell::new(value).fg(scheme.cell_color(&detect_data_type_cached(value))),
+                Cell::new(count).fg(scheme.cell_color(&DataType::IntNumber)),
+                Cell::new(format!("{:.3}", similarity))
+                    .fg(scheme.cell_color(&DataType::FloatNumber)),
+            ]);
+        }
+    }
+
+    println!("{}", table);
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use crate::config::ColorScheme;
+use crate::input::ReadOptions;
+use crate::{paint, palette, read_csv_data, styled, themes};
+use clap::Args;
+use pcsv::TableView;
+
+#[derive(Args)]
+pub struct ThemesArgs {
+    #[arg(value_name = "THEME")]
+    pub names: Vec<String>,
+
+    #[arg(long, value_name = "FILE")]
+    pub sample: Option<String>,
+
+    #[arg(long, value_name = "N", default_value_t = 4)]
+    pub rows: usize,
+}
+
+// A row of every type, so each of a theme's colors shows
+const SAMPLE_HEADERS: [&str; 6] = ["name", "joined", "score", "visits", "active", "note"];
+const SAMPLE_ROWS: [[&str; 6]; 3] = [
+    ["Ada", "2024-01-02", "12.50", "7", "true", "first"],
+    ["Grace", "2023-11-30", "3.75", "42", "false", ""],
+    ["Linus", "2024-06-17", "0.10", "1", "yes", "on leave"],
+];
+
+// Every theme that can be picked, built-in ones first, with where it
+// comes from
+fn all_themes() -> Vec<(String, String)> {
+    let user = themes::user_themes();
+    let mut all: Vec<(String, String)> = themes::THEMES
+        .iter()
+        .filter(|(name, _)| !user.iter().any(|(theirs, _)| theirs.eq_ignore_ascii_case(name)))
+        .map(|(name, _)| (name.to_string(), "built-in".to_string()))
+        .collect();
+    all.extend(
+        user.into_iter()
+            .map(|(name, path)| (name, path.display().to_string())),
+    );
+    all
+}
+
+// The status bar and diff colors, which the table doesn't show
+fn swatches(scheme: &ColorScheme, styled: bool) -> String {
+    let labels = [
+        ("added", &scheme.diff.added),
+        ("removed", &scheme.diff.removed),
+        ("changed", &scheme.diff.changed),
+    ];
+    if !styled {
+        let diff: Vec<String> = labels
+            .iter()
+            .map(|(label, hex)| format!("{} {}", label, hex))
+            .collect();
+        return format!("status bar {} · diff {}", scheme.status_bar, diff.join(", "));
+    }
+    let bar = match palette::sgr(ColorScheme::hex_to_color(&scheme.status_bar), true) {
+        Some(sgr) => format!("\x1b[{}m status bar \x1b[49m", sgr),
+        None => " status bar ".to_string(),
+    };
+    let diff: Vec<String> = labels
+        .iter()
+        .map(|(label, hex)| paint(label, ColorScheme::hex_to_color(hex)))
+        .collect();
+    format!("{} · diff {}", bar, diff.join(" "))
+}
+
+pub fn run(args: &ThemesArgs, options: &ReadOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let (headers, rows) = match &args.sample {
+        Some(path) => {
+            let (headers, records) = read_csv_data(path, options)?;
+            let rows: Vec<Vec<String>> = records
+                .iter()
+                .take(args.rows)
+                .map(|record| record.iter().map(|val| val.to_string()).collect())
+                .collect();
+            (headers, rows)
+        }
+        None => (
+            Some(SAMPLE_HEADERS.iter().map(|name| name.to_string()).collect()),
+            SAMPLE_ROWS
+                .iter()
+                .take(args.rows)
+                .map(|row| row.iter().map(|val| val.to_string()).collect())
+                .collect(),
+        ),
+    };
+
+    let mut shown = all_themes();
+    if !args.names.is_empty() {
+        for name in &args.names {
+            themes::parse_theme(name)?;
+        }
+        shown.retain(|(theme, _)| args.names.iter().any(|name| name.eq_ignore_ascii_case(theme)));
+    }
+
+    let styled = styled();
+    for (idx, (name, origin)) in shown.iter().enumerate() {
+        if idx > 0 {
+            println!();
+        }
+        let scheme = match themes::load(name) {
+            Some(Ok(scheme)) => scheme,
+            Some(Err(e)) => {
+                println!("{} ({}): not a valid theme, {}", name, origin, e);
+                continue;
+            }
+            None => continue,
+        };
+        let title = format!("{} ({})", name, origin);
+        if styled {
+            println!("{}", paint(&title, scheme.header_color()));
+        } else {
+            println!("{}", title);
+        }
+        let mut table = TableView::builder().rows(rows.clone()).theme(scheme.clone());
+        if let Some(headers) = &headers {
+            table = table.headers(headers.clone());
+        }
+        let table = if styled { table.to_ansi_string() } else { table.to_plain_string() };
+        println!("{}", table);
+        println!("{}", swatches(&scheme, styled));
+    }
+    Ok(())
+}
@@ -0,0 +1,421 @@
+use crate::config::ColorScheme;
+use crate::locale::Locale;
+use crate::pager::{PagerContent, RenderFn};
+use crate::rules::CellRules;
+use crate::config::TruncateMode;
+use crate::truncate::{cut_lines, Truncation};
+use crate::store::Field;
+use crate::{detect_data_type_cached, groups, hyperlink, is_url, new_table, null_placeholder, plain, raw_text};
+use comfy_table::presets::{UTF8_FULL, UTF8_FULL_CONDENSED};
+use comfy_table::{Attribute, Cell, ColumnConstraint, Table, Width};
+use std::borrow::Cow;
+use std::ops::Range;
+use std::sync::Arc;
+use unicode_width::UnicodeWidthStr;
+
+// A comfy-table with the borders tables are drawn with, with or without
+// the lines between rows
+pub fn bordered_table(row_lines: bool) -> Table {
+    let mut table = new_table();
+    table.load_preset(if row_lines { UTF8_FULL } else { UTF8_FULL_CONDENSED });
+    table
+}
+
+// The header's cells, after the `#` of the row numbers if they are shown
+pub fn header_cells(names: &[String], row_numbers: bool, scheme: &ColorScheme) -> Vec<Cell> {
+    let numbers = row_numbers.then_some("#");
+    numbers
+        .into_iter()
+        .chain(names.iter().map(String::as_str))
+        .map(|name| Cell::new(name).fg(scheme.header_color()))
+        .collect()
+}
+
+// How the cells of a table show their values, the same in printed tables
+// and in the pager
+pub struct CellFormat<'a> {
+    pub scheme: &'a ColorScheme,
+    pub rules: &'a CellRules,
+    pub truncation: &'a Truncation,
+    pub locale: Option<&'a Locale>,
+    // Cells as raw text instead of their value
+    pub raw: bool,
+    // Web addresses as links that open them
+    pub links: bool,
+}
+
+impl CellFormat<'_> {
+    // The text a value is shown with, cut as --truncate says, and to
+    // `width` too for a column narrowed to fit the screen
+    fn text<'v>(&'v self, field: usize, val: &'v str, width: Option<usize>) -> Cow<'v, str> {
+        let text = shown(val, self.raw, self.locale, self.scheme);
+        let text = self.truncation.cut(field, text);
+        match width {
+            Some(width) => self.truncation.cut_to(field, text, width),
+            None => text,
+        }
+    }
+
+    // A cell in the color of its value's type, or of the first rule it
+    // matches (bold too if the rule says so). Cells keep that color when
+    // they show raw text, but aren't links then.
+    fn cell(&self, field: usize, val: &str, width: Option<usize>) -> Cell {
+        let data_type = detect_data_type_cached(val);
+        let style = self.rules.style(field, val, &data_type);
+        let color = style
+            .and_then(|style| style.color)
+            .unwrap_or_else(|| self.scheme.cell_color(&data_type));
+        let text = self.text(field, val, width);
+        let text = if self.links && !self.raw && is_url(val) {
+            Cow::Owned(hyperlink(&text, val.trim()))
+        } else {
+            text
+        };
+        let cell = Cell::new(text).fg(color);
+        match style {
+            Some(style) if style.bold => cell.add_attribute(Attribute::Bold),
+            _ => cell,
+        }
+    }
+
+    // The cells of a record's `fields`, each with the width it is cut to
+    // if any, after the record's number if it is given. Fields the record
+    // is short of are left blank.
+    pub fn row(
+        &self,
+        number: Option<usize>,
+        record: &[Field],
+        fields: impl Iterator<Item = (usize, Option<usize>)>,
+    ) -> Vec<Cell> {
+        let number = number.map(|number| Cell::new(number).fg(self.scheme.header_color()));
+        let cells = fields.map(|(field, width)| match record.get(field) {
+            Some(val) => self.cell(field, val, width),
+            None => Cell::new(""),
+        });
+        number.into_iter().chain(cells).collect()
+    }
+}
+
+// Draws a table one chunk of records at a time, so the pager can format
+// just the rows that scroll into view. Every chunk is a comfy-table drawn
+// like printed tables are, with the columns at widths measured over all
+// records up front, which is cheap next to painting.
+struct Grid {
+    // Names of the shown columns
+    headers: Option<Vec<String>>,
+    records: Arc<Vec<Vec<Field>>>,
+    // The record fields shown, in order
+    columns: Vec<usize>,
+    row_numbers: bool,
+    // Whether a border is drawn between records
+    row_lines: bool,
+    // Whether cells show their raw text instead of their value
+    raw: bool,
+    // Content width of every column, not counting the padding
+    widths: Vec<usize>,
+    // Lines of every record's cells, the border below it not counted
+    heights: Vec<usize>,
+    scheme: ColorScheme,
+    rules: CellRules,
+    truncation: Truncation,
+    locale: Option<Locale>,
+}
+
+// How the pager's table is drawn
+pub struct GridOptions<'a> {
+    pub row_numbers: bool,
+    // A border between records
+    pub row_lines: bool,
+    // Cells as raw text instead of their value
+    pub raw: bool,
+    // Fields of every record that are left out
+    pub hidden: &'a [usize],
+    // The config's rules, for the file's fields
+    pub rules: &'a CellRules,
+    // How long values are cut
+    pub truncation: &'a Truncation,
+    // Numbers and dates as this locale writes them
+    pub locale: Option<&'a Locale>,
+    // The screen width the columns are narrowed to fit, if they are
+    pub width: Option<usize>,
+}
+
+// Narrowest a column is made to fit the table on screen
+const MIN_FITTED_WIDTH: usize = 8;
+
+// Narrows the widest columns until the table fits in `width` screen
+// columns, each to an equal share of the room the narrower ones leave.
+// The row numbers keep their width, and no column goes below
+// MIN_FITTED_WIDTH however narrow the screen; the rest is panned to.
+fn fit_widths(widths: &mut [usize], leading: usize, width: usize) {
+    // A border left of every column and the right one, and a space of
+    // padding on each side
+    let chrome = 3 * widths.len() + 1;
+    let fixed: usize = widths[..leading].iter().sum();
+    let mut room = width.saturating_sub(chrome + fixed);
+    let columns = &mut widths[leading..];
+    if columns.iter().sum::<usize>() <= room {
+        return;
+    }
+    let mut order: Vec<usize> = (0..columns.len()).collect();
+    order.sort_by_key(|&col| columns[col]);
+    for (idx, &col) in order.iter().enumerate() {
+        let left = order.len() - idx;
+        let share = room / left;
+        if columns[col] <= share {
+            room -= columns[col];
+            continue;
+        }
+        // This column and every wider one get the same share, the odd
+        // columns of room going to the first of them
+        for (extra, &wide) in order[idx..].iter().enumerate() {
+            let fitted = share + usize::from(extra < room % left);
+            columns[wide] = fitted.max(MIN_FITTED_WIDTH).min(columns[wide]);
+        }
+        return;
+    }
+}
+
+fn text_width(val: &str) -> usize {
+    val.split('\n')
+        .map(UnicodeWidthStr::width)
+        .max()
+        .unwrap_or(0)
+}
+
+fn shown<'a>(val: &'a str, raw: bool, locale: Option<&Locale>, scheme: &'a ColorScheme) -> Cow<'a, str> {
+    if raw {
+        return Cow::Owned(raw_text(val));
+    }
+    match (null_placeholder(val, scheme), locale) {
+        (Some(glyph), _) => Cow::Borrowed(glyph),
+        (None, Some(locale)) => locale.render(Cow::Borrowed(val)),
+        (None, None) => Cow::Borrowed(val),
+    }
+}
+
+impl Grid {
+    fn new(
+        headers: Option<Vec<String>>,
+        records: Arc<Vec<Vec<Field>>>,
+        scheme: &ColorScheme,
+        options: &GridOptions,
+    ) -> Self {
+        let GridOptions {
+            row_numbers,
+            row_lines,
+            raw,
+            hidden,
+            rules,
+            truncation,
+            locale,
+            width,
+        } = *options;
+        let fields = records
+            .iter()
+            .map(Vec::len)
+            .chain(headers.as_ref().map(Vec::len))
+            .max()
+            .unwrap_or(0);
+        let columns: Vec<usize> = (0..fields).filter(|col| !hidden.contains(col)).collect();
+        // Where each field of a record is drawn, if it is
+        let mut slots = vec![None; fields];
+        let leading = usize::from(row_numbers);
+        for (slot, &col) in columns.iter().enumerate() {
+            slots[col] = Some(leading + slot);
+        }
+        let headers = headers.map(|h| {
+            h.into_iter()
+                .enumerate()
+                .filter(|(col, _)| slots[*col].is_some())
+                .map(|(_, name)| name)
+                .collect::<Vec<_>>()
+        });
+
+        let mut widths = vec![0; leading + columns.len()];
+        let mut measure = |col: usize, width: usize| widths[col] = widths[col].max(width);
+        if let Some(h) = &headers {
+            if row_numbers {
+                measure(0, 1);
+            }
+            for (col, name) in h.iter().enumerate() {
+                measure(leading + col, text_width(name));
+            }
+        }
+        if row_numbers {
+            measure(0, records.len().to_string().len());
+        }
+        let mut heights = Vec::with_capacity(records.len());
+        for record in records.iter() {
+            let mut height = 1;
+            for (col, (val, slot)) in record.iter().zip(&slots).enumerate() {
+                if let Some(slot) = *slot {
+                    let text = truncation.cut(col, shown(val, raw, locale, scheme));
+                    measure(slot, text_width(&text));
+                    height = height.max(text.split('\n').count());
+                }
+            }
+            heights.push(height);
+        }
+
+        if let Some(width) = width {
+            fit_widths(&mut widths, leading, width);
+        }
+        // Names don't get a say in how narrow a column is made
+        let headers = headers.map(|h| {
+            h.into_iter()
+                .enumerate()
+                .map(|(col, name)| {
+                    cut_lines(Cow::Owned(name), widths[leading + col], TruncateMode::End).into_owned()
+                })
+                .collect()
+        });
+
+        Grid {
+            headers,
+            records,
+            columns,
+            row_numbers,
+            row_lines,
+            raw,
+            widths,
+            heights,
+            scheme: scheme.clone(),
+            rules: rules.clone(),
+            truncation: truncation.clone(),
+            locale: locale.copied(),
+        }
+    }
+
+    // Where the border left of each data column sits, and the right border
+    fn column_edges(&self) -> Vec<usize> {
+        let mut edges = vec![0];
+        for width in &self.widths {
+            edges.push(edges[edges.len() - 1] + width + 3);
+        }
+        edges.split_off(usize::from(self.row_numbers))
+    }
+
+    fn format(&self) -> CellFormat<'_> {
+        CellFormat {
+            scheme: &self.scheme,
+            rules: &self.rules,
+            truncation: &self.truncation,
+            locale: self.locale.as_ref(),
+            raw: self.raw,
+            links: !plain(),
+        }
+    }
+
+    // Lines of a record with the border below it, which the last one
+    // doesn't have
+    fn record_height(&self, idx: usize) -> usize {
+        self.heights[idx] + usize::from(self.row_lines && idx + 1 < self.records.len())
+    }
+
+    // The table with the header and the records in `range`, split into the
+    // lines above the records, theirs with the borders between them, and
+    // the bottom border
+    fn draw(&self, range: Range<usize>) -> (Vec<String>, Vec<String>, String) {
+        let mut table = bordered_table(self.row_lines);
+        // Styled in the pager, even when what it draws is printed
+        if !plain() {
+            table.enforce_styling();
+        }
+        if let Some(h) = &self.headers {
+            table.set_header(header_cells(h, self.row_numbers, &self.scheme));
+        }
+        let format = self.format();
+        let leading = usize::from(self.row_numbers);
+        let body_len: usize = self.heights[range.clone()].iter().sum::<usize>()
+            + usize::from(self.row_lines) * range.len().saturating_sub(1);
+        for idx in range {
+            let fields = self
+                .columns
+                .iter()
+                .enumerate()
+                .map(|(slot, &col)| (col, Some(self.widths[leading + slot])));
+            table.add_row(format.row(self.row_numbers.then_some(idx + 1), &self.records[idx], fields));
+        }
+        // Every column at its measured width, the padding on top
+        let widths = self
+            .widths
+            .iter()
+            .map(|&width| ColumnConstraint::Absolute(Width::Fixed(width as u16 + 2)));
+        table.set_constraints(widths);
+
+        let mut lines: Vec<String> = table.lines().collect();
+        let bottom = lines.pop().unwrap_or_default();
+        let body = lines.split_off(lines.len().saturating_sub(body_len));
+        (lines, body, bottom)
+    }
+
+    // The lines of records `range`, each followed by the border below it
+    // but the last of all records
+    fn record_lines(&self, range: Range<usize>) -> Vec<String> {
+        // Drawn with the record after them, if there is one, for the
+        // border between
+        let end = (range.end + 1).min(self.records.len());
+        let len: usize = range.clone().map(|idx| self.record_height(idx)).sum();
+        let (_, mut body, _) = self.draw(range.start..end);
+        body.truncate(len);
+        body
+    }
+
+    // The lines above the records, with the group labels over the header
+    // if there are any, and the bottom border
+    fn head_lines(&self, group_labels: Option<&[Option<String>]>) -> (Vec<String>, String) {
+        let (head, _, bottom) = self.draw(0..self.records.len().min(1));
+        let head = match group_labels {
+            Some(labels) => groups::add_group_header(
+                &head.join("\n"),
+                &self
+                    .columns
+                    .iter()
+                    .map(|&col| labels.get(col).cloned().flatten())
+                    .collect::<Vec<_>>(),
+                usize::from(self.row_numbers),
+                (!plain()).then(|| self.scheme.header_color()),
+            )
+            .lines()
+            .map(str::to_string)
+            .collect(),
+            None => head,
+        };
+        (head, bottom)
+    }
+}
+
+// The table layout for the pager, formatted as it scrolls into view
+pub fn table_content(
+    headers: Option<Vec<String>>,
+    records: Arc<Vec<Vec<Field>>>,
+    group_labels: Option<&[Option<String>]>,
+    scheme: &ColorScheme,
+    options: &GridOptions,
+) -> PagerContent {
+    let names = crate::stats::column_names(headers.as_deref(), &records);
+    let grid = Grid::new(headers, records, scheme, options);
+    let (head, bottom) = grid.head_lines(group_labels);
+    let tail = vec![bottom];
+
+    let mut starts = Vec::with_capacity(grid.records.len());
+    let mut line = head.len();
+    for idx in 0..grid.records.len() {
+        starts.push(line);
+        line += grid.record_height(idx);
+    }
+
+    let edges = grid.column_edges();
+    let records = Arc::clone(&grid.records);
+    let grid_columns = grid.columns.clone();
+    let render: RenderFn = Arc::new(move |range| grid.record_lines(range));
+    let mut content = PagerContent::lazy(head, starts, line, tail, render);
+    content.column_edges = edges;
+    content.bordered = options.row_lines;
+    content.column_names = grid_columns.iter().map(|&col| names[col].clone()).collect();
+    content.field_names = names;
+    content.fields = grid_columns;
+    content.records = Some(records);
+    content
+}
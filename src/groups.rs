@@ -0,0 +1,116 @@
+use crate::config::ColumnGroupConfig;
+use crate::paint;
+use comfy_table::Color;
+
+// One group label per column; `None` leaves the column ungrouped.
+pub type GroupLabels = Vec<Option<String>>;
+
+// Labels from a group header row such as `Q1,,,Q2,,`: blank cells
+// continue the group to their left.
+pub fn labels_from_row(row: &[String]) -> GroupLabels {
+    let mut current: Option<String> = None;
+    row.iter()
+        .map(|cell| {
+            let cell = cell.trim();
+            if !cell.is_empty() {
+                current = Some(cell.to_string());
+            }
+            current.clone()
+        })
+        .collect()
+}
+
+pub fn labels_from_config(groups: &[ColumnGroupConfig], headers: &[String]) -> GroupLabels {
+    headers
+        .iter()
+        .map(|name| {
+            groups
+                .iter()
+                .find(|group| group.columns.iter().any(|col| col == name))
+                .map(|group| group.label.clone())
+        })
+        .collect()
+}
+
+fn center(label: &str, width: usize) -> String {
+    // Keep the one-space cell padding on both sides
+    let label: String = label.chars().take(width.saturating_sub(2)).collect();
+    let len = label.chars().count();
+    let left = (width - len) / 2;
+    format!("{}{}{}", " ".repeat(left), label, " ".repeat(width - len - left))
+}
+
+// Adds a row of group labels above a rendered UTF8_FULL table. Column
+// edges are taken from the junctions of the table's top border, so each
+// label spans exactly the columns of its group.
+pub fn add_group_header(
+    table: &str,
+    labels: &[Option<String>],
+    leading_columns: usize,
+    color: Option<Color>,
+) -> String {
+    let mut lines = table.lines();
+    let top: Vec<char> = match lines.next() {
+        Some(line) => line.chars().collect(),
+        None => return table.to_string(),
+    };
+    let edges: Vec<usize> = top
+        .iter()
+        .enumerate()
+        .filter(|(_, c)| matches!(c, '┌' | '┬' | '┐'))
+        .map(|(idx, _)| idx)
+        .collect();
+    let column_count = edges.len().saturating_sub(1);
+    if column_count == 0 {
+        return table.to_string();
+    }
+
+    // Merge adjacent columns sharing a label into (first, last + 1, label) spans
+    let mut spans: Vec<(usize, usize, Option<&str>)> = Vec::new();
+    for col in 0..column_count {
+        let label = col
+            .checked_sub(leading_columns)
+            .and_then(|idx| labels.get(idx))
+            .and_then(|label| label.as_deref());
+        match spans.last_mut() {
+            Some(span) if label.is_some() && span.2 == label => span.1 = col + 1,
+            _ => spans.push((col, col + 1, label)),
+        }
+    }
+    let boundaries: Vec<usize> = spans.iter().map(|span| edges[span.0]).collect();
+
+    let group_top: String = (0..top.len())
+        .map(|idx| match idx {
+            _ if idx == edges[0] => '┌',
+            _ if idx == edges[column_count] => '┐',
+            _ if boundaries.contains(&idx) => '┬',
+            _ => '─',
+        })
+        .collect();
+
+    let mut label_line = String::from("│");
+    for (first, end, label) in &spans {
+        let width = edges[*end] - edges[*first] - 1;
+        let text = center(label.unwrap_or(""), width);
+        match color {
+            Some(color) => label_line.push_str(&paint(&text, color)),
+            None => label_line.push_str(&text),
+        }
+        label_line.push('│');
+    }
+
+    let column_top: String = top
+        .iter()
+        .enumerate()
+        .map(|(idx, c)| match c {
+            '┌' => '├',
+            '┐' => '┤',
+            '┬' if boundaries.contains(&idx) => '┼',
+            other => *other,
+        })
+        .collect();
+
+    let mut output = vec![group_top, label_line, column_top];
+    output.extend(lines.map(|line| line.to_string()));
+    output.join("\n")
+}
@@ -0,0 +1,382 @@
+use crate::compression::Compression;
+use crate::input::{Encoding, InputFormat, ReadOptions};
+use crate::store::{Field, Text};
+use crate::remote;
+use crate::{parse_csv, parse_records, read_csv_data, source, CsvData};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+#[derive(clap::Args)]
+pub struct IndexArgs {
+    pub input: String,
+}
+
+const MAGIC: &[u8; 8] = b"PCSVIDX2";
+// Every this many records the index remembers where one starts
+const STRIDE: u64 = 1024;
+
+// Where a file's records start, saved next to it as `<file>.pcsvidx`.
+// The byte and line are kept for every STRIDE-th data record (the header
+// excluded).
+struct Index {
+    delimiter: u8,
+    source_len: u64,
+    source_modified: u64,
+    records: u64,
+    starts: Vec<(u64, u64)>,
+}
+
+fn index_path(input: &str) -> String {
+    format!("{}.pcsvidx", input)
+}
+
+// Size and modification time (in nanoseconds) tell an index is stale
+fn stamp(path: &str) -> io::Result<(u64, u64)> {
+    let metadata = fs::metadata(path)?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_nanos() as u64);
+    Ok((metadata.len(), modified))
+}
+
+impl Index {
+    fn write(&self, path: &str) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        out.write_all(MAGIC)?;
+        out.write_all(&[self.delimiter])?;
+        for value in [self.source_len, self.source_modified, self.records, STRIDE] {
+            out.write_all(&value.to_le_bytes())?;
+        }
+        for (offset, line) in &self.starts {
+            out.write_all(&offset.to_le_bytes())?;
+            out.write_all(&line.to_le_bytes())?;
+        }
+        out.flush()
+    }
+
+    fn read(path: &str) -> io::Result<Self> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        let invalid = || io::Error::new(io::ErrorKind::InvalidData, "not a pcsv index");
+        if bytes.len() < 41 || &bytes[..8] != MAGIC {
+            return Err(invalid());
+        }
+        let words: Vec<u64> = bytes[9..]
+            .chunks_exact(8)
+            .map(|word| u64::from_le_bytes(word.try_into().unwrap_or_default()))
+            .collect();
+        if words[3] != STRIDE {
+            return Err(invalid());
+        }
+        Ok(Index {
+            delimiter: bytes[8],
+            source_len: words[0],
+            source_modified: words[1],
+            records: words[2],
+            starts: words[4..]
+                .chunks_exact(2)
+                .map(|pair| (pair[0], pair[1]))
+                .collect(),
+        })
+    }
+
+    // The header, read and checked the way the whole file would be
+    fn read_headers(
+        &self,
+        input: &str,
+        options: &ReadOptions,
+    ) -> Result<Option<Vec<String>>, Box<dyn std::error::Error>> {
+        let end = self.starts.first().map(|&(offset, _)| offset);
+        let text = read_text(input, 0, end)?;
+        let ((headers, _), _) = parse_csv(&text, self.delimiter, options, false)?;
+        Ok(headers)
+    }
+
+    // Reads the records in `range` from the blocks of STRIDE records they
+    // are in. They go through the same checks and repairs as when the
+    // whole file is read, and a partial last record is left out.
+    fn read_records(
+        &self,
+        input: &str,
+        range: Range<u64>,
+        headers: Option<&[String]>,
+        options: &ReadOptions,
+    ) -> Result<Vec<Vec<Field>>, Box<dyn std::error::Error>> {
+        if range.is_empty() {
+            return Ok(Vec::new());
+        }
+        let (first, last) = (
+            (range.start / STRIDE) as usize,
+            ((range.end - 1) / STRIDE) as usize,
+        );
+        let (offset, line) = self.starts[first];
+        let end = self.starts.get(last + 1).map(|&(offset, _)| offset);
+        let text = read_text(input, offset, end)?;
+        let (records, _) = parse_records(&text, 0, line, headers, self.delimiter, options, false)?;
+
+        let skip = (range.start - first as u64 * STRIDE) as usize;
+        Ok(records
+            .into_iter()
+            .skip(skip)
+            .take((range.end - range.start) as usize)
+            .collect())
+    }
+}
+
+// The text of a file from byte `start` up to `end` (or its end). A CRLF
+// line ending split at `end` is kept whole.
+fn read_text(
+    input: &str,
+    start: u64,
+    end: Option<u64>,
+) -> Result<Arc<Text>, Box<dyn std::error::Error>> {
+    let mut file = File::open(input)?;
+    file.seek(SeekFrom::Start(start))?;
+    let mut bytes = Vec::new();
+    match end {
+        Some(end) => {
+            file.take(end - start + 1).read_to_end(&mut bytes)?;
+            if bytes.len() as u64 > end - start && bytes.last() != Some(&b'\n') {
+                bytes.pop();
+            }
+        }
+        None => {
+            file.read_to_end(&mut bytes)?;
+        }
+    }
+    let len = bytes.len();
+    let text = Text::new(source::Source::Owned(bytes), 0..len)
+        .map_err(|_| format!("{} is not valid UTF-8", input))?;
+    Ok(Arc::new(text))
+}
+
+// Scans the file once and writes its index
+pub fn run(args: &IndexArgs, options: &ReadOptions) -> Result<(), Box<dyn std::error::Error>> {
+    let input = args.input.as_str();
+    if input == "-" {
+        return Err("standard input can't be indexed".into());
+    }
+    if source::is_remote(input) {
+        return Err("remote files can't be indexed".into());
+    }
+    let mut head = Vec::new();
+    File::open(input)?.take(8192).read_to_end(&mut head)?;
+    if Compression::detect(&head, Some(input)).is_some() {
+        return Err(format!(
+            "{} is compressed; only plain CSV and TSV files can be indexed",
+            input
+        )
+        .into());
+    }
+    let delimiter = match options
+        .format
+        .unwrap_or_else(|| InputFormat::detect(input, &head))
+    {
+        InputFormat::Csv => b',',
+        InputFormat::Tsv => b'\t',
+        _ => {
+            return Err(format!(
+                "only CSV and TSV files can be indexed, {} is not one",
+                input
+            )
+            .into())
+        }
+    };
+
+    let (source_len, source_modified) = stamp(input)?;
+    // Records of the wrong length are indexed too, and repaired or
+    // explained when they are read
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(BufReader::new(File::open(input)?));
+    rdr.byte_headers()?;
+    let mut record = csv::ByteRecord::new();
+    let mut starts = Vec::new();
+    let mut records = 0;
+    while rdr.read_byte_record(&mut record)? {
+        if records % STRIDE == 0 {
+            starts.push(
+                record
+                    .position()
+                    .map_or((0, 1), |pos| (pos.byte(), pos.line())),
+            );
+        }
+        records += 1;
+    }
+
+    let index = Index {
+        delimiter,
+        source_len,
+        source_modified,
+        records,
+        starts,
+    };
+    let path = index_path(input);
+    index.write(&path)?;
+    eprintln!("Indexed {} records of {} in {}", records, input, path);
+    Ok(())
+}
+
+// A slice of a file's records, from `--tail` or `--rows`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Window {
+    Tail(usize),
+    Rows(Option<Bound>, Option<Bound>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Bound {
+    // 1-based, like `--show-row-numbers`
+    Row(usize),
+    Percent(f64),
+}
+
+fn parse_bound(text: &str) -> Result<Option<Bound>, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(None);
+    }
+    if let Some(percent) = text.strip_suffix('%') {
+        return match percent.trim().parse::<f64>() {
+            Ok(percent) if (0.0..=100.0).contains(&percent) => Ok(Some(Bound::Percent(percent))),
+            _ => Err(format!(
+                "'{}' is not a percentage between 0% and 100%",
+                text
+            )),
+        };
+    }
+    match text.parse::<usize>() {
+        Ok(row) if row > 0 => Ok(Some(Bound::Row(row))),
+        _ => Err(format!("'{}' is not a row number (rows start at 1)", text)),
+    }
+}
+
+// `START:END` (inclusive, either side optional), a single row, or a
+// percentage to read on from
+pub fn parse_rows(text: &str) -> Result<Window, String> {
+    match text.split_once(':') {
+        Some((start, end)) => Ok(Window::Rows(parse_bound(start)?, parse_bound(end)?)),
+        None => match parse_bound(text)? {
+            Some(Bound::Percent(percent)) => Ok(Window::Rows(Some(Bound::Percent(percent)), None)),
+            row => Ok(Window::Rows(row, row)),
+        },
+    }
+}
+
+impl Window {
+    // The 0-based record range this window covers out of `total`
+    pub fn range(self, total: usize) -> Range<usize> {
+        let position = |bound: Bound, is_end: bool| match bound {
+            Bound::Row(row) if is_end => row.min(total),
+            Bound::Row(row) => (row - 1).min(total),
+            Bound::Percent(percent) => {
+                ((total as f64 * percent / 100.0).round() as usize).min(total)
+            }
+        };
+        match self {
+            Window::Tail(count) => total.saturating_sub(count)..total,
+            Window::Rows(start, end) => {
+                let start = start.map_or(0, |bound| position(bound, false));
+                let end = end.map_or(total, |bound| position(bound, true));
+                start..end.max(start)
+            }
+        }
+    }
+}
+
+// The index for a file, if there is one that still matches it
+fn fresh_index(input: &str, options: &ReadOptions) -> Option<Index> {
+    if input == "-" || !matches!(options.encoding, None | Some(Encoding::Utf8)) {
+        return None;
+    }
+    let path = index_path(input);
+    let index = Index::read(&path).ok()?;
+    if stamp(input).ok()? != (index.source_len, index.source_modified) {
+        // Said once up front, not on every reload of a followed file
+        if !options.quiet {
+            eprintln!(
+                "warning: {} is out of date, reading the whole file; run `pcsv index {}` to rebuild it",
+                path, input
+            );
+        }
+        return None;
+    }
+    Some(index)
+}
+
+// Reads only the records in the window, through the file's index when it
+// has one. `leading` rows after the header (group labels) are always kept.
+pub fn read_window(
+    input: &str,
+    window: Window,
+    leading: usize,
+    options: &ReadOptions,
+) -> Result<CsvData, Box<dyn std::error::Error>> {
+    if source::is_remote(input) {
+        if let Some(data) = remote::read_window(input, window, leading, options)? {
+            return Ok(data);
+        }
+    }
+    let Some(index) = fresh_index(input, options) else {
+        let (headers, mut records) = read_csv_data(input, options)?;
+        let leading = leading.min(records.len());
+        let range = window.range(records.len() - leading);
+        let mut rows: Vec<Vec<Field>> = records.drain(..leading).collect();
+        rows.extend(records.drain(range));
+        return Ok((headers, rows));
+    };
+
+    let headers = index.read_headers(input, options)?;
+    let leading = (leading as u64).min(index.records);
+    let range = window.range((index.records - leading) as usize);
+    let mut records = index.read_records(input, 0..leading, headers.as_deref(), options)?;
+    records.extend(index.read_records(
+        input,
+        leading + range.start as u64..leading + range.end as u64,
+        headers.as_deref(),
+        options,
+    )?);
+    Ok((headers, records))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_the_records_read_through_the_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.csv");
+        let mut text = String::from("id,name\n");
+        for id in 0..3000 {
+            text += &format!("{},n{}\n", id, id);
+        }
+        text += "3000,a,b\n3001,\"cut";
+        fs::write(&path, text).unwrap();
+        let input = path.to_str().unwrap();
+        let args = IndexArgs {
+            input: input.to_string(),
+        };
+        run(&args, &ReadOptions::from_args(&[])).unwrap();
+
+        let options = ReadOptions::from_args(&["--repair", "merge-extra-into=name"]);
+        let (headers, records) = read_window(input, Window::Tail(2), 0, &options).unwrap();
+        assert_eq!(headers.unwrap(), ["id", "name"]);
+        let rows: Vec<Vec<String>> = records
+            .iter()
+            .map(|record| record.iter().map(Field::to_string).collect())
+            .collect();
+        // The partial last record is left out, but still counted by the index
+        assert_eq!(rows, [["3000", "a,b"]]);
+
+        let options = ReadOptions::from_args(&["--max-cell-size", "4"]);
+        let window = Window::Rows(Some(Bound::Row(1025)), None);
+        let error = read_window(input, window, 0, &options).unwrap_err();
+        assert!(error.to_string().contains("line 1026"));
+    }
+}
@@ -0,0 +1,208 @@
+use crate::config::{self, ColorScheme, HeaderStyle, PagerConfig, TruncateMode};
+use clap::{Args, Subcommand};
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Args)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub action: ConfigAction,
+}
+
+#[derive(Subcommand)]
+pub enum ConfigAction {
+    /// Write the default configuration, with every option explained, to start from
+    Init(InitArgs),
+}
+
+#[derive(Args)]
+pub struct InitArgs {
+    // Default: config.toml in the user's config directory
+    #[arg(long, value_name = "FILE")]
+    pub path: Option<PathBuf>,
+
+    #[arg(long)]
+    pub overwrite: bool,
+
+    #[arg(long, conflicts_with_all = ["path", "overwrite"])]
+    pub stdout: bool,
+}
+
+fn header_name(style: HeaderStyle) -> &'static str {
+    match style {
+        HeaderStyle::Full => "full",
+        HeaderStyle::Compact => "compact",
+        HeaderStyle::Hidden => "hidden",
+    }
+}
+
+fn mode_name(mode: TruncateMode) -> &'static str {
+    match mode {
+        TruncateMode::Start => "start",
+        TruncateMode::Middle => "middle",
+        TruncateMode::End => "end",
+    }
+}
+
+fn quoted(words: &[String]) -> String {
+    words.iter().map(|word| format!("{:?}", word)).collect::<Vec<_>>().join(", ")
+}
+
+// The default configuration as a config file, every option set to what
+// pcsv uses without one, or commented out where it has no default
+pub fn default_config() -> String {
+    let scheme = ColorScheme::default();
+    let pager = PagerConfig::default();
+    let colors = &scheme.data_types;
+    format!(
+        r##"# pcsv configuration. Every option is set to its default; change what
+# you like and delete the rest. `pcsv --set KEY=VALUE` overrides one for
+# a single run, e.g. `--set header=#FFAA00`.
+
+# A built-in theme (or one in the `themes` directory next to this file)
+# to start from. The colors below go on top of it.
+# theme = "dracula"
+
+# Header color
+header = "{header}"
+
+# Background of the pager's status bar
+status_bar = "{status_bar}"
+
+# Background of HTML and xlsx output
+background = "{background}"
+
+# A glyph before each header name for its column's type: "off",
+# "unicode" (# ƒ 📅 ✓ ¤ 🔗 ✉ 🔑 🌐) or "ascii" (# f d b $ u @ k n)
+type_icons = "off"
+
+# Conditional formatting: styles cells whose value passes every test of
+# a rule (gt, ge, lt, le, eq, ne and a `matches` regex). The first rule
+# that matches wins; without a column a rule looks at every column.
+# rules = [
+#     {{ column = "amount", gt = 1000, color = "#F38BA8", bold = true }},
+#     {{ column = "status", eq = "failed", color = "#F38BA8" }},
+#     {{ matches = "^N/A$", color = "#585B70" }},
+# ]
+
+# Labels drawn over groups of columns, as an extra header line
+# column_groups = [
+#     {{ label = "Q1", columns = ["Jan", "Feb", "Mar"] }},
+# ]
+
+# The color of each detected type of value
+[data_types]
+text = "{text}"
+date = "{date}"
+float_number = "{float_number}"
+int_number = "{int_number}"
+currency = "{currency}"
+url = "{url}"
+email = "{email}"
+uuid = "{uuid}"
+network = "{network}"
+boolean = "{boolean}"
+empty = "{empty}"
+
+# Pager configuration
+[pager]
+scroll_single_line = {scroll_single_line}     # Lines to scroll with 'j' and 'k'
+scroll_multi_line = {scroll_multi_line}     # Lines to scroll with 'J' and 'K'
+header = "{pager_header}"            # Table header: "full", "compact" or "hidden" (switch with 'H')
+compact_header_width = {compact_header_width}   # Characters of each name the compact header keeps
+fit_columns = {fit_columns}         # Narrow the widest columns to fit the terminal, and again on resize
+
+# Cutting long values, in printed tables and the pager
+[truncate]
+# max_width = 30            # Cut values wider than this many columns (default: never)
+mode = "{truncate_mode}"               # Where values are cut: "start", "middle" or "end"
+# columns = {{ path = "start", request_id = "middle" }}
+
+# Colors used by `pcsv diff`
+[diff]
+added = "{added}"
+removed = "{removed}"
+changed = "{changed}"
+
+# The words for true and false, in any case. Adding "1" and "0" makes
+# those booleans rather than numbers.
+[booleans]
+true = [{truthy}]
+false = [{falsy}]
+
+# How numbers are written in the files read, e.g. decimal = "," and
+# thousands = "." for 1.234,56
+[numbers]
+decimal = "{decimal}"
+# thousands = ","
+
+# Values that mean a value is missing, shown like empty cells and
+# counted as missing by --stats and --missing
+[nulls]
+tokens = []                 # e.g. ["NA", "N/A", "null", "-", "NaN"]
+# placeholder = "∅"         # Shown in place of every missing value
+
+# Types of your own, recognized by a regex before the built-in ones and
+# shown in their own color. Repeat the block for more.
+# [[custom_types]]
+# name = "sku"
+# pattern = '^SKU-\d+$'
+# color = "#F5C2E7"
+"##,
+        header = scheme.header,
+        status_bar = scheme.status_bar,
+        background = scheme.background,
+        text = colors.text,
+        date = colors.date,
+        float_number = colors.float_number,
+        int_number = colors.int_number,
+        currency = colors.currency.as_deref().unwrap_or(&colors.float_number),
+        url = colors.url.as_deref().unwrap_or(&colors.text),
+        email = colors.email.as_deref().unwrap_or(&colors.text),
+        uuid = colors.uuid.as_deref().unwrap_or(&colors.text),
+        network = colors.network.as_deref().unwrap_or(&colors.text),
+        boolean = colors.boolean,
+        empty = colors.empty,
+        scroll_single_line = pager.scroll_single_line,
+        scroll_multi_line = pager.scroll_multi_line,
+        pager_header = header_name(pager.header),
+        compact_header_width = pager.compact_header_width,
+        fit_columns = pager.fit_columns,
+        truncate_mode = mode_name(scheme.truncate.mode),
+        decimal = scheme.numbers.decimal,
+        truthy = quoted(&scheme.booleans.truthy),
+        falsy = quoted(&scheme.booleans.falsy),
+        added = scheme.diff.added,
+        removed = scheme.diff.removed,
+        changed = scheme.diff.changed,
+    )
+}
+
+pub fn run(args: &ConfigArgs) -> Result<(), Box<dyn std::error::Error>> {
+    match &args.action {
+        ConfigAction::Init(init) => run_init(init),
+    }
+}
+
+fn run_init(args: &InitArgs) -> Result<(), Box<dyn std::error::Error>> {
+    let content = default_config();
+    if args.stdout {
+        print!("{}", content);
+        return Ok(());
+    }
+    let path = match &args.path {
+        Some(path) => path.clone(),
+        None => config::user_config_dir()
+            .ok_or("no config directory: set HOME or XDG_CONFIG_HOME, or pass --path")?
+            .join("config.toml"),
+    };
+    if path.exists() && !args.overwrite {
+        return Err(format!("{} already exists, --overwrite replaces it", path.display()).into());
+    }
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir).map_err(|e| format!("failed to create {}: {}", dir.display(), e))?;
+    }
+    fs::write(&path, content).map_err(|e| format!("failed to write {}: {}", path.display(), e))?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
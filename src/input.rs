@@ -0,0 +1,285 @@
+use crate::repair::{self, Repair};
+use clap::ValueEnum;
+use std::borrow::Cow;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum InputFormat {
+    Csv,
+    Tsv,
+    // Any spreadsheet calamine reads: xlsx, xlsm, xlsb, xls and ods
+    Xlsx,
+    Parquet,
+    // An array of objects or JSON Lines
+    Json,
+    Sqlite,
+}
+
+impl InputFormat {
+    // From the extension (looking through encryption and compression
+    // suffixes), then the
+    // magic bytes of Parquet, SQLite or a zip (xlsx, ods) or OLE (xls) container,
+    // then a leading `[` or `{` for JSON
+    pub fn detect(path: &str, bytes: &[u8]) -> Self {
+        let name = path.to_lowercase();
+        let strip = |exts: &[&str], path: &str| {
+            exts.iter()
+                .find_map(|ext| path.strip_suffix(&format!(".{}", ext)))
+                .map(str::to_string)
+                .unwrap_or_else(|| path.to_string())
+        };
+        let name = strip(&["age", "gpg", "pgp", "asc"], &name);
+        let name = strip(&["gz", "gzip", "zst", "zstd", "bz2"], &name);
+        match Path::new(&name).extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => InputFormat::Csv,
+            Some("tsv" | "tab") => InputFormat::Tsv,
+            Some("json" | "jsonl" | "ndjson") => InputFormat::Json,
+            Some("xlsx" | "xlsm" | "xlsb" | "xls" | "ods") => InputFormat::Xlsx,
+            Some("parquet" | "pq") => InputFormat::Parquet,
+            Some("sqlite" | "sqlite3" | "db") => InputFormat::Sqlite,
+            _ if bytes.starts_with(b"SQLite format 3\0") => InputFormat::Sqlite,
+            _ if bytes.starts_with(b"PAR1") => InputFormat::Parquet,
+            _ if bytes.starts_with(b"PK\x03\x04")
+                || bytes.starts_with(&[0xd0, 0xcf, 0x11, 0xe0]) =>
+            {
+                InputFormat::Xlsx
+            }
+            _ => match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+                Some(b'[' | b'{') => InputFormat::Json,
+                _ => InputFormat::Csv,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    #[value(name = "utf-8", alias = "utf8")]
+    Utf8,
+    #[value(name = "utf-16le", alias = "utf16le")]
+    Utf16Le,
+    #[value(name = "utf-16be", alias = "utf16be")]
+    Utf16Be,
+    #[value(name = "latin1", alias = "iso-8859-1")]
+    Latin1,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ReadOptions {
+    #[arg(long, value_enum, global = true)]
+    pub format: Option<InputFormat>,
+
+    #[arg(long, value_enum, global = true)]
+    pub encoding: Option<Encoding>,
+
+    #[arg(long, global = true)]
+    pub force: bool,
+
+    #[arg(long, global = true)]
+    pub sheet: Option<String>,
+
+    #[arg(long, global = true)]
+    pub table: Option<String>,
+
+    #[arg(long, value_name = "SQL", global = true, conflicts_with = "table")]
+    pub query: Option<String>,
+
+    // An age identity file, for files encrypted to a key rather than a passphrase
+    #[arg(long, value_name = "FILE", global = true)]
+    pub identity: Option<PathBuf>,
+
+    #[arg(long, value_name = "N", default_value_t = 10_000, global = true)]
+    pub max_columns: usize,
+
+    #[arg(long, value_name = "BYTES", default_value_t = 1 << 20, global = true)]
+    pub max_cell_size: usize,
+
+    #[arg(long, value_name = "REPAIR", value_parser = repair::parse_repair, global = true)]
+    pub repair: Option<Repair>,
+
+    // Set for the reloads of a file in the pager, which owns the screen:
+    // warnings are left out rather than printed over it
+    #[arg(skip)]
+    pub quiet: bool,
+}
+
+#[cfg(test)]
+impl ReadOptions {
+    // The options a command line such as `--repair merge-extra-into=name`
+    // gives, for tests
+    pub fn from_args(args: &[&str]) -> Self {
+        use clap::Parser;
+
+        #[derive(Parser)]
+        struct Options {
+            #[command(flatten)]
+            read: ReadOptions,
+        }
+        Options::parse_from(std::iter::once("pcsv").chain(args.iter().copied())).read
+    }
+}
+
+// Checks a CSV record against --max-columns and --max-cell-size. A file
+// past those is almost certainly read with the wrong delimiter or has an
+// unclosed quote, and laying it out would take ages.
+pub fn check_record<'a>(
+    fields: impl IntoIterator<Item = &'a [u8]>,
+    line: u64,
+    content: &str,
+    delimiter: u8,
+    options: &ReadOptions,
+) -> Result<(), String> {
+    let (mut columns, mut largest) = (0, 0);
+    for field in fields {
+        columns += 1;
+        largest = largest.max(field.len());
+    }
+    if columns > options.max_columns {
+        return Err(format!(
+            "line {} has {} columns, more than --max-columns ({}). {}Pass a larger --max-columns to read it anyway",
+            line,
+            columns,
+            options.max_columns,
+            delimiter_hint(content, delimiter)
+        ));
+    }
+    if largest > options.max_cell_size {
+        return Err(format!(
+            "a cell on line {} is {} bytes, more than --max-cell-size ({}). This usually comes from an unclosed quote or the wrong delimiter. {}Pass a larger --max-cell-size to read it anyway",
+            line,
+            largest,
+            options.max_cell_size,
+            delimiter_hint(content, delimiter)
+        ));
+    }
+    Ok(())
+}
+
+// Lines looked at when guessing the delimiter
+const HINT_LINES: usize = 5;
+
+// Suggests another delimiter when one splits the first lines into the same
+// number of columns each
+fn delimiter_hint(content: &str, delimiter: u8) -> String {
+    let lines: Vec<&[u8]> = content
+        .as_bytes()
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .take(HINT_LINES)
+        .collect();
+    let consistent = |candidate: u8| {
+        let mut counts = lines
+            .iter()
+            .map(|line| line.iter().filter(|&&b| b == candidate).count());
+        counts
+            .next()
+            .is_some_and(|first| first > 0 && counts.all(|count| count == first))
+    };
+    let current = delimiter as char;
+    match [b'\t', b',', b';', b'|']
+        .into_iter()
+        .find(|&candidate| candidate != delimiter && consistent(candidate))
+    {
+        Some(b'\t') => "The file looks tab-separated, try --format tsv. ".to_string(),
+        Some(b',') => "The file looks comma-separated, try --format csv. ".to_string(),
+        Some(other) => format!(
+            "The file looks separated by '{}' rather than {:?}. ",
+            other as char, current
+        ),
+        None => String::new(),
+    }
+}
+
+// Bytes looked at when guessing whether a file is text
+const SNIFF_LEN: usize = 8192;
+
+// Whether a file's last record, `record` from where it starts to the end
+// of the file, stops inside a quoted value: the file was cut off before
+// the closing quote, whatever follows the opening one
+pub fn ends_in_quotes(record: &[u8], delimiter: u8) -> bool {
+    let (mut quoted, mut field_start) = (false, true);
+    let mut bytes = record.iter().peekable();
+    while let Some(&byte) = bytes.next() {
+        if quoted {
+            // `""` is a quote inside the value
+            if byte == b'"' && bytes.next_if_eq(&&b'"').is_none() {
+                quoted = false;
+            }
+        } else if byte == b'"' && field_start {
+            quoted = true;
+        }
+        field_start = !quoted && (byte == delimiter || byte == b'\n' || byte == b'\r');
+    }
+    quoted
+}
+
+// Why the data doesn't look like text, if it doesn't
+pub fn binary_reason(bytes: &[u8]) -> Option<&'static str> {
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if sample.contains(&0) {
+        return Some("it contains NUL bytes");
+    }
+    let control = sample
+        .iter()
+        .filter(|&&b| (b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c)) || b == 0x7f)
+        .count();
+    // Bytes above 0x7f count as printable, they are usually UTF-8 or Latin-1
+    if control * 10 > sample.len() {
+        return Some("most of it is not printable");
+    }
+    None
+}
+
+pub fn decode_utf16(bytes: &[u8], little_endian: bool) -> String {
+    let units = bytes.chunks_exact(2).map(|pair| {
+        if little_endian {
+            u16::from_le_bytes([pair[0], pair[1]])
+        } else {
+            u16::from_be_bytes([pair[0], pair[1]])
+        }
+    });
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+// Turns raw input into text, borrowing it when it already is UTF-8.
+// Without `--encoding`, a UTF-16 byte order mark is honoured and anything
+// else must be UTF-8.
+pub fn decode<'a>(bytes: &'a [u8], name: &str, options: &ReadOptions) -> Result<Cow<'a, str>, String> {
+    let encoding = match options.encoding {
+        Some(encoding) => encoding,
+        None if bytes.starts_with(&[0xff, 0xfe]) => Encoding::Utf16Le,
+        None if bytes.starts_with(&[0xfe, 0xff]) => Encoding::Utf16Be,
+        None => Encoding::Utf8,
+    };
+
+    if encoding == Encoding::Utf8 && !options.force {
+        if let Some(reason) = binary_reason(bytes) {
+            return Err(format!(
+                "{} looks like a binary file ({}); pass --encoding if it is UTF-16 or Latin-1 text, --format to choose the file format, or --force to show it anyway",
+                name, reason
+            ));
+        }
+    }
+
+    let text = match encoding {
+        Encoding::Utf8 if options.force => String::from_utf8_lossy(bytes),
+        Encoding::Utf8 => Cow::Borrowed(std::str::from_utf8(bytes).map_err(|_| {
+            format!(
+                "{} is not valid UTF-8; try --encoding latin1, or --force to replace invalid bytes",
+                name
+            )
+        })?),
+        Encoding::Utf16Le => Cow::Owned(decode_utf16(bytes, true)),
+        Encoding::Utf16Be => Cow::Owned(decode_utf16(bytes, false)),
+        Encoding::Latin1 => Cow::Owned(bytes.iter().map(|&b| b as char).collect()),
+    };
+    Ok(match text {
+        Cow::Borrowed(text) => Cow::Borrowed(text.strip_prefix('\u{feff}').unwrap_or(text)),
+        Cow::Owned(text) => match text.strip_prefix('\u{feff}') {
+            Some(rest) => Cow::Owned(rest.to_string()),
+            None => Cow::Owned(text),
+        },
+    })
+}
@@ -0,0 +1,138 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+// Keys given with `--keys`, pressed in the pager one after the other
+#[derive(Debug, Clone)]
+pub struct KeyScript(pub Vec<KeyEvent>);
+
+// A key written as `<Name>`, such as `<Enter>`
+fn named_key(name: &str) -> Option<KeyEvent> {
+    let code = match name.to_ascii_lowercase().as_str() {
+        "enter" | "cr" => KeyCode::Enter,
+        "esc" => KeyCode::Esc,
+        "space" => KeyCode::Char(' '),
+        "tab" => KeyCode::Tab,
+        "s-tab" | "backtab" => KeyCode::BackTab,
+        "bs" | "backspace" => KeyCode::Backspace,
+        "up" => KeyCode::Up,
+        "down" => KeyCode::Down,
+        "left" => KeyCode::Left,
+        "right" => KeyCode::Right,
+        "pgup" | "pageup" => KeyCode::PageUp,
+        "pgdn" | "pagedown" => KeyCode::PageDown,
+        "home" => KeyCode::Home,
+        "end" => KeyCode::End,
+        "lt" => KeyCode::Char('<'),
+        _ => return None,
+    };
+    Some(KeyEvent::new(code, KeyModifiers::NONE))
+}
+
+fn char_key(c: char) -> KeyEvent {
+    KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE)
+}
+
+// `:` commands that take the word after them too: `:export out.csv`
+const WITH_ARGUMENT: [&str; 1] = ["export"];
+
+// Every character is a key of its own, except whitespace, which only
+// separates them, and keys without a character of their own, written in
+// angle brackets: `G <PgUp> l q`. What follows a key that opens a prompt
+// (`/`, `:` or `&`) is typed into it up to the end of the word and entered,
+// as in `/error n :120 &qty>5`; a word in quotes is typed whole, spaces
+// and all: `/"out of stock"`.
+pub fn parse_keys(script: &str) -> Result<KeyScript, String> {
+    let mut keys = Vec::new();
+    let mut rest = script.trim_start();
+    while !rest.is_empty() {
+        let (key, after) = next_key(rest)?;
+        keys.push(key);
+        rest = after;
+        if let KeyCode::Char(prompt @ ('/' | ':' | '&')) = key.code {
+            rest = prompt_text(prompt, rest, &mut keys)?;
+        }
+        rest = rest.trim_start();
+    }
+    Ok(KeyScript(keys))
+}
+
+// The key `text` starts with, and the text after it
+fn next_key(text: &str) -> Result<(KeyEvent, &str), String> {
+    if text.starts_with('<') {
+        let end = text
+            .find('>')
+            .ok_or_else(|| format!("unclosed '<' in keys '{}'", text))?;
+        let name = &text[1..end];
+        let key = named_key(name).ok_or_else(|| {
+            format!(
+                "unknown key '<{}>', expected a character or one of <Enter>, <Esc>, <Space>, <Tab>, <S-Tab>, <BS>, <Up>, <Down>, <Left>, <Right>, <PgUp>, <PgDn>, <Home>, <End> or <lt>",
+                name
+            )
+        })?;
+        return Ok((key, &text[end + 1..]));
+    }
+    let c = text.chars().next().ok_or("no key left")?;
+    Ok((char_key(c), &text[c.len_utf8()..]))
+}
+
+// Types the text of the prompt `prompt` opened and enters it, unless an
+// `<Enter>` or `<Esc>` in the text ends the prompt first. Returns the
+// script after the text.
+fn prompt_text<'a>(prompt: char, mut rest: &'a str, keys: &mut Vec<KeyEvent>) -> Result<&'a str, String> {
+    let mut typed = String::new();
+    loop {
+        if let Some(quote) = rest.chars().next().filter(|&c| c == '"' || c == '\'') {
+            let end = rest[1..]
+                .find(quote)
+                .ok_or_else(|| format!("unclosed {} in keys '{}'", quote, rest))?;
+            let quoted = &rest[1..end + 1];
+            typed.push_str(quoted);
+            keys.extend(quoted.chars().map(char_key));
+            rest = &rest[end + 2..];
+        }
+        while rest.starts_with(|c: char| !c.is_whitespace()) {
+            let (key, after) = next_key(rest)?;
+            rest = after;
+            keys.push(key);
+            match key.code {
+                KeyCode::Enter | KeyCode::Esc => return Ok(rest),
+                KeyCode::Char(c) => typed.push(c),
+                _ => {}
+            }
+        }
+        let argument = prompt == ':' && WITH_ARGUMENT.contains(&typed.as_str());
+        rest = rest.trim_start();
+        if !argument || rest.is_empty() {
+            break;
+        }
+        keys.push(char_key(' '));
+        typed.push(' ');
+    }
+    keys.push(KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE));
+    Ok(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(script: &str) -> String {
+        let keys = parse_keys(script).unwrap().0;
+        keys.iter()
+            .map(|key| match key.code {
+                KeyCode::Char(c) => c.to_string(),
+                KeyCode::Enter => "⏎".to_string(),
+                KeyCode::Esc => "⎋".to_string(),
+                code => format!("<{:?}>", code),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn enters_the_text_typed_into_prompts() {
+        assert_eq!(typed("G /error n n :export out.csv q"), "G/error⏎nn:export out.csv⏎q");
+        assert_eq!(typed(":120<Enter> <PgUp> q"), ":120⏎<PageUp>q");
+        assert_eq!(typed("/\"out of stock\" &'qty > 5'<Esc>"), "/out of stock⏎&qty > 5⎋");
+        assert_eq!(typed("cnote<Enter> :export"), "cnote⏎:export⏎");
+        assert!(parse_keys("/\"open").is_err());
+    }
+}
@@ -0,0 +1,204 @@
+use crate::config::ColorScheme;
+use crate::stats::{column_names, dominant_type};
+use crate::store::Field;
+use crate::{detect_data_type_cached, hides_text, new_table, null_placeholder, paint, raw_text, DataType};
+use comfy_table::presets::UTF8_FULL_CONDENSED;
+use comfy_table::{Attribute, Cell, CellAlignment, Table};
+use crate::pager::{PagerContent, RenderFn};
+use std::collections::HashSet;
+use std::sync::Arc;
+
+// A `key,value` dump: two columns of unique, textual keys
+pub fn looks_like_properties(headers: Option<&[String]>, records: &[Vec<Field>]) -> bool {
+    if headers.is_some_and(|h| h.len() != 2) || records.len() < 2 {
+        return false;
+    }
+    let mut keys = HashSet::new();
+    for record in records {
+        if record.len() != 2 || !keys.insert(record[0].as_str()) {
+            return false;
+        }
+    }
+    let keys: Vec<&str> = keys.into_iter().collect();
+    dominant_type(&keys) == DataType::Text
+}
+
+pub fn create_properties_table(
+    headers: Option<Vec<String>>,
+    records: Vec<Vec<Field>>,
+    scheme: &ColorScheme,
+) -> Table {
+    // Condensed rows with bold, right-aligned keys read like a settings listing
+    let mut table = new_table();
+    table.load_preset(UTF8_FULL_CONDENSED);
+    if let Some(h) = headers {
+        table.set_header(h.iter().map(|name| Cell::new(name).fg(scheme.header_color())));
+    }
+
+    for record in records {
+        let mut cells = record.into_iter();
+        let key = cells.next().unwrap_or_default();
+        // Anything after the value belongs to it; blank keys are kept visible
+        let value = cells.collect::<Vec<_>>().join(",");
+        table.add_row(vec![
+            Cell::new(key)
+                .fg(scheme.header_color())
+                .add_attribute(Attribute::Bold)
+                .set_alignment(CellAlignment::Right),
+            Cell::new(&value).fg(scheme.cell_color(&detect_data_type_cached(&value))),
+        ]);
+    }
+
+    table
+}
+
+fn name_width(names: &[String]) -> usize {
+    names
+        .iter()
+        .map(|name| name.chars().count())
+        .max()
+        .unwrap_or(0)
+}
+
+// The lines of one record in the vertical layout: a `── Row N` rule,
+// shortened to fit `max_width` when given, and one line per field,
+// followed by its raw text when some of it doesn't show
+fn record_block(
+    names: &[String],
+    name_width: usize,
+    row_idx: usize,
+    record: &[Field],
+    scheme: &ColorScheme,
+    styled: bool,
+    max_width: Option<usize>,
+) -> Vec<String> {
+    let paint_if = |text: &str, color| {
+        if styled {
+            paint(text, color)
+        } else {
+            text.to_string()
+        }
+    };
+
+    let mut lines = Vec::new();
+    let title = format!("── Row {} ", row_idx + 1);
+    let mut rule_len = (name_width + 2).saturating_sub(title.chars().count()) + 20;
+    if let Some(width) = max_width {
+        rule_len = rule_len.min(width.saturating_sub(title.chars().count()));
+    }
+    let rule = "─".repeat(rule_len);
+    lines.push(paint_if(&format!("{}{}", title, rule), scheme.header_color()));
+
+    for (name, val) in names.iter().zip(record) {
+        let color = scheme.cell_color(&detect_data_type_cached(val));
+        let shown = null_placeholder(val, scheme).unwrap_or(val);
+        // Continuation lines of multi-line values stay aligned
+        for (line_idx, part) in shown.split('\n').enumerate() {
+            let label = if line_idx == 0 {
+                format!("{:>width$}:", name, width = name_width)
+            } else {
+                " ".repeat(name_width + 1)
+            };
+            lines.push(format!(
+                "{} {}",
+                paint_if(&label, scheme.header_color()),
+                paint_if(part, color)
+            ));
+        }
+        if hides_text(val) {
+            lines.push(format!(
+                "{} {}",
+                " ".repeat(name_width + 1),
+                paint_if(&format!("raw {}", raw_text(val)), scheme.cell_color(&DataType::Empty))
+            ));
+        }
+    }
+    lines
+}
+
+// One `field: value` block per record, like MySQL's `\G`. Returns the
+// lines and the line each record starts at.
+pub fn vertical_lines(
+    headers: Option<&[String]>,
+    records: &[Vec<Field>],
+    scheme: &ColorScheme,
+    styled: bool,
+    max_width: Option<usize>,
+) -> (Vec<String>, Vec<usize>) {
+    let names = column_names(headers, records);
+    let width = name_width(&names);
+
+    let mut lines = Vec::new();
+    let mut starts = Vec::with_capacity(records.len());
+    for (row_idx, record) in records.iter().enumerate() {
+        starts.push(lines.len());
+        lines.extend(record_block(&names, width, row_idx, record, scheme, styled, max_width));
+    }
+
+    (lines, starts)
+}
+
+// Plain sentences for screen readers and braille displays, without borders
+// or colors: the columns first, then a line per record with every value
+// followed by its type in words, e.g. `Row 1 of 30. id: 7, integer.`
+pub fn accessible_lines(headers: Option<&[String]>, records: &[Vec<Field>]) -> Vec<String> {
+    let names = column_names(headers, records);
+    let plural = |count: usize, noun: &str| match count {
+        1 => format!("1 {}", noun),
+        count => format!("{} {}s", count, noun),
+    };
+    let mut lines = vec![format!(
+        "{}, {}: {}.",
+        plural(records.len(), "row"),
+        plural(names.len(), "column"),
+        names.join(", ")
+    )];
+    for (row_idx, record) in records.iter().enumerate() {
+        let mut line = format!("Row {} of {}.", row_idx + 1, records.len());
+        for (name, val) in names.iter().zip(record) {
+            let ty = detect_data_type_cached(val);
+            if ty == DataType::Empty {
+                line.push_str(&format!(" {}: empty.", name));
+            } else {
+                // Each line of a multi-line value is read as part of one sentence
+                let text = val.lines().collect::<Vec<_>>().join(" ");
+                line.push_str(&format!(" {}: {}, {}.", name, text, ty.spoken()));
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// The vertical layout for the pager, formatted as it scrolls into view
+pub fn vertical_content(
+    headers: Option<&[String]>,
+    records: Arc<Vec<Vec<Field>>>,
+    scheme: &ColorScheme,
+) -> PagerContent {
+    let names = column_names(headers, &records);
+    let width = name_width(&names);
+
+    let mut starts = Vec::with_capacity(records.len());
+    let mut line = 0;
+    for record in records.iter() {
+        starts.push(line);
+        let fields: usize = names
+            .iter()
+            .zip(record)
+            .map(|(_, val)| val.split('\n').count() + usize::from(hides_text(val)))
+            .sum();
+        line += 1 + fields;
+    }
+
+    let scheme = scheme.clone();
+    let cells = Arc::clone(&records);
+    let render: RenderFn = Arc::new(move |range| {
+        range
+            .flat_map(|idx| record_block(&names, width, idx, &records[idx], &scheme, true, None))
+            .collect()
+    });
+    let mut content = PagerContent::lazy(Vec::new(), starts, line, Vec::new(), render);
+    content.records = Some(cells);
+    content
+}
@@ -0,0 +1,499 @@
+// The parts of pcsv other programs can use: its color scheme, the type
+// detection behind the cell colors, and tables rendered the way pcsv prints
+// them
+
+use comfy_table::Color;
+use config::{BooleanConfig, ColorScheme, CustomType, NumberConfig, TypeIcons};
+use regex::Regex;
+use std::borrow::Cow;
+use std::net::IpAddr;
+use std::sync::OnceLock;
+
+pub mod config;
+pub mod palette;
+pub mod themes;
+mod view;
+
+pub use view::{TableView, TableViewBuilder};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataType {
+    Text,
+    IntNumber,
+    FloatNumber,
+    Boolean,
+    Date,
+    Empty,
+    // An amount with a currency symbol or code, like `$1,200.00` or `12,50 €`
+    Currency,
+    // An `http://` or `https://` address
+    Url,
+    // An address like `name@example.com`
+    Email,
+    // A UUID in its usual form, like `123e4567-e89b-12d3-a456-426614174000`
+    Uuid,
+    // An IPv4 or IPv6 address, or a CIDR block like `10.0.0.0/8`
+    Network,
+    // One of the config's `custom_types`, by its place in the list
+    Custom(usize),
+}
+
+impl DataType {
+    // Matches the keys of the `data_types` config table
+    pub fn name(&self) -> &'static str {
+        match self {
+            DataType::Text => "text",
+            DataType::IntNumber => "int_number",
+            DataType::FloatNumber => "float_number",
+            DataType::Boolean => "boolean",
+            DataType::Date => "date",
+            DataType::Empty => "empty",
+            DataType::Currency => "currency",
+            DataType::Url => "url",
+            DataType::Email => "email",
+            DataType::Uuid => "uuid",
+            DataType::Network => "network",
+            DataType::Custom(idx) => custom_name(*idx),
+        }
+    }
+
+    // The type in words, for output that can't lean on colors
+    pub fn spoken(&self) -> &'static str {
+        match self {
+            DataType::Text => "text",
+            DataType::IntNumber => "integer",
+            DataType::FloatNumber => "decimal number",
+            DataType::Boolean => "boolean",
+            DataType::Date => "date",
+            DataType::Empty => "empty",
+            DataType::Currency => "amount of money",
+            DataType::Url => "link",
+            DataType::Email => "email address",
+            DataType::Uuid => "UUID",
+            DataType::Network => "network address",
+            DataType::Custom(idx) => custom_name(*idx),
+        }
+    }
+
+    // The glyph a header shows for a column of this type. Text, and
+    // columns without a single type, go without.
+    pub fn icon(&self, icons: TypeIcons) -> Option<&'static str> {
+        let (unicode, ascii) = match self {
+            DataType::IntNumber => ("#", "#"),
+            DataType::FloatNumber => ("ƒ", "f"),
+            DataType::Date => ("📅", "d"),
+            DataType::Boolean => ("✓", "b"),
+            DataType::Currency => ("¤", "$"),
+            DataType::Url => ("🔗", "u"),
+            DataType::Email => ("✉", "@"),
+            DataType::Uuid => ("🔑", "k"),
+            DataType::Network => ("🌐", "n"),
+            DataType::Text | DataType::Empty | DataType::Custom(_) => return None,
+        };
+        match icons {
+            TypeIcons::Off => None,
+            TypeIcons::Unicode => Some(unicode),
+            TypeIcons::Ascii => Some(ascii),
+        }
+    }
+}
+
+impl ColorScheme {
+    // Channels that aren't two hex digits come out as 0. Configs and
+    // themes have their colors checked when they load.
+    pub fn hex_to_color(hex: &str) -> Color {
+        let hex = hex.trim_start_matches('#');
+        let channel = |digits: std::ops::Range<usize>| {
+            hex.get(digits)
+                .and_then(|digits| u8::from_str_radix(digits, 16).ok())
+                .unwrap_or(0)
+        };
+        palette::reduce(channel(0..2), channel(2..4), channel(4..6))
+    }
+
+    pub fn cell_hex(&self, ty: &DataType) -> &str {
+        match ty {
+            DataType::IntNumber => &self.data_types.int_number,
+            DataType::FloatNumber => &self.data_types.float_number,
+            DataType::Boolean => &self.data_types.boolean,
+            DataType::Date => &self.data_types.date,
+            DataType::Empty => &self.data_types.empty,
+            DataType::Text => &self.data_types.text,
+            DataType::Currency => self
+                .data_types
+                .currency
+                .as_deref()
+                .unwrap_or(&self.data_types.float_number),
+            DataType::Url => self.data_types.url.as_deref().unwrap_or(&self.data_types.text),
+            DataType::Email => self.data_types.email.as_deref().unwrap_or(&self.data_types.text),
+            DataType::Uuid => self.data_types.uuid.as_deref().unwrap_or(&self.data_types.text),
+            DataType::Network => self.data_types.network.as_deref().unwrap_or(&self.data_types.text),
+            DataType::Custom(idx) => self
+                .custom_types
+                .get(*idx)
+                .map_or(&self.data_types.text, |custom| &custom.color),
+        }
+    }
+
+    pub fn cell_color(&self, ty: &DataType) -> Color {
+        Self::hex_to_color(self.cell_hex(ty))
+    }
+
+    pub fn header_color(&self) -> Color {
+        Self::hex_to_color(&self.header)
+    }
+}
+
+static DATA_PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+
+static CUSTOM_PATTERNS: OnceLock<Vec<(String, Regex)>> = OnceLock::new();
+
+static NULL_TOKENS: OnceLock<Vec<String>> = OnceLock::new();
+
+// Makes values spelled like one of these, spaces around them aside, count
+// as empty. They can be set once.
+pub fn set_null_tokens(tokens: &[String]) -> Result<(), String> {
+    let tokens = tokens.iter().map(|token| token.trim().to_string()).collect();
+    NULL_TOKENS
+        .set(tokens)
+        .map_err(|_| "null tokens can only be set once".to_string())
+}
+
+static NUMBER_FORMAT: OnceLock<NumberConfig> = OnceLock::new();
+
+// Makes numbers written with this decimal mark and thousands separator
+// numbers, next to the plain `1234.5` kind. It can be set once.
+pub fn set_number_format(format: NumberConfig) -> Result<(), String> {
+    let marks = [Some(format.decimal), format.thousands];
+    if let Some(mark) = marks.into_iter().flatten().find(|c| c.is_alphanumeric() || matches!(c, '-' | '+')) {
+        return Err(format!("numbers: '{}' can't separate digits", mark));
+    }
+    if format.thousands == Some(format.decimal) {
+        return Err(format!("numbers: '{}' can't be both the decimal and the thousands mark", format.decimal));
+    }
+    NUMBER_FORMAT
+        .set(format)
+        .map_err(|_| "the number format can only be set once".to_string())
+}
+
+// A number in the file's format spelled the plain way, `1.234,5` as
+// `1234.5` for a decimal comma, or the value as it is. Thousands have to
+// be grouped by three. A space as the separator takes the no-break ones
+// as well.
+pub fn plain_number(val: &str) -> Cow<'_, str> {
+    let format = NUMBER_FORMAT.get().copied().unwrap_or_default();
+    if format == NumberConfig::default() {
+        return Cow::Borrowed(val);
+    }
+    let trimmed = val.trim();
+    let (sign, digits) = match trimmed.strip_prefix(['-', '+']) {
+        Some(rest) => (&trimmed[..1], rest),
+        None => ("", trimmed),
+    };
+    let (whole, fraction) = match digits.split_once(format.decimal) {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (digits, None),
+    };
+    let is_digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    if !fraction.is_none_or(is_digits) {
+        return Cow::Borrowed(val);
+    }
+    let is_separator = |c: char| match format.thousands {
+        Some(' ') => matches!(c, ' ' | '\u{a0}' | '\u{202f}'),
+        Some(thousands) => c == thousands,
+        None => false,
+    };
+    let mut groups = whole.split(is_separator);
+    let first = groups.next().unwrap_or("");
+    let grouped = groups.all(|group| group.len() == 3 && is_digits(group));
+    if !is_digits(first) || !grouped || (first.len() > 3 && whole.len() > first.len()) {
+        return Cow::Borrowed(val);
+    }
+    let mut plain = sign.to_string();
+    plain.extend(whole.chars().filter(|c| c.is_ascii_digit()));
+    if let Some(fraction) = fraction {
+        plain.push('.');
+        plain.push_str(fraction);
+    }
+    Cow::Owned(plain)
+}
+
+static CURRENCY: OnceLock<Regex> = OnceLock::new();
+
+// Symbols and the ISO codes of the most traded currencies, before or after
+// the amount, and the amount itself with any separators
+fn init_currency() -> Regex {
+    let symbol = r"(?:[A-Z]{1,3}\$|\$|€|£|¥|₹|₩|₽|₺|₴|₪|₫|฿|₱|₦|₡|₲|₵|₸|kr\.?|zł|Kč|Fr\.|USD|EUR|GBP|JPY|CNY|CHF|CAD|AUD|NZD|BRL|MXN|INR|KRW|RUB|TRY|SEK|NOK|DKK|PLN|CZK|HUF|ZAR|SGD|HKD|ILS|AED|SAR|THB)";
+    let amount = r"(?:\d{1,3}(?:[.,  \u{202f}]\d{3})+|\d+)(?:[.,]\d+)?";
+    Regex::new(&format!(
+        r"^(?P<sign>[-+]?)\s*(?:{symbol}\s?(?P<inner>-?)\s*(?P<before>{amount})|(?P<after>{amount})\s?{symbol})$"
+    ))
+    .unwrap()
+}
+
+// A currency amount spelled as a plain number, `$1,200.00` as `1200.00`
+// and `R$ 3,50` as `3.50`. The file's number format decides which mark is
+// the decimal one when it has one; otherwise the last of two different
+// marks is, and a single one unless three digits follow it.
+pub fn plain_amount(val: &str) -> Option<String> {
+    let caps = CURRENCY.get_or_init(init_currency).captures(val.trim())?;
+    let amount = caps.name("before").or_else(|| caps.name("after"))?.as_str();
+    let negative = caps["sign"].starts_with('-') || caps.name("inner").is_some_and(|sign| sign.as_str() == "-");
+    let format = NUMBER_FORMAT.get().copied().unwrap_or_default();
+    let digits = if format != NumberConfig::default() {
+        plain_number(amount).into_owned()
+    } else {
+        let marks: Vec<char> = amount.chars().filter(|c| matches!(c, '.' | ',')).collect();
+        let decimal = match marks.as_slice() {
+            [] => None,
+            [.., last] if marks.iter().any(|c| c != last) => Some(*last),
+            [only] if amount.rsplit(*only).next().is_some_and(|digits| digits.len() != 3) => Some(*only),
+            _ => None,
+        };
+        amount
+            .chars()
+            .filter_map(|c| match c {
+                _ if c.is_ascii_digit() => Some(c),
+                _ if Some(c) == decimal => Some('.'),
+                _ => None,
+            })
+            .collect()
+    };
+    Some(if negative { format!("-{}", digits) } else { digits })
+}
+
+static URL: OnceLock<Regex> = OnceLock::new();
+
+// A web address with nothing around it, spaces aside, and nothing in it
+// that could pass for a terminal escape
+pub fn is_url(val: &str) -> bool {
+    URL.get_or_init(|| Regex::new(r"(?i)^https?://[^\s\x00-\x1f\x7f]+$").unwrap())
+        .is_match(val.trim())
+}
+
+static EMAIL: OnceLock<Regex> = OnceLock::new();
+
+// One address, spaces around it aside: a local part of the characters
+// addresses are written with, and a domain of dotted labels ending in a
+// name of two letters or more
+pub fn is_email(val: &str) -> bool {
+    EMAIL
+        .get_or_init(|| {
+            Regex::new(
+                r"^[A-Za-z0-9!#$%&'*+/=?^_`{|}~-]+(\.[A-Za-z0-9!#$%&'*+/=?^_`{|}~-]+)*@([A-Za-z0-9]([A-Za-z0-9-]*[A-Za-z0-9])?\.)+[A-Za-z]{2,}$",
+            )
+            .unwrap()
+        })
+        .is_match(val.trim())
+}
+
+static UUID: OnceLock<Regex> = OnceLock::new();
+
+// 32 hex digits in groups of 8, 4, 4, 4 and 12, in either case
+pub fn is_uuid(val: &str) -> bool {
+    UUID.get_or_init(|| Regex::new(r"^[0-9A-Fa-f]{8}-([0-9A-Fa-f]{4}-){3}[0-9A-Fa-f]{12}$").unwrap())
+        .is_match(val.trim())
+}
+
+// An IP address, or a block of them with the length of its prefix after a
+// slash, no longer than the address has bits
+pub fn is_network(val: &str) -> bool {
+    let val = val.trim();
+    let (addr, prefix) = match val.split_once('/') {
+        Some((addr, prefix)) => (addr, Some(prefix)),
+        None => (val, None),
+    };
+    let bits = match addr.parse::<IpAddr>() {
+        Ok(IpAddr::V4(_)) => 32,
+        Ok(IpAddr::V6(_)) => 128,
+        Err(_) => return false,
+    };
+    prefix.is_none_or(|prefix| {
+        prefix.bytes().all(|b| b.is_ascii_digit()) && prefix.parse::<u8>().is_ok_and(|len| len <= bits)
+    })
+}
+
+static BOOLEANS: OnceLock<(Vec<String>, Vec<String>)> = OnceLock::new();
+
+fn lowercase(words: &[String]) -> Vec<String> {
+    words.iter().map(|word| word.to_lowercase()).collect()
+}
+
+// Replaces the English spellings of true and false the booleans are known
+// by. They can be set once, before any value is looked at.
+pub fn set_booleans(booleans: &BooleanConfig) -> Result<(), String> {
+    let (truthy, falsy) = (lowercase(&booleans.truthy), lowercase(&booleans.falsy));
+    if let Some(word) = truthy.iter().find(|word| falsy.contains(word)) {
+        return Err(format!("booleans: '{}' can't be both true and false", word));
+    }
+    BOOLEANS
+        .set((truthy, falsy))
+        .map_err(|_| "booleans can only be set once".to_string())
+}
+
+// The truth value of a boolean, in whatever case it is written
+pub fn parse_boolean(val: &str) -> Option<bool> {
+    let (truthy, falsy) = BOOLEANS.get_or_init(|| {
+        let defaults = BooleanConfig::default();
+        (lowercase(&defaults.truthy), lowercase(&defaults.falsy))
+    });
+    let val = val.to_lowercase();
+    if truthy.contains(&val) {
+        Some(true)
+    } else if falsy.contains(&val) {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+// Whether a value is missing: blank, or one of the null tokens
+pub fn is_null(val: &str) -> bool {
+    let val = val.trim();
+    val.is_empty() || NULL_TOKENS.get().is_some_and(|tokens| tokens.iter().any(|token| token == val))
+}
+
+// Makes `detect_data_type_cached` try these types, in order, before the
+// built-in ones. They can be set once; the color scheme they come from
+// gives their colors.
+pub fn set_custom_types(types: &[CustomType]) -> Result<(), String> {
+    let mut patterns = Vec::with_capacity(types.len());
+    for custom in types {
+        let pattern = Regex::new(&custom.pattern)
+            .map_err(|e| format!("custom type '{}': invalid pattern: {}", custom.name, e))?;
+        if !config::is_hex_color(&custom.color) {
+            return Err(format!(
+                "custom type '{}': expected a #RRGGBB color, got '{}'",
+                custom.name, custom.color
+            ));
+        }
+        patterns.push((custom.name.clone(), pattern));
+    }
+    CUSTOM_PATTERNS
+        .set(patterns)
+        .map_err(|_| "custom types can only be set once".to_string())
+}
+
+fn custom_name(idx: usize) -> &'static str {
+    CUSTOM_PATTERNS
+        .get()
+        .and_then(|patterns| patterns.get(idx))
+        .map_or("custom", |(name, _)| name.as_str())
+}
+
+fn init_patterns() -> Vec<Regex> {
+    vec![
+        Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap(), // YYYY-MM-DD
+        Regex::new(r"^\d{2}/\d{2}/\d{4}$").unwrap(), // MM/DD/YYYY
+        Regex::new(r"^\d{2}-\d{2}-\d{4}$").unwrap(), // MM-DD-YYYY
+        Regex::new(r"^\d{4}/\d{2}/\d{2}$").unwrap(), // YYYY/MM/DD
+        Regex::new(r"^\d{1,2}/\d{1,2}/\d{4}$").unwrap(), // M/D/YYYY
+        Regex::new(r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}(\.\d+)?$").unwrap(), // YYYY-MM-DD HH:MM:SS[.fff]
+    ]
+}
+
+pub fn detect_data_type_cached(val: &str) -> DataType {
+    if let Some(custom) = CUSTOM_PATTERNS.get() {
+        if let Some(idx) = custom.iter().position(|(_, pattern)| pattern.is_match(val)) {
+            return DataType::Custom(idx);
+        }
+    }
+    detect_builtin_type(val)
+}
+
+// The type of a value leaving out the custom types, for wherever what it
+// is matters more than how it is shown: sorting, arithmetic, statistics
+// and typed exports
+pub fn detect_builtin_type(val: &str) -> DataType {
+    let patterns = DATA_PATTERNS.get_or_init(init_patterns);
+    for pattern in patterns {
+        if pattern.is_match(val) {
+            return DataType::Date;
+        }
+    }
+
+    if is_null(val) {
+        return DataType::Empty;
+    }
+
+    if parse_boolean(val).is_some() {
+        return DataType::Boolean;
+    }
+
+    // Before numbers, which with `.` separating thousands could take an
+    // IPv4 address for one
+    if is_network(val) {
+        return DataType::Network;
+    }
+
+    let plain = &*plain_number(val);
+    if let Ok(_num) = plain.parse::<f64>() {
+        if plain.contains('.') || plain.to_lowercase().contains('e') {
+            DataType::FloatNumber
+        } else if plain.parse::<i64>().is_ok() {
+            DataType::IntNumber
+        } else {
+            DataType::FloatNumber
+        }
+    } else if plain_amount(val).is_some() {
+        DataType::Currency
+    } else if is_url(val) {
+        DataType::Url
+    } else if is_email(val) {
+        DataType::Email
+    } else if is_uuid(val) {
+        DataType::Uuid
+    } else {
+        DataType::Text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_scheme_colors() {
+        let mut scheme = ColorScheme::default();
+        assert_eq!(scheme.check_colors(), Ok(()));
+        scheme.header = "#FFF".to_string();
+        let error = "'header' expects a #RRGGBB color, got '#FFF'".to_string();
+        assert_eq!(scheme.check_colors(), Err(error));
+        // Drawn anyway where it wasn't checked
+        ColorScheme::hex_to_color("#FFF");
+        ColorScheme::hex_to_color("#ééé");
+    }
+
+    #[test]
+    fn detects_currency() {
+        for val in ["$1,200.00", "12,50 €", "EUR 30", "-$5"] {
+            assert_eq!(detect_builtin_type(val), DataType::Currency, "{}", val);
+        }
+        assert_eq!(detect_builtin_type("1200.00"), DataType::FloatNumber);
+    }
+
+    #[test]
+    fn detects_network() {
+        for val in ["192.168.1.10", "10.0.0.0/8", "::1", "2001:db8::/32"] {
+            assert_eq!(detect_builtin_type(val), DataType::Network, "{}", val);
+        }
+        for val in ["10.0.0.0/33", "1.2.3", "300.1.1.1"] {
+            assert_ne!(detect_builtin_type(val), DataType::Network, "{}", val);
+        }
+    }
+
+    #[test]
+    fn detects_uuid() {
+        assert_eq!(detect_builtin_type("123e4567-e89b-12d3-a456-426614174000"), DataType::Uuid);
+        assert_eq!(detect_builtin_type("123E4567-E89B-12D3-A456-426614174000"), DataType::Uuid);
+        assert_eq!(detect_builtin_type("123e4567-e89b-12d3-a456-42661417400"), DataType::Text);
+    }
+
+    #[test]
+    fn detects_email() {
+        assert_eq!(detect_builtin_type("name@example.com"), DataType::Email);
+        assert_eq!(detect_builtin_type("first.last+tag@mail.example.org"), DataType::Email);
+        assert_eq!(detect_builtin_type("name@localhost"), DataType::Text);
+        assert_eq!(detect_builtin_type("https://example.com/@name"), DataType::Url);
+    }
+}
@@ -0,0 +1,149 @@
+use crate::sort::date_key;
+use crate::{detect_builtin_type, plain_number, DataType};
+use std::borrow::Cow;
+
+// How a locale writes numbers and dates. Only what is shown changes:
+// sorting, filtering and every export still see the file's own values.
+#[derive(Debug, Clone, Copy)]
+pub struct Locale {
+    pub name: &'static str,
+    decimal: char,
+    group: char,
+    // Digits before the integer part is grouped at all; many locales
+    // leave four-digit numbers alone
+    min_grouping: usize,
+    // `D`, `M` and `Y` for the day, month and year
+    date: &'static str,
+}
+
+const fn pack(name: &'static str, decimal: char, group: char, min_grouping: usize, date: &'static str) -> Locale {
+    Locale {
+        name,
+        decimal,
+        group,
+        min_grouping,
+        date,
+    }
+}
+
+const NARROW_NBSP: char = '\u{202f}';
+
+// The locale packs, from the CLDR conventions of each
+const PACKS: &[Locale] = &[
+    pack("de-DE", ',', '.', 4, "D.M.Y"),
+    pack("de-AT", ',', NARROW_NBSP, 4, "D.M.Y"),
+    pack("de-CH", '.', '’', 4, "D.M.Y"),
+    pack("en-US", '.', ',', 4, "M/D/Y"),
+    pack("en-GB", '.', ',', 4, "D/M/Y"),
+    pack("en-IE", '.', ',', 4, "D/M/Y"),
+    pack("en-AU", '.', ',', 4, "D/M/Y"),
+    pack("en-CA", '.', ',', 4, "Y-M-D"),
+    pack("es-ES", ',', '.', 5, "D/M/Y"),
+    pack("es-MX", '.', ',', 4, "D/M/Y"),
+    pack("fr-FR", ',', NARROW_NBSP, 4, "D/M/Y"),
+    pack("fr-CA", ',', '\u{a0}', 4, "Y-M-D"),
+    pack("fr-CH", ',', NARROW_NBSP, 4, "D.M.Y"),
+    pack("it-IT", ',', '.', 4, "D/M/Y"),
+    pack("nl-NL", ',', '.', 4, "D-M-Y"),
+    pack("pt-BR", ',', '.', 4, "D/M/Y"),
+    pack("pt-PT", ',', '\u{a0}', 5, "D/M/Y"),
+    pack("pl-PL", ',', '\u{a0}', 5, "D.M.Y"),
+    pack("sv-SE", ',', '\u{a0}', 4, "Y-M-D"),
+    pack("da-DK", ',', '.', 4, "D.M.Y"),
+    pack("nb-NO", ',', '\u{a0}', 4, "D.M.Y"),
+    pack("fi-FI", ',', '\u{a0}', 4, "D.M.Y"),
+    pack("cs-CZ", ',', '\u{a0}', 4, "D. M. Y"),
+    pack("ru-RU", ',', '\u{a0}', 4, "D.M.Y"),
+    pack("tr-TR", ',', '.', 4, "D.M.Y"),
+    pack("ja-JP", '.', ',', 4, "Y/M/D"),
+    pack("zh-CN", '.', ',', 4, "Y/M/D"),
+    pack("ko-KR", '.', ',', 4, "Y. M. D."),
+];
+
+// A `--display-locale` value such as `de-DE`, `de_DE` or just `de`, which
+// picks the first pack of that language
+pub fn parse_locale(name: &str) -> Result<Locale, String> {
+    let wanted = name.replace('_', "-");
+    PACKS
+        .iter()
+        .find(|pack| pack.name.eq_ignore_ascii_case(&wanted))
+        .or_else(|| {
+            PACKS.iter().find(|pack| {
+                pack.name
+                    .split('-')
+                    .next()
+                    .is_some_and(|language| language.eq_ignore_ascii_case(&wanted))
+            })
+        })
+        .copied()
+        .ok_or_else(|| {
+            let names: Vec<&str> = PACKS.iter().map(|pack| pack.name).collect();
+            format!("unknown locale '{}', expected one of {}", name, names.join(", "))
+        })
+}
+
+impl Locale {
+    // A cell as this locale writes it, if it is a number or a date
+    pub fn render<'a>(&self, val: Cow<'a, str>) -> Cow<'a, str> {
+        let shown = match detect_builtin_type(&val) {
+            DataType::IntNumber | DataType::FloatNumber => self.number(plain_number(&val).trim()),
+            DataType::Date => self.date(val.trim()),
+            _ => None,
+        };
+        shown.map_or(val, Cow::Owned)
+    }
+
+    // Plain decimal numbers only: exponents, `inf` and zero-padded codes
+    // keep their spelling
+    fn number(&self, val: &str) -> Option<String> {
+        let (sign, digits) = match val.strip_prefix(['-', '+']) {
+            Some(rest) => (&val[..1], rest),
+            None => ("", val),
+        };
+        let (whole, fraction) = match digits.split_once('.') {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (digits, None),
+        };
+        let is_digits = |part: &str| part.chars().all(|c| c.is_ascii_digit());
+        if whole.is_empty() || !is_digits(whole) || !fraction.is_none_or(is_digits) {
+            return None;
+        }
+        if whole.len() > 1 && whole.starts_with('0') {
+            return None;
+        }
+        let mut out = sign.to_string();
+        if whole.len() >= self.min_grouping {
+            for (idx, digit) in whole.chars().enumerate() {
+                if idx > 0 && (whole.len() - idx) % 3 == 0 {
+                    out.push(self.group);
+                }
+                out.push(digit);
+            }
+        } else {
+            out.push_str(whole);
+        }
+        if let Some(fraction) = fraction {
+            out.push(self.decimal);
+            out.push_str(fraction);
+        }
+        Some(out)
+    }
+
+    fn date(&self, val: &str) -> Option<String> {
+        let [year, month, day, hour, minute, second] = date_key(val)?;
+        let mut out = String::new();
+        for c in self.date.chars() {
+            match c {
+                'D' => out.push_str(&format!("{:02}", day)),
+                'M' => out.push_str(&format!("{:02}", month)),
+                'Y' => out.push_str(&format!("{:04}", year)),
+                _ => out.push(c),
+            }
+        }
+        // Only timestamps have a time, even one of midnight
+        if val.len() > 10 {
+            out.push_str(&format!(" {:02}:{:02}:{:02}", hour, minute, second));
+        }
+        Some(out)
+    }
+}
@@ -1,203 +1,724 @@
-use clap::Parser;
-use comfy_table::presets::UTF8_FULL;
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
 use comfy_table::{Cell, Color, Table};
-use config::{load_config, ColorScheme, PagerConfig};
-use pager::Pager;
-use regex::Regex;
+use aliases::Aliases;
+use config::{load_themed_config, ColorScheme, HeaderStyle};
+use pcsv::{config, detect_builtin_type, detect_data_type_cached, is_null, is_url, palette, parse_boolean, plain_amount, plain_number, themes, DataType};
+use filter::RowFilter;
+use input::{InputFormat, ReadOptions};
+use pager::{FilterFn, FilterStack, FilteredLayouts, JumpTarget, Pager, PagerContent, PagerLayouts, ReadingFn, ReloadFn, RestyleFn, TableStyle};
+use rayon::prelude::*;
+use std::borrow::Cow;
 use std::fs;
-use std::io::{self, Read};
-use std::sync::OnceLock;
+use std::io::{self, IsTerminal, Write};
+use std::num::NonZeroUsize;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+use std::thread;
+use store::{Field, Interner, Text};
+use unicode_width::UnicodeWidthStr;
 
-mod config;
+mod aliases;
+#[cfg(feature = "parquet")]
+mod columnar;
+mod compression;
+mod checks;
+mod clipboard;
+#[cfg(feature = "cloud")]
+mod cloud;
+#[cfg(feature = "sqlite")]
+mod database;
+mod diff;
+mod distinct;
+mod documents;
+mod encryption;
+mod expr;
+mod filter;
+mod follow;
+mod fuzzy;
+mod gallery;
+mod grid;
+mod groups;
+mod index;
+mod init;
+mod input;
+mod keys;
+mod layouts;
+mod locale;
+mod meta;
+mod output;
 mod pager;
+mod preview;
+mod remote;
+mod repair;
+mod rules;
+mod serve;
+#[cfg(feature = "simd")]
+mod simd;
+mod sniff;
+mod sort;
+mod source;
+mod stats;
+mod store;
+mod timeline;
+mod truncate;
+#[cfg(feature = "xlsx")]
+mod workbook;
 
-#[derive(Debug, Clone)]
-enum DataType {
-    Text,
-    IntNumber,
-    FloatNumber,
-    Boolean,
-    Date,
-    Empty,
-}
-
-impl ColorScheme {
-    fn hex_to_color(hex: &str) -> Color {
-        let hex = hex.trim_start_matches('#');
-        let r = u8::from_str_radix(&hex[0..2], 16).unwrap_or(0);
-        let g = u8::from_str_radix(&hex[2..4], 16).unwrap_or(0);
-        let b = u8::from_str_radix(&hex[4..6], 16).unwrap_or(0);
-        Color::Rgb { r, g, b } // Changed from Color::Rgb(r, g, b)
-    }
-
-    fn cell_color(&self, ty: &DataType) -> Color {
-        let hex = match ty {
-            DataType::IntNumber => &self.data_types.int_number,
-            DataType::FloatNumber => &self.data_types.float_number,
-            DataType::Boolean => &self.data_types.boolean,
-            DataType::Date => &self.data_types.date,
-            DataType::Empty => &self.data_types.empty,
-            DataType::Text => &self.data_types.text,
-        };
-        Self::hex_to_color(hex)
+// Colors text for output assembled outside of comfy-table
+fn paint(text: &str, color: Color) -> String {
+    if plain() {
+        return text.to_string();
     }
-
-    fn header_color(&self) -> Color {
-        Self::hex_to_color(&self.header)
+    match palette::sgr(color, false) {
+        Some(sgr) => format!("\x1b[{}m{}\x1b[39m", sgr, text),
+        None => text.to_string(),
     }
 }
 
+// Text that opens `url` when clicked, in terminals that know OSC 8
+// hyperlinks. Others show the text as it is.
+fn hyperlink(text: &str, url: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+// A cell's exact text, quoted and with whitespace and control characters
+// spelled out (`"Paris\t"`), to check what the file really holds
+fn raw_text(val: &str) -> String {
+    format!("{:?}", val)
+}
+
+// The config's stand-in for a missing value, if it has one and the value is
+fn null_placeholder<'a>(val: &str, scheme: &'a ColorScheme) -> Option<&'a str> {
+    scheme.nulls.placeholder.as_deref().filter(|_| is_null(val))
+}
+
+// Whether a cell holds something that doesn't show on screen: spaces around
+// it, tabs, carriage returns or other invisible characters
+fn hides_text(val: &str) -> bool {
+    val.trim() != val
+        || val
+            .chars()
+            .any(|c| !matches!(c, '\n' | '"' | '\'' | '\\') && c.escape_debug().len() > 1)
+}
+
 #[derive(Parser)]
 #[command(name = "csv-viewer")]
 #[command(about = "A colorful CSV viewer")]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+#[command(group(ArgGroup::new("report").args(["stats", "schema", "missing", "frequency", "timeline"])))]
 struct Args {
-    input: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    #[arg(required = true, value_name = "INPUT")]
+    inputs: Vec<String>,
 
     #[arg(short, long)]
     show_row_numbers: bool,
 
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     config: Option<String>,
 
+    #[arg(long, global = true, value_parser = themes::parse_theme)]
+    theme: Option<String>,
+
+    #[arg(long, global = true)]
+    plain: bool,
+
+    #[arg(long, global = true, value_enum)]
+    color_depth: Option<palette::ColorDepth>,
+
+    #[arg(long = "set", value_name = "KEY=VALUE", global = true)]
+    overrides: Vec<String>,
+
+    #[command(flatten)]
+    read: ReadOptions,
+
     #[arg(short, long)]
     max_rows: Option<usize>,
 
+    #[arg(long, value_name = "N")]
+    tail: Option<usize>,
+
+    #[arg(long, value_name = "RANGE", value_parser = index::parse_rows, conflicts_with = "tail")]
+    rows: Option<index::Window>,
+
     #[arg(short, long)]
     pager: bool,
+
+    #[arg(long)]
+    follow: bool,
+
+    #[arg(long, value_name = "N")]
+    freeze_cols: Option<usize>,
+
+    #[arg(long, value_name = "KEYS", value_parser = keys::parse_keys)]
+    keys: Option<keys::KeyScript>,
+
+    #[arg(long)]
+    no_tty: bool,
+
+    #[arg(long, conflicts_with_all = ["pager", "follow", "freeze_cols", "keys", "no_tty", "render_size", "tail", "rows", "vertical", "a11y", "export", "report"])]
+    preview: bool,
+
+    #[arg(long, value_name = "COLSxROWS", value_parser = pager::parse_size)]
+    render_size: Option<(u16, u16)>,
+
+    #[arg(skip)]
+    start: Option<pager::StartAt>,
+
+    #[arg(long, value_name = "N")]
+    repeat_header: Option<NonZeroUsize>,
+
+    #[arg(long)]
+    no_row_lines: bool,
+
+    #[arg(long, value_name = "N")]
+    max_width: Option<usize>,
+
+    #[arg(long, value_name = "STYLE", value_enum, num_args = 0..=1, require_equals = true, default_missing_value = "unicode")]
+    type_icons: Option<config::TypeIcons>,
+
+    #[arg(long, value_name = "[COLUMN=]MODE", value_parser = truncate::parse_truncate)]
+    truncate: Vec<(Option<String>, config::TruncateMode)>,
+
+    #[arg(long, value_name = "LOCALE", value_parser = locale::parse_locale)]
+    display_locale: Option<locale::Locale>,
+
+    #[arg(long, value_name = "SAMPLE", value_parser = config::NumberConfig::from_sample)]
+    number_format: Option<config::NumberConfig>,
+
+    #[arg(short, long)]
+    filter: Option<String>,
+
+    #[arg(long)]
+    properties: bool,
+
+    #[arg(long, conflicts_with = "properties")]
+    vertical: bool,
+
+    #[arg(long, conflicts_with_all = ["pager", "follow", "freeze_cols", "keys", "no_tty", "render_size", "vertical", "properties"])]
+    a11y: bool,
+
+    #[arg(long)]
+    stats: bool,
+
+    #[arg(long)]
+    schema: bool,
+
+    #[arg(long)]
+    missing: bool,
+
+    #[arg(long, value_name = "COLUMN")]
+    frequency: Option<String>,
+
+    #[arg(long, requires = "frequency")]
+    bars: bool,
+
+    #[arg(long, value_name = "COLUMN[:UNIT]", value_parser = timeline::parse_timeline)]
+    timeline: Option<timeline::Timeline>,
+
+    #[command(flatten)]
+    checks: checks::CheckOptions,
+
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    output: OutputFormat,
+
+    #[arg(long, value_name = "FILE")]
+    export: Option<String>,
+
+    #[arg(long, value_name = "FILE")]
+    aliases: Option<String>,
+
+    #[arg(long, value_enum, default_value_t = HeaderNames::Original)]
+    export_headers: HeaderNames,
+
+    #[arg(long, value_name = "CHAR", value_parser = output::parse_delimiter)]
+    out_delimiter: Option<u8>,
+
+    #[arg(long)]
+    out_quote_all: bool,
+
+    #[arg(long, value_enum, default_value_t = output::LineEnding::Lf)]
+    line_ending: output::LineEnding,
+
+    #[arg(long, value_delimiter = ',')]
+    columns: Option<Vec<String>>,
+
+    #[arg(long, value_name = "NAME=EXPR", value_parser = expr::parse_derived)]
+    derive: Vec<expr::Derived>,
+
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+    header_rows: u8,
+
+    #[arg(long)]
+    sort: Option<String>,
+
+    #[arg(long, requires = "sort")]
+    desc: bool,
 }
 
-static DATA_PATTERNS: OnceLock<Vec<Regex>> = OnceLock::new();
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum OutputFormat {
+    Table,
+    Json,
+    Markdown,
+    Html,
+    Csv,
+    Xlsx,
+}
 
-fn init_patterns() -> Vec<Regex> {
-    vec![
-        Regex::new(r"^\d{4}-\d{2}-\d{2}$").unwrap(), // YYYY-MM-DD
-        Regex::new(r"^\d{2}/\d{2}/\d{4}$").unwrap(), // MM/DD/YYYY
-        Regex::new(r"^\d{2}-\d{2}-\d{4}$").unwrap(), // MM-DD-YYYY
-        Regex::new(r"^\d{4}/\d{2}/\d{2}$").unwrap(), // YYYY/MM/DD
-        Regex::new(r"^\d{1,2}/\d{1,2}/\d{4}$").unwrap(), // M/D/YYYY
-        Regex::new(r"^\d{4}-\d{2}-\d{2} \d{2}:\d{2}:\d{2}$").unwrap(), // YYYY-MM-DD HH:MM:SS
-    ]
+// Which header names JSON and CSV output use when --aliases is given
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum HeaderNames {
+    Original,
+    Display,
 }
 
-fn detect_data_type_cached(val: &str) -> DataType {
-    let patterns = DATA_PATTERNS.get_or_init(|| init_patterns());
-    for pattern in patterns {
-        if pattern.is_match(val) {
-            return DataType::Date;
-        }
-    }
+#[derive(Subcommand)]
+enum Command {
+    /// Cluster near-duplicate values in a column
+    FuzzyDupes(fuzzy::FuzzyArgs),
+    /// Profile one or more files, optionally as a comparison report
+    Stats(stats::StatsArgs),
+    /// Compare two files row by row, highlighting what changed in each cell
+    Diff(diff::DiffArgs),
+    /// Record where rows start in a large file, so --tail and --rows open it instantly
+    Index(index::IndexArgs),
+    /// List the distinct values of columns with their counts
+    Distinct(distinct::DistinctArgs),
+    /// Show every color theme, built-in and your own, on a sample table
+    Themes(gallery::ThemesArgs),
+    /// Report the delimiter, quoting, header, encoding and line endings a file seems to use
+    Sniff(sniff::SniffArgs),
+    /// Manage the configuration file
+    Config(init::ConfigArgs),
+    /// Render tables for editors and other tools over a Unix socket
+    Serve(serve::ServeArgs),
+}
 
-    if val.trim().is_empty() {
-        return DataType::Empty;
-    }
+type Records = Vec<Vec<Field>>;
+type CsvData = (Option<Vec<String>>, Records);
 
-    match val.to_lowercase().as_str() {
-        "true" | "false" | "yes" | "no" | "y" | "n" => DataType::Boolean,
-        _ => {
-            if let Ok(_num) = val.parse::<f64>() {
-                if val.contains('.') || val.to_lowercase().contains('e') {
-                    DataType::FloatNumber
-                } else if val.parse::<i64>().is_ok() {
-                    DataType::IntNumber
-                } else {
-                    DataType::FloatNumber
-                }
-            } else {
-                DataType::Text
-            }
-        }
+fn read_csv_data(input: &str, options: &ReadOptions) -> Result<CsvData, Box<dyn std::error::Error>> {
+    read_csv_source(input, source::open(input)?, options)
+}
+
+// Reads an input's bytes, already fetched, as any file named like it would be
+fn read_csv_source(
+    input: &str,
+    source: source::Source,
+    options: &ReadOptions,
+) -> Result<CsvData, Box<dyn std::error::Error>> {
+    let path = (input != "-").then_some(input);
+    let bytes = encryption::decrypt(source, path, options.identity.as_deref())
+        .map_err(|e| format!("failed to decrypt {}: {}", input, e))?;
+    let bytes = compression::decompress(bytes, path)
+        .map_err(|e| format!("failed to decompress {}: {}", input, e))?;
+    let format = options.format.unwrap_or_else(|| InputFormat::detect(input, &bytes));
+    let data = read_records(input, bytes, format, options)?;
+    match &options.query {
+        // A database runs the query itself, any other file is the table `t`
+        Some(sql) if format != InputFormat::Sqlite => query_records(data, sql),
+        _ => Ok(data),
     }
 }
 
-fn read_csv_data(
+fn read_records(
     input: &str,
-) -> Result<(Option<Vec<String>>, Vec<Vec<String>>), Box<dyn std::error::Error>> {
-    let content = if input == "-" {
-        let mut buffer = String::new();
-        io::stdin().read_to_string(&mut buffer)?;
-        buffer
-    } else {
-        fs::read_to_string(input)?
+    bytes: source::Source,
+    format: InputFormat,
+    options: &ReadOptions,
+) -> Result<CsvData, Box<dyn std::error::Error>> {
+    let delimiter = match format {
+        InputFormat::Csv => b',',
+        InputFormat::Tsv => b'\t',
+        InputFormat::Xlsx => return read_workbook(bytes.into_vec(), options),
+        InputFormat::Parquet => return read_parquet(bytes.into_vec()),
+        InputFormat::Sqlite => return read_sqlite(input, options),
+        InputFormat::Json => return documents::read_json(&input::decode(&bytes, input, options)?),
     };
+    // Borrowed straight from the file (or its mapping) when it is UTF-8,
+    // and then kept for the cells to point into
+    let text = match input::decode(&bytes, input, options)? {
+        Cow::Owned(text) => Text::from(text),
+        Cow::Borrowed(text) => {
+            let start = text.as_ptr() as usize - bytes.as_ptr() as usize;
+            let range = start..start + text.len();
+            Text::new(bytes, range)?
+        }
+    };
+    let text = Arc::new(text);
+    #[cfg(feature = "simd")]
+    if options.repair.is_none() && simd::supports(text.as_str(), delimiter) {
+        if let Some(data) = simd::read_records(&text, delimiter, options)? {
+            return Ok(data);
+        }
+    }
+    let (data, _) = parse_csv(&text, delimiter, options, false)?;
+    Ok(data)
+}
 
-    let mut rdr = csv::Reader::from_reader(content.as_bytes());
-    let headers = if rdr.has_headers() {
+// Where reading CSV text stopped: at its end, or where a partial last
+// record starts, with the line of that record
+struct Stop {
+    end: usize,
+    partial: Option<u64>,
+}
+
+// The header and records of CSV text, and where they stop.
+// With `growing`, the text is of a file still being written, and a last
+// record without its line ending is left for when the rest of it arrives.
+fn parse_csv(
+    text: &Arc<Text>,
+    delimiter: u8,
+    options: &ReadOptions,
+    growing: bool,
+) -> Result<(CsvData, Stop), Box<dyn std::error::Error>> {
+    let content = text.as_str();
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers: Option<Vec<String>> = if rdr.has_headers() {
+        input::check_record(rdr.byte_headers()?, 1, content, delimiter, options)?;
         Some(rdr.headers()?.iter().map(|s| s.to_string()).collect())
     } else {
         None
     };
+    let start = rdr.position();
+    let (records, stop) = parse_records(
+        text,
+        start.byte() as usize,
+        start.line(),
+        headers.as_deref(),
+        delimiter,
+        options,
+        growing,
+    )?;
+    Ok(((headers, records), stop))
+}
+
+// The records of CSV text from byte `from`, which is on line `line`, up
+// to its end. Each is checked against --max-columns and --max-cell-size,
+// and fitted to the header as --repair says.
+fn parse_records(
+    text: &Arc<Text>,
+    from: usize,
+    line: u64,
+    headers: Option<&[String]>,
+    delimiter: u8,
+    options: &ReadOptions,
+    growing: bool,
+) -> Result<(Records, Stop), Box<dyn std::error::Error>> {
+    let content = text.as_str();
+    // Records of the wrong length are read too, to be repaired or explained
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .flexible(true)
+        .has_headers(false)
+        .from_reader(&content.as_bytes()[from..]);
+    let header_len = headers.map_or(0, |h| h.len());
+    let fix = match &options.repair {
+        Some(repair) => Some(repair.resolve(headers.unwrap_or_default())?),
+        None => None,
+    };
+    let mut mismatches = repair::Mismatches::default();
+    // A file that is still being written ends in a partial line
+    let unterminated = content.len() > from && !content.ends_with('\n');
+    // Positions are counted from `from`
+    let at = |pos: &csv::Position| (from + pos.byte() as usize, line + pos.line() - 1);
 
+    // Two byte records are reused for every row, one looking ahead to
+    // tell whether the other is the last
     let mut records = Vec::new();
-    for result in rdr.records() {
-        let record = result?;
-        records.push(record.iter().map(|s| s.to_string()).collect());
+    let (mut end, mut partial) = (content.len(), None);
+    let mut interner = Interner::default();
+    let (mut record, mut ahead) = (csv::ByteRecord::new(), csv::ByteRecord::new());
+    let mut result = rdr.read_byte_record(&mut record);
+    loop {
+        let current = match result {
+            Ok(false) => break,
+            Ok(true) => Ok(()),
+            Err(err) => Err(err),
+        };
+        let next = rdr.read_byte_record(&mut ahead);
+        let is_last = matches!(next, Ok(false));
+        // Cut off inside quotes, or without its line ending and short of
+        // fields, or at all while the file grows
+        let cut_off = |record: &csv::ByteRecord| {
+            let start = record.position().map_or(from, |pos| at(pos).0);
+            (unterminated && (growing || record.len() < header_len))
+                || input::ends_in_quotes(&content.as_bytes()[start..], delimiter)
+        };
+        match current {
+            Ok(()) if is_last && cut_off(&record) => {
+                let (start, line) = record.position().map_or((from, line), at);
+                (end, partial) = (start, Some(line));
+                warn_truncated_record(Some(line), options);
+            }
+            Ok(()) => {
+                let (start, line) = record.position().map_or((from, line), at);
+                // No field can be too large if all of them together aren't
+                if record.len() > options.max_columns || record.as_slice().len() > options.max_cell_size {
+                    input::check_record(&record, line, content, delimiter, options)?;
+                }
+                if record.len() == header_len {
+                    records.push(interner.record_in(text, start, record.iter(), delimiter));
+                } else if let Some(fix) = &fix {
+                    let fields: Vec<String> =
+                        record.iter().map(|field| String::from_utf8_lossy(field).into_owned()).collect();
+                    let repaired = fix.apply(&fields, header_len, delimiter).ok_or_else(|| {
+                        format!(
+                            "line {} has {} fields, which --repair can't fit to the {} of the header",
+                            line,
+                            record.len(),
+                            header_len
+                        )
+                    })?;
+                    records.push(interner.record(repaired));
+                } else {
+                    mismatches.add(line, &record);
+                }
+            }
+            Err(err) if is_last && unterminated => {
+                let (start, line) = err.position().map_or((from, line), at);
+                (end, partial) = (start, Some(line));
+                warn_truncated_record(Some(line), options);
+            }
+            Err(err) => return Err(err.into()),
+        }
+        std::mem::swap(&mut record, &mut ahead);
+        result = next;
+    }
+
+    if !mismatches.is_empty() {
+        let message = mismatches.explain(headers.unwrap_or_default(), &records, delimiter);
+        return Err(message.into());
     }
+    Ok((records, Stop { end, partial }))
+}
+
+#[cfg(feature = "xlsx")]
+fn read_workbook(bytes: Vec<u8>, options: &ReadOptions) -> Result<CsvData, Box<dyn std::error::Error>> {
+    workbook::read_sheet(bytes, options.sheet.as_deref())
+}
+
+#[cfg(not(feature = "xlsx"))]
+fn read_workbook(_bytes: Vec<u8>, _options: &ReadOptions) -> Result<CsvData, Box<dyn std::error::Error>> {
+    Err("reading spreadsheets needs pcsv built with the `xlsx` feature".into())
+}
+
+#[cfg(feature = "xlsx")]
+fn write_workbook(view: &View, scheme: &ColorScheme, args: &Args) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    workbook::write_sheet(
+        view.headers.as_deref(),
+        &view.records,
+        view.group_labels.as_deref(),
+        scheme,
+        args.show_row_numbers,
+    )
+}
+
+#[cfg(not(feature = "xlsx"))]
+fn write_workbook(_view: &View, _scheme: &ColorScheme, _args: &Args) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    Err("writing spreadsheets needs pcsv built with the `xlsx` feature".into())
+}
 
-    Ok((headers, records))
+#[cfg(feature = "parquet")]
+fn read_parquet(bytes: Vec<u8>) -> Result<CsvData, Box<dyn std::error::Error>> {
+    columnar::read_parquet(bytes)
+}
+
+#[cfg(not(feature = "parquet"))]
+fn read_parquet(_bytes: Vec<u8>) -> Result<CsvData, Box<dyn std::error::Error>> {
+    Err("reading Parquet files needs pcsv built with the `parquet` feature".into())
+}
+
+#[cfg(feature = "sqlite")]
+fn read_sqlite(path: &str, options: &ReadOptions) -> Result<CsvData, Box<dyn std::error::Error>> {
+    database::read_sqlite(path, options)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn read_sqlite(_path: &str, _options: &ReadOptions) -> Result<CsvData, Box<dyn std::error::Error>> {
+    Err("reading SQLite databases needs pcsv built with the `sqlite` feature".into())
+}
+
+#[cfg(feature = "sqlite")]
+fn query_records(data: CsvData, sql: &str) -> Result<CsvData, Box<dyn std::error::Error>> {
+    database::query_records(data, sql)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn query_records(_data: CsvData, _sql: &str) -> Result<CsvData, Box<dyn std::error::Error>> {
+    Err("running --query needs pcsv built with the `sqlite` feature".into())
+}
+
+// Set by --plain or NO_COLOR, for output without any colors
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+fn plain() -> bool {
+    PLAIN.load(Ordering::Relaxed)
+}
+
+// Whether printed output is colored: only on a terminal, unless plain
+fn styled() -> bool {
+    !plain() && io::stdout().is_terminal()
+}
+
+// A comfy-table that stays uncolored when the output is plain, even on a
+// terminal
+fn new_table() -> Table {
+    let mut table = Table::new();
+    if plain() {
+        table.force_no_tty();
+    }
+    table
 }
 
+fn warn_truncated_record(line: Option<u64>, options: &ReadOptions) {
+    if options.quiet {
+        return;
+    }
+    let at = line.map(|line| format!(" (line {})", line)).unwrap_or_default();
+    eprintln!(
+        "warning: ignoring incomplete last record{}, the file may still be being written",
+        at
+    );
+}
+
+fn column_index(headers: Option<&[String]>, name: &str) -> Result<usize, Box<dyn std::error::Error>> {
+    headers
+        .and_then(|h| h.iter().position(|col| col == name))
+        .ok_or_else(|| format!("column '{}' not found", name).into())
+}
+
+fn select_columns<T: Clone + Default>(row: &[T], indices: &[usize]) -> Vec<T> {
+    indices
+        .iter()
+        .map(|&idx| row.get(idx).cloned().unwrap_or_default())
+        .collect()
+}
+
+// Header names with the glyph of their column's type in front, when the
+// config asks for them
+fn header_names(headers: &[String], records: &[Vec<Field>], scheme: &ColorScheme) -> Vec<String> {
+    if scheme.type_icons == config::TypeIcons::Off {
+        return headers.to_vec();
+    }
+    headers
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            match stats::dominant_type(&stats::column_values(records, idx)).icon(scheme.type_icons) {
+                Some(icon) => format!("{} {}", icon, name),
+                None => name.clone(),
+            }
+        })
+        .collect()
+}
+
+// Rows given to each rayon task when building a table
+const ROWS_PER_TASK: usize = 256;
+
+// `links` makes web addresses OSC 8 links, for output that goes to a
+// terminal whether or not pcsv's own does
 fn create_table(
     headers: Option<Vec<String>>,
-    records: Vec<Vec<String>>,
+    records: Vec<Vec<Field>>,
     scheme: &ColorScheme,
     args: &Args,
+    links: bool,
 ) -> Table {
-    let mut table = Table::new();
+    if args.properties {
+        return layouts::create_properties_table(headers, records, scheme);
+    }
+
+    let names = stats::column_names(headers.as_deref(), &records);
+    // Checked when the config was loaded
+    let rules = rules::CellRules::new(&scheme.rules, &names).unwrap_or_default();
+    let truncation = truncate::Truncation::new(&scheme.truncate, &names);
 
-    table.load_preset(UTF8_FULL);
+    // Without the lines between rows, twice as many fit on a screen
+    let mut table = grid::bordered_table(!args.no_row_lines);
+    let mut repeated_header = None;
     // Set headers with colors
     if let Some(h) = headers {
-        let header_cells: Vec<Cell> = if args.show_row_numbers {
-            std::iter::once(Cell::new("#").fg(scheme.header_color()))
-                .chain(
-                    h.iter()
-                        .map(|name| Cell::new(name).fg(scheme.header_color())),
-                )
-                .collect()
-        } else {
-            h.iter()
-                .map(|name| Cell::new(name).fg(scheme.header_color()))
-                .collect()
-        };
+        let h = header_names(&h, &records, scheme);
+        let header_cells = grid::header_cells(&h, args.show_row_numbers, scheme);
+        if !args.pager {
+            repeated_header = args
+                .repeat_header
+                .map(|every| (every.get(), header_cells.clone()));
+        }
         table.set_header(header_cells);
     }
 
-    let limited_records = if let Some(max) = args.max_rows {
-        records.into_iter().take(max).collect::<Vec<_>>()
-    } else {
-        records
+    // Typing and coloring every cell dominates on wide files, so the rows
+    // are built on all cores and only added to the table in order
+    let format = grid::CellFormat {
+        scheme,
+        rules: &rules,
+        truncation: &truncation,
+        locale: args.display_locale.as_ref(),
+        raw: false,
+        links,
     };
+    let rows: Vec<Vec<Cell>> = records
+        .par_iter()
+        .enumerate()
+        .with_min_len(ROWS_PER_TASK)
+        .map(|(row_idx, record)| {
+            let number = args.show_row_numbers.then_some(row_idx + 1);
+            format.row(number, record, (0..record.len()).map(|field| (field, None)))
+        })
+        .collect();
 
-    for (row_idx, record) in limited_records.iter().enumerate() {
-        let mut row_cells = Vec::new();
-
-        if args.show_row_numbers {
-            row_cells.push(Cell::new(&format!("{}", row_idx + 1)).fg(scheme.header_color()));
-        }
-
-        for value in record {
-            let data_type = detect_data_type_cached(value);
-            let color = scheme.cell_color(&data_type);
-            row_cells.push(Cell::new(value).fg(color));
+    for (row_idx, row_cells) in rows.into_iter().enumerate() {
+        if let Some((every, header_cells)) = &repeated_header {
+            if row_idx > 0 && row_idx % every == 0 {
+                table.add_row(header_cells.clone());
+            }
         }
-
         table.add_row(row_cells);
     }
 
     table
 }
 
+fn render_table(
+    table: &Table,
+    group_labels: Option<&[Option<String>]>,
+    scheme: &ColorScheme,
+    args: &Args,
+) -> String {
+    match group_labels {
+        Some(labels) => groups::add_group_header(
+            &table.to_string(),
+            labels,
+            usize::from(args.show_row_numbers),
+            table.should_style().then(|| scheme.header_color()),
+        ),
+        None => table.to_string(),
+    }
+}
+
 fn create_table_lines(
     headers: Option<Vec<String>>,
-    records: Vec<Vec<String>>,
+    records: Vec<Vec<Field>>,
+    group_labels: Option<&[Option<String>]>,
     scheme: &ColorScheme,
     args: &Args,
 ) -> Vec<String> {
     let mut lines = Vec::new();
     
     // Create a temporary table to get the formatted output
-    let table = create_table(headers.clone(), records, scheme, args);
-    let table_string = table.to_string();
+    let table = create_table(headers.clone(), records, scheme, args, styled());
+    let table_string = render_table(&table, group_labels, scheme, args);
     
     // Split the table into lines
     for line in table_string.lines() {
@@ -207,28 +728,740 @@ fn create_table_lines(
     lines
 }
 
+// Records ready for display, after header handling, filtering and sorting
+struct View {
+    headers: Option<Vec<String>>,
+    records: Vec<Vec<Field>>,
+    group_labels: Option<groups::GroupLabels>,
+    // Where each run of equal values starts in the sorted column
+    group_starts: Vec<(String, usize)>,
+    aliases: Aliases,
+}
+
+fn load_view(input: &str, scheme: &ColorScheme, args: &Args) -> Result<View, Box<dyn std::error::Error>> {
+    let window = args.tail.map(index::Window::Tail).or(args.rows);
+    let data = match window {
+        // The group label row always comes along
+        Some(window) => index::read_window(input, window, usize::from(args.header_rows - 1), &args.read)?,
+        None => read_csv_data(input, &args.read)?,
+    };
+    view_of(data, scheme, args)
+}
+
+// Applies header rows, aliases, the filter, derived columns and sorting to
+// records already read
+fn view_of(data: CsvData, scheme: &ColorScheme, args: &Args) -> Result<View, Box<dyn std::error::Error>> {
+    let (mut headers, mut records) = data;
+    let mut group_labels = None;
+    if args.header_rows == 2 && !records.is_empty() {
+        // The first row holds group labels, the second the column names
+        group_labels = headers.as_deref().map(groups::labels_from_row);
+        headers = Some(records.remove(0).iter().map(Field::to_string).collect());
+    } else if let (Some(column_groups), Some(h)) = (&scheme.column_groups, &headers) {
+        group_labels = Some(groups::labels_from_config(column_groups, h));
+    }
+
+    let aliases = match &args.aliases {
+        Some(path) => Aliases::load(path)?,
+        None => Aliases::default(),
+    };
+    if let Some(h) = &mut headers {
+        aliases.rename_headers(h);
+    }
+
+    let records = match &args.filter {
+        Some(expr) => {
+            let filter = RowFilter::parse(&aliases.filter_expr(expr), headers.as_deref())?;
+            filter::filter_records(records, &filter)
+        }
+        None => records,
+    };
+    let records = derive_columns(&mut headers, &mut group_labels, records, &args.derive, &aliases)?;
+    let mut group_starts = Vec::new();
+    let records = match &args.sort {
+        Some(name) => {
+            let name = aliases.display(name);
+            let column = column_index(headers.as_deref(), name)?;
+            let records = sort::sort_records(records, column, args.desc);
+            group_starts = sort::group_starts(&records, column, name);
+            records
+        }
+        None => records,
+    };
+
+    Ok(View {
+        headers,
+        records,
+        group_labels,
+        group_starts,
+        aliases,
+    })
+}
+
+// Appends the --derive columns, computed over the rows the filter kept.
+// Each one can use the columns derived before it.
+fn derive_columns(
+    headers: &mut Option<Vec<String>>,
+    group_labels: &mut Option<groups::GroupLabels>,
+    mut records: Vec<Vec<Field>>,
+    derived: &[expr::Derived],
+    aliases: &Aliases,
+) -> Result<Vec<Vec<Field>>, Box<dyn std::error::Error>> {
+    for column in derived {
+        let mut expr = column.expr.clone();
+        expr.resolve(&mut |name: &str| column_index(headers.as_deref(), aliases.display(name)))?;
+        expr.prepare(&records);
+        let width = match headers {
+            Some(h) => h.len(),
+            None => records.iter().map(Vec::len).max().unwrap_or(0),
+        };
+        for record in &mut records {
+            let val = Field::from(expr.eval(record).to_cell());
+            if record.len() < width {
+                record.resize(width, Field::default());
+            }
+            record.insert(width, val);
+        }
+        if let Some(h) = headers {
+            h.push(column.name.clone());
+        }
+        if let Some(labels) = group_labels {
+            labels.resize(width, None);
+            labels.push(None);
+        }
+    }
+    Ok(records)
+}
+
+// Applies --max-rows and --columns
+fn shape_view(view: &mut View, args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(max) = args.max_rows {
+        view.records.truncate(max);
+    }
+
+    if let Some(names) = &args.columns {
+        let indices = names
+            .iter()
+            .map(|name| column_index(view.headers.as_deref(), view.aliases.display(name.trim())))
+            .collect::<Result<Vec<_>, _>>()?;
+        view.headers = view.headers.take().map(|h| select_columns(&h, &indices));
+        view.records = view
+            .records
+            .iter()
+            .map(|record| select_columns(record, &indices))
+            .collect();
+        view.group_labels = view
+            .group_labels
+            .take()
+            .map(|labels| select_columns(&labels, &indices));
+    }
+    Ok(())
+}
+
+// CSV output keeps the input's delimiter unless --out-delimiter says otherwise
+fn csv_dialect(input: &str, args: &Args) -> output::CsvDialect {
+    let delimiter = args.out_delimiter.unwrap_or_else(|| {
+        match args.read.format.unwrap_or_else(|| InputFormat::detect(input, &[])) {
+            InputFormat::Tsv => b'\t',
+            _ => b',',
+        }
+    });
+    output::CsvDialect {
+        delimiter,
+        quote_all: args.out_quote_all,
+        line_ending: args.line_ending,
+    }
+}
+
+// The pager's layouts of a view, which the filters typed at `&` can narrow
+// down to the records they let through
+fn pager_layouts(view: View, scheme: &Rc<ColorScheme>, args: &Rc<Args>) -> PagerLayouts {
+    let records = Arc::new(view.records);
+    let refilter: FilterFn = {
+        let (headers, group_labels) = (view.headers.clone(), view.group_labels.clone());
+        let (group_starts, aliases) = (view.group_starts.clone(), view.aliases);
+        let (records, scheme, args) = (Arc::clone(&records), Rc::clone(scheme), Rc::clone(args));
+        Rc::new(move |stack: &FilterStack| {
+            let filters: Vec<(RowFilter, bool)> = stack
+                .active()
+                .map(|filter| {
+                    let expr = aliases.filter_expr(&filter.expr);
+                    Ok((RowFilter::parse(&expr, headers.as_deref())?, filter.negated))
+                })
+                .collect::<Result<_, String>>()?;
+            let passes = |record: &[Field]| {
+                let mut results = filters.iter().map(|(filter, negated)| filter.matches(record) != *negated);
+                if stack.any {
+                    results.any(|matched| matched)
+                } else {
+                    results.all(|matched| matched)
+                }
+            };
+            let rows: Vec<usize> = (0..records.len())
+                .filter(|&idx| filters.is_empty() || passes(&records[idx]))
+                .collect();
+            let kept = rows.iter().map(|&idx| records[idx].clone()).collect();
+            // Groups keep their first matching record, if they have one
+            let group_starts = group_starts
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, (label, start))| {
+                    let end = group_starts.get(idx + 1).map_or(records.len(), |(_, next)| *next);
+                    let first = rows.partition_point(|&row| row < *start);
+                    (rows.get(first).is_some_and(|&row| row < end)).then(|| (label.clone(), first))
+                })
+                .collect();
+            let layouts = layouts_of(headers.clone(), Arc::new(kept), group_labels.clone(), group_starts, &scheme, &args);
+            Ok(FilteredLayouts {
+                layouts,
+                rows,
+                total: records.len(),
+            })
+        })
+    };
+    let mut layouts = layouts_of(view.headers, records, view.group_labels, view.group_starts, scheme, args);
+    layouts.refilter = Some(refilter);
+    layouts
+}
+
+fn layouts_of(
+    headers: Option<Vec<String>>,
+    records: Arc<Vec<Vec<Field>>>,
+    group_labels: Option<groups::GroupLabels>,
+    group_starts: Vec<(String, usize)>,
+    scheme: &ColorScheme,
+    args: &Args,
+) -> PagerLayouts {
+    // Both layouts are formatted bit by bit as they scroll into view
+    let vertical = layouts::vertical_content(headers.as_deref(), Arc::clone(&records), scheme);
+    let (table, restyle) = if args.properties {
+        let records = Arc::try_unwrap(records).unwrap_or_else(|records| records.as_ref().clone());
+        let table = PagerContent::from_table_lines(create_table_lines(
+            headers,
+            records,
+            group_labels.as_deref(),
+            scheme,
+            args,
+        ));
+        (table, None)
+    } else {
+        let restyle = table_restyle(headers, records, group_labels, scheme, args);
+        let config = scheme.pager.unwrap_or_default();
+        (restyle(TableStyle::new(config.header)), Some(restyle))
+    };
+    let (content, alternate) = if args.vertical {
+        (vertical, table)
+    } else {
+        (table, vertical)
+    };
+
+    let jumps = group_starts
+        .into_iter()
+        .map(|(label, record)| JumpTarget { label, record })
+        .collect();
+
+    PagerLayouts {
+        content,
+        alternate,
+        jumps,
+        restyle,
+        refilter: None,
+        note: None,
+    }
+}
+
+// Builds the pager's table layout with the header shown in a given style,
+// the cells as values or raw text, and some columns left out. Columns are as wide as their widest
+// value, so a shorter header can make them narrower too.
+fn table_restyle(
+    headers: Option<Vec<String>>,
+    records: Arc<Vec<Vec<Field>>>,
+    group_labels: Option<groups::GroupLabels>,
+    scheme: &ColorScheme,
+    args: &Args,
+) -> RestyleFn {
+    let names = stats::column_names(headers.as_deref(), &records);
+    let headers = headers.map(|h| header_names(&h, &records, scheme));
+    let compact_width = scheme.pager.unwrap_or_default().compact_header_width;
+    let rules = rules::CellRules::new(&scheme.rules, &names).unwrap_or_default();
+    let truncation = truncate::Truncation::new(&scheme.truncate, &names);
+    let locale = args.display_locale;
+    let scheme = scheme.clone();
+    let (row_numbers, row_lines) = (args.show_row_numbers, !args.no_row_lines);
+    Rc::new(move |style: TableStyle| {
+        let (headers, group_labels) = match style.header {
+            HeaderStyle::Hidden => (None, None),
+            header => (
+                headers.as_ref().map(|h| h.iter().map(|name| header.name(name, compact_width)).collect()),
+                group_labels.clone(),
+            ),
+        };
+        let mut content = grid::table_content(
+            headers,
+            Arc::clone(&records),
+            group_labels.as_deref(),
+            &scheme,
+            &grid::GridOptions {
+                row_numbers,
+                row_lines,
+                raw: style.raw,
+                hidden: &style.hidden,
+                rules: &rules,
+                truncation: &truncation,
+                locale: locale.as_ref(),
+                width: style.width,
+            },
+        );
+        // Jumping to a column still goes by its full name
+        content.column_names = content.fields.iter().map(|&field| names[field].clone()).collect();
+        content.field_names = names.clone();
+        content
+    })
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let args = Args::parse();
-    let scheme = load_config(args.config.as_deref());
-    let (headers, records) = read_csv_data(&args.input)?;
-
-    if args.pager {
-        // Use pager mode
-        let table_lines = create_table_lines(headers, records, &scheme, &args);
-        let total_rows = table_lines.len();
-        
-        let pager_config = scheme.pager.unwrap_or_else(|| PagerConfig {
-            scroll_single_line: 1,
-            scroll_multi_line: 10,
-        });
-        
-        let mut pager = Pager::new(table_lines, None, total_rows, pager_config)?;
-        pager.run()?;
+    let mut args = Args::parse();
+    // https://no-color.org: set and not empty
+    if args.plain || std::env::var_os("NO_COLOR").is_some_and(|val| !val.is_empty()) {
+        PLAIN.store(true, Ordering::Relaxed);
+        crossterm::style::force_color_output(false);
+    }
+    palette::set_depth(args.color_depth.unwrap_or_else(palette::detect));
+    // Less-style `+N`, `+G` or `+/pattern` among the inputs says where the
+    // pager opens, unless there is a file by that name
+    while let Some(idx) = args.inputs.iter().position(|input| input.starts_with('+') && !Path::new(input).exists()) {
+        if args.start.is_some() {
+            return Err("only one startup position can be given".into());
+        }
+        args.start = Some(pager::parse_start(&args.inputs.remove(idx))?);
+    }
+    if args.start.is_some() {
+        if args.inputs.is_empty() {
+            return Err("a startup position needs an input to open".into());
+        }
+        if args.a11y {
+            return Err("a startup position can't be used with --a11y".into());
+        }
+    }
+    // Following a file, freezing columns, pressing keys, drawing the
+    // pager's screen and where it opens only make sense in the pager
+    args.no_tty |= args.render_size.is_some();
+    args.pager |= args.follow
+        || args.freeze_cols.is_some()
+        || args.keys.is_some()
+        || args.no_tty
+        || args.start.is_some();
+    let mut scheme = load_themed_config(args.config.as_deref(), args.theme.as_deref());
+    for assignment in &args.overrides {
+        scheme.apply_override(assignment)?;
+    }
+    rules::CellRules::new(&scheme.rules, &[]).map_err(|e| format!("config rules: {}", e))?;
+    pcsv::set_custom_types(&scheme.custom_types).map_err(|e| format!("config: {}", e))?;
+    pcsv::set_null_tokens(&scheme.nulls.tokens).map_err(|e| format!("config: {}", e))?;
+    pcsv::set_booleans(&scheme.booleans).map_err(|e| format!("config: {}", e))?;
+    if let Some(format) = args.number_format {
+        scheme.numbers = format;
+    }
+    pcsv::set_number_format(scheme.numbers).map_err(|e| format!("config: {}", e))?;
+    if let Some(width) = args.max_width {
+        scheme.truncate.max_width = Some(width);
+    }
+    if let Some(icons) = args.type_icons {
+        scheme.type_icons = icons;
+    }
+    for (column, mode) in &args.truncate {
+        match column {
+            Some(column) => {
+                scheme.truncate.columns.insert(column.clone(), *mode);
+            }
+            None => scheme.truncate.mode = *mode,
+        }
+    }
+
+    if let Some(command) = &args.command {
+        return match command {
+            Command::FuzzyDupes(fuzzy_args) => fuzzy::run(fuzzy_args, &args.read, &scheme),
+            Command::Stats(stats_args) => stats::run(stats_args, &args.read, &scheme),
+            Command::Diff(diff_args) => diff::run(diff_args, &args.read, &scheme),
+            Command::Index(index_args) => index::run(index_args, &args.read),
+            Command::Distinct(distinct_args) => distinct::run(distinct_args, &args.read, &scheme),
+            Command::Themes(themes_args) => gallery::run(themes_args, &args.read),
+            Command::Sniff(sniff_args) => sniff::run(sniff_args, &args.read, &scheme),
+            Command::Config(config_args) => init::run(config_args),
+            Command::Serve(serve_args) => serve::run(serve_args, &scheme),
+        };
+    }
+
+    let is_report = !args.checks.is_empty()
+        || args.stats || args.schema || args.missing || args.frequency.is_some() || args.timeline.is_some();
+    if args.inputs.len() > 1 && (args.output != OutputFormat::Table || args.export.is_some()) {
+        return Err("several input files can only be shown as tables or in the pager".into());
+    }
+    if args.preview {
+        if args.output != OutputFormat::Table {
+            return Err("--preview shows a table".into());
+        }
+        return preview::run(args, scheme);
+    }
+    if args.pager && !is_report && args.output == OutputFormat::Table && args.export.is_none() {
+        return run_pager(args, scheme);
+    }
+
+    for (idx, input) in args.inputs.iter().enumerate() {
+        if args.inputs.len() > 1 {
+            // Like `head` with several files
+            if idx > 0 {
+                println!();
+            }
+            println!("==> {} <==", input);
+        }
+        show(input, &scheme, &args)?;
+    }
+    Ok(())
+}
+
+fn show(input: &str, scheme: &ColorScheme, args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    let mut view = load_view(input, scheme, args)?;
+
+    if !args.checks.is_empty() {
+        return checks::run(
+            &args.checks,
+            view.headers.as_deref(),
+            &view.records,
+            &view.aliases,
+            scheme,
+        );
+    }
+
+    if args.stats || args.schema || args.missing || args.frequency.is_some() || args.timeline.is_some() {
+        let report = if let Some(timeline) = &args.timeline {
+            let column = column_index(view.headers.as_deref(), view.aliases.display(&timeline.column))?;
+            stats::Report::Timeline {
+                entries: timeline::count_timeline(&view.records, column, timeline.unit, &timeline.column)?,
+                unit: timeline.unit,
+            }
+        } else if let Some(name) = &args.frequency {
+            let column = column_index(view.headers.as_deref(), view.aliases.display(name))?;
+            stats::Report::Frequency {
+                entries: stats::count_frequencies(&view.records, column),
+                bars: args.bars,
+            }
+        } else if args.stats {
+            stats::Report::Stats(stats::profile_columns(view.headers.as_deref(), &view.records))
+        } else if args.schema {
+            stats::Report::Schema(stats::infer_schema(view.headers.as_deref(), &view.records))
+        } else {
+            stats::Report::Missing(stats::count_missing(view.headers.as_deref(), &view.records))
+        };
+        match args.output {
+            OutputFormat::Json => println!("{}", report.to_json()?),
+            _ => println!("{}", report.to_table(scheme)),
+        }
+        return Ok(());
+    }
+
+    shape_view(&mut view, args)?;
+    // A spreadsheet goes to the --export file, or anywhere but a terminal
+    if args.output == OutputFormat::Xlsx {
+        let bytes = write_workbook(&view, scheme, args)?;
+        match &args.export {
+            Some(path) => {
+                fs::write(path, bytes)?;
+                eprintln!("Exported {} rows to {}", view.records.len(), path);
+            }
+            None if io::stdout().is_terminal() => {
+                return Err("xlsx output is binary; redirect it to a file or give --export FILE".into());
+            }
+            None => io::stdout().write_all(&bytes)?,
+        }
+        return Ok(());
+    }
+    let View {
+        headers,
+        records,
+        group_labels,
+        aliases,
+        ..
+    } = view;
+    // Machine-readable output uses the original names unless asked otherwise
+    let export_headers = match (args.export_headers, &headers) {
+        (HeaderNames::Original, Some(h)) => {
+            Some(h.iter().map(|name| aliases.original(name).to_string()).collect())
+        }
+        _ => headers.clone(),
+    };
+
+    if let Some(path) = &args.export {
+        let file = fs::File::create(path)?;
+        let dialect = csv_dialect(input, args);
+        output::write_csv(file, export_headers.as_deref(), &records, args.show_row_numbers, &dialect)?;
+        eprintln!("Exported {} rows to {}", records.len(), path);
+        return Ok(());
+    }
+
+    match args.output {
+        OutputFormat::Json => {
+            println!("{}", output::records_to_json(export_headers.as_deref(), &records)?);
+            return Ok(());
+        }
+        OutputFormat::Markdown => {
+            let markdown =
+                output::records_to_markdown(headers.as_deref(), &records, args.show_row_numbers);
+            println!("{}", markdown);
+            return Ok(());
+        }
+        OutputFormat::Html => {
+            let html = output::records_to_html(
+                headers.as_deref(),
+                &records,
+                group_labels.as_deref(),
+                scheme,
+                args.show_row_numbers,
+            );
+            println!("{}", html);
+            return Ok(());
+        }
+        OutputFormat::Csv => {
+            let dialect = csv_dialect(input, args);
+            output::write_csv(io::stdout(), export_headers.as_deref(), &records, args.show_row_numbers, &dialect)?;
+            return Ok(());
+        }
+        OutputFormat::Table | OutputFormat::Xlsx => {}
+    }
+
+    if args.a11y {
+        for line in layouts::accessible_lines(headers.as_deref(), &records) {
+            println!("{}", line);
+        }
+    } else if args.vertical || output_width().is_some_and(|width| too_narrow(headers.as_deref(), &records, scheme, width)) {
+        let (lines, _) = layouts::vertical_lines(headers.as_deref(), &records, scheme, styled(), output_width());
+        for line in lines {
+            println!("{}", line);
+        }
     } else {
         // Use normal table display
-        let table = create_table(headers, records, &scheme, &args);
-        println!("{}", table);
+        let suggest_properties = !args.properties
+            && io::stderr().is_terminal()
+            && layouts::looks_like_properties(headers.as_deref(), &records);
+        let table = create_table(headers, records, scheme, args, styled());
+        println!("{}", render_table(&table, group_labels.as_deref(), scheme, args));
+        if suggest_properties {
+            eprintln!("hint: this looks like a key/value file, try --properties");
+        }
+    }
+
+    Ok(())
+}
+
+// The width of the terminal the output goes to, if it goes to one
+fn output_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    crossterm::terminal::size().ok().map(|(width, _)| width as usize)
+}
+
+// Whether a terminal is narrower than the table's narrowest column with
+// its borders, which get the records one field per line instead
+fn too_narrow(headers: Option<&[String]>, records: &[Vec<Field>], scheme: &ColorScheme, width: usize) -> bool {
+    let names = stats::column_names(headers, records);
+    let narrowest = (0..names.len())
+        .map(|col| {
+            let widest = records
+                .iter()
+                .filter_map(|record| record.get(col))
+                .flat_map(|val| val.split('\n'))
+                .map(UnicodeWidthStr::width)
+                .fold(names[col].width(), usize::max);
+            scheme.truncate.max_width.map_or(widest, |max| widest.min(max))
+        })
+        .min();
+    // A border and a space of padding on each side
+    narrowest.is_some_and(|column| column + 4 > width)
+}
+
+// How the rows on screen were picked, e.g. `filter: city=Paris · sort: amount desc`
+fn view_summary(args: &Args) -> Option<String> {
+    let mut parts = Vec::new();
+    if let Some(filter) = &args.filter {
+        parts.push(format!("filter: {}", filter));
+    }
+    if let Some(column) = &args.sort {
+        let order = if args.desc { " desc" } else { "" };
+        parts.push(format!("sort: {}{}", column, order));
+    }
+    if let Some(columns) = &args.columns {
+        parts.push(format!("columns: {}", columns.join(",")));
+    }
+    (!parts.is_empty()).then(|| parts.join(" · "))
+}
+
+// Files at least this large open in the pager with their first rows
+// while the rest is read
+const READ_AHEAD_BYTES: u64 = 16 << 20;
+const FIRST_ROWS: usize = 1_000;
+
+// The first rows of a large plain CSV or TSV file, with the whole file
+// coming through the receiver once another thread has read it. None when
+// the file is read here and now: it is small, or its first rows aren't
+// the first ones shown, as with --sort, or --derive needs all of them.
+type ReadAhead = Option<(CsvData, Receiver<Result<CsvData, String>>)>;
+
+fn read_ahead(input: &str, args: &Args) -> Result<ReadAhead, Box<dyn std::error::Error>> {
+    let shown_first = args.tail.is_none()
+        && args.rows.is_none()
+        && args.sort.is_none()
+        && args.derive.is_empty()
+        && args.max_rows.is_none_or(|max| max > FIRST_ROWS);
+    // Scripted keys and --start act on every row right away
+    let interactive = args.keys.is_none() && args.start.is_none() && !args.no_tty;
+    let large = fs::metadata(input).is_ok_and(|metadata| metadata.len() >= READ_AHEAD_BYTES);
+    if !shown_first || !interactive || args.follow || !large {
+        return Ok(None);
+    }
+    let Some((data, false)) = preview::read_start(input, FIRST_ROWS, &args.read)? else {
+        return Ok(None);
+    };
+    // Warnings about the rest would land on the pager's screen
+    let (input, mut options) = (input.to_string(), args.read.clone());
+    options.quiet = true;
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+        let _ = sender.send(read_csv_data(&input, &options).map_err(|e| e.to_string()));
+    });
+    Ok(Some((data, receiver)))
+}
+
+// Every input becomes a tab of its own
+fn run_pager(mut args: Args, scheme: ColorScheme) -> Result<(), Box<dyn std::error::Error>> {
+    let scheme = Rc::new(scheme);
+    let pager_config = scheme.pager.unwrap_or_default();
+
+    if args.follow && args.inputs.iter().any(|input| input == "-") {
+        return Err("--follow needs a file, standard input can't be watched".into());
+    }
+    if args.follow && args.inputs.iter().any(|input| source::is_remote(input)) {
+        return Err("--follow needs a local file, remote ones can't be watched".into());
+    }
+
+    source::read_into_memory();
+    // Every file is read before the pager takes the screen, so warnings
+    // about them can still be printed, except for the rest of a large one.
+    // A followed file is then read as it grows, a windowed one through its
+    // index each time.
+    let mut views = Vec::new();
+    for input in &args.inputs {
+        let growing = match args.follow && args.tail.is_none() && args.rows.is_none() {
+            true => follow::Growing::open(input, &args.read)?,
+            false => None,
+        };
+        let mut rest = None;
+        let mut view = match &growing {
+            Some(growing) => view_of(growing.data(), &scheme, &args)?,
+            None => match read_ahead(input, &args)? {
+                Some((first, receiver)) => {
+                    rest = Some(receiver);
+                    view_of(first, &scheme, &args)?
+                }
+                None => load_view(input, &scheme, &args)?,
+            },
+        };
+        shape_view(&mut view, &args)?;
+        views.push((view, growing, rest));
+    }
+    args.read.quiet = true;
+    let args = Rc::new(args);
+
+    let mut pager: Option<Pager> = None;
+    for (input, (view, mut growing, rest)) in args.inputs.iter().zip(views) {
+        let layouts = pager_layouts(view, &scheme, &args);
+        let reading = rest.map(|rest| {
+            let (args, scheme) = (Rc::clone(&args), Rc::clone(&scheme));
+            Box::new(move || {
+                let data = match rest.try_recv() {
+                    Ok(data) => data,
+                    Err(TryRecvError::Empty) => return None,
+                    Err(TryRecvError::Disconnected) => Err("reading stopped".to_string()),
+                };
+                let view = data.and_then(|data| {
+                    let mut view = view_of(data, &scheme, &args).map_err(|e| e.to_string())?;
+                    shape_view(&mut view, &args).map_err(|e| e.to_string())?;
+                    Ok(view)
+                });
+                Some(view.map(|view| pager_layouts(view, &scheme, &args)))
+            }) as ReadingFn
+        });
+        let sidecar = meta::Sidecar::load(input)?;
+        // Standard input can only be read once
+        let reload = (input != "-").then(|| {
+            let (input, args, scheme) = (input.clone(), Rc::clone(&args), Rc::clone(&scheme));
+            Box::new(move || {
+                let view = match &mut growing {
+                    Some(growing) => growing.refresh().and_then(|data| view_of(data, &scheme, &args)),
+                    None => load_view(&input, &scheme, &args),
+                };
+                let mut view = view.map_err(|e| e.to_string())?;
+                shape_view(&mut view, &args).map_err(|e| e.to_string())?;
+                let mut layouts = pager_layouts(view, &scheme, &args);
+                // The warnings about a last record cut off are left out of
+                // reloads, so the status tells of it instead
+                let partial = growing.as_ref().and_then(follow::Growing::partial);
+                layouts.note = partial.map(|line| format!("line {} is still being written", line));
+                Ok(layouts)
+            }) as ReloadFn
+        });
+
+        match &mut pager {
+            None => {
+                let mut first = Pager::new(layouts.content, None, pager_config)?;
+                first.set_alternate(layouts.alternate);
+                first.set_jumps(layouts.jumps);
+                if let Some(restyle) = layouts.restyle {
+                    first.set_restyle(restyle);
+                }
+                if let Some(refilter) = layouts.refilter {
+                    first.set_refilter(refilter);
+                }
+                if let Some(reload) = reload {
+                    first.set_reload(reload);
+                }
+                if let Some(reading) = reading {
+                    first.set_reading(reading);
+                }
+                first.set_tab_name(input.clone());
+                if let Some(sidecar) = sidecar {
+                    first.set_sidecar(sidecar);
+                }
+                pager = Some(first);
+            }
+            Some(pager) => pager.add_tab(input.clone(), layouts, reload, reading, sidecar),
+        }
+    }
+
+    if let Some(mut pager) = pager {
+        if args.follow {
+            pager.set_follow();
+        }
+        if let Some(columns) = args.freeze_cols {
+            pager.set_freeze_cols(columns);
+        }
+        if let Some(keys) = &args.keys {
+            pager.set_keys(keys.0.clone());
+        }
+        if args.no_tty {
+            pager.set_headless(args.render_size);
+        }
+        if let Some(start) = &args.start {
+            pager.start_at(start);
+        }
+        if let Some(summary) = view_summary(&args) {
+            pager.set_view_summary(summary);
+        }
+        // A plain status bar is drawn in reverse video instead
+        if let (Some(sgr), false) = (palette::sgr(ColorScheme::hex_to_color(&scheme.status_bar), true), plain()) {
+            pager.set_status_color(sgr);
+        }
+        pager.run()?;
     }
-    
     Ok(())
 }
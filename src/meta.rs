@@ -0,0 +1,90 @@
+use crate::encryption::Encryption;
+use crate::source;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+
+// What has been learned about a file's columns, kept next to it in
+// `FILE.pcsv-meta.toml` for the next session and for whoever else opens it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    // The columns the pager leaves out, by name
+    pub hidden: Vec<String>,
+    // What a column holds, by name
+    pub notes: BTreeMap<String, String>,
+}
+
+// The metadata of one input and the file it lives in, which may not exist
+// until something is saved
+#[derive(Debug, Clone)]
+pub struct Sidecar {
+    path: PathBuf,
+    exists: bool,
+    pub meta: Metadata,
+}
+
+// Standard input and remote files have nowhere to keep one, and an
+// encrypted file's would give away its column names
+pub fn sidecar_path(input: &str) -> Option<PathBuf> {
+    (input != "-" && !source::is_remote(input) && Encryption::from_extension(input).is_none())
+        .then(|| PathBuf::from(format!("{}.pcsv-meta.toml", input)))
+}
+
+impl Sidecar {
+    pub fn load(input: &str) -> Result<Option<Self>, String> {
+        let Some(path) = sidecar_path(input) else {
+            return Ok(None);
+        };
+        let (meta, exists) = match fs::read_to_string(&path) {
+            Ok(content) => {
+                let meta = toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))?;
+                (meta, true)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => (Metadata::default(), false),
+            Err(e) => return Err(format!("{}: {}", path.display(), e)),
+        };
+        Ok(Some(Sidecar { path, exists, meta }))
+    }
+
+    pub fn path(&self) -> &PathBuf {
+        &self.path
+    }
+
+    pub fn note(&self, column: &str) -> Option<&str> {
+        self.meta.notes.get(column).map(String::as_str)
+    }
+
+    // Sets a column's note, or removes it when blank, and saves
+    pub fn set_note(&mut self, column: &str, note: &str) -> Result<(), String> {
+        let note = note.trim();
+        if note.is_empty() {
+            self.meta.notes.remove(column);
+        } else {
+            self.meta.notes.insert(column.to_string(), note.to_string());
+        }
+        self.save()
+    }
+
+    // Remembers which columns are hidden. Only a sidecar that was already
+    // written is updated, so hiding a column doesn't leave files behind.
+    pub fn set_hidden(&mut self, hidden: Vec<String>) -> Result<(), String> {
+        if self.meta.hidden == hidden {
+            return Ok(());
+        }
+        self.meta.hidden = hidden;
+        if self.exists {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    fn save(&mut self) -> Result<(), String> {
+        let content = toml::to_string(&self.meta).map_err(|e| e.to_string())?;
+        fs::write(&self.path, content).map_err(|e| format!("{}: {}", self.path.display(), e))?;
+        self.exists = true;
+        Ok(())
+    }
+}
@@ -0,0 +1,304 @@
+use crate::config::ColorScheme;
+use crate::stats::{column_names, column_values, dominant_type};
+use crate::store::Field;
+use crate::{detect_builtin_type, detect_data_type_cached, parse_boolean, plain_number, DataType};
+use clap::ValueEnum;
+use serde_json::{Map, Number, Value};
+use std::io::Write;
+
+// A value as JSON of its column's type, or None if it would lose
+// something that way: the zeros of a padded code like `007` or `02134`, or
+// the digits of a number too long to be exact
+fn typed_value(val: &str, ty: DataType) -> Option<Value> {
+    if detect_builtin_type(val) == DataType::Empty {
+        return Some(Value::Null);
+    }
+    match ty {
+        DataType::IntNumber | DataType::FloatNumber => {
+            let plain = plain_number(val);
+            let digits = plain.trim().trim_start_matches('-');
+            if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") {
+                return None;
+            }
+            if ty == DataType::IntNumber {
+                plain.parse::<i64>().ok().map(Value::from)
+            } else {
+                plain.parse::<f64>().ok().and_then(Number::from_f64).map(Value::Number)
+            }
+        }
+        DataType::Boolean => parse_boolean(val).map(Value::Bool),
+        // Amounts keep their symbol, which a bare number would lose
+        _ => Some(Value::String(val.to_string())),
+    }
+}
+
+// The type every value of a column is written as, so a column is all
+// numbers or all strings: the one covering its values, or text if one of
+// them can't be written as that
+fn column_types(records: &[Vec<Field>], columns: usize) -> Vec<DataType> {
+    (0..columns)
+        .map(|idx| {
+            let values = column_values(records, idx);
+            let ty = dominant_type(&values);
+            if values.iter().all(|val| typed_value(val, ty).is_some()) {
+                ty
+            } else {
+                DataType::Text
+            }
+        })
+        .collect()
+}
+
+fn json_value(val: &str, ty: DataType) -> Value {
+    typed_value(val, ty).unwrap_or_else(|| Value::String(val.to_string()))
+}
+
+pub fn records_to_json(
+    headers: Option<&[String]>,
+    records: &[Vec<Field>],
+) -> serde_json::Result<String> {
+    let names = column_names(headers, records);
+    let types = column_types(records, names.len());
+    let rows: Vec<Value> = records
+        .iter()
+        .map(|record| {
+            let object: Map<String, Value> = names
+                .iter()
+                .zip(record)
+                .zip(&types)
+                .map(|((name, val), &ty)| (name.clone(), json_value(val, ty)))
+                .collect();
+            Value::Object(object)
+        })
+        .collect();
+    serde_json::to_string_pretty(&rows)
+}
+
+// Each record as an array of values, for rows without a header
+pub fn records_to_json_arrays(records: &[Vec<Field>]) -> serde_json::Result<String> {
+    let types = column_types(records, records.iter().map(Vec::len).max().unwrap_or(0));
+    let rows: Vec<Value> = records
+        .iter()
+        .map(|record| Value::Array(record.iter().zip(&types).map(|(val, &ty)| json_value(val, ty)).collect()))
+        .collect();
+    serde_json::to_string_pretty(&rows)
+}
+
+fn escape_markdown(val: &str) -> String {
+    val.replace('|', "\\|").replace("\r\n", "<br>").replace('\n', "<br>")
+}
+
+pub fn records_to_markdown(
+    headers: Option<&[String]>,
+    records: &[Vec<Field>],
+    show_row_numbers: bool,
+) -> String {
+    let mut names = column_names(headers, records);
+    // Numeric columns are right-aligned, like they would be in a spreadsheet
+    let mut alignments: Vec<&str> = (0..names.len())
+        .map(|idx| match dominant_type(&column_values(records, idx)) {
+            DataType::IntNumber | DataType::FloatNumber => "---:",
+            _ => "---",
+        })
+        .collect();
+    if show_row_numbers {
+        names.insert(0, "#".to_string());
+        alignments.insert(0, "---:");
+    }
+
+    let mut lines = Vec::with_capacity(records.len() + 2);
+    let header: Vec<String> = names.iter().map(|name| escape_markdown(name)).collect();
+    lines.push(format!("| {} |", header.join(" | ")));
+    lines.push(format!("| {} |", alignments.join(" | ")));
+
+    for (row_idx, record) in records.iter().enumerate() {
+        let mut cells = Vec::with_capacity(names.len());
+        if show_row_numbers {
+            cells.push((row_idx + 1).to_string());
+        }
+        cells.extend(record.iter().map(|val| escape_markdown(val)));
+        lines.push(format!("| {} |", cells.join(" | ")));
+    }
+
+    lines.join("\n")
+}
+
+fn escape_html(val: &str) -> String {
+    let mut escaped = String::with_capacity(val.len());
+    for c in val.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            '\n' => escaped.push_str("<br>"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+// Inline styles only, so the table survives being pasted into an email.
+// The background is the scheme's.
+const TABLE_STYLE: &str = "border-collapse: collapse; font-family: monospace;";
+const CELL_STYLE: &str = "border: 1px solid #45475A; padding: 2px 8px;";
+
+pub fn records_to_html(
+    headers: Option<&[String]>,
+    records: &[Vec<Field>],
+    group_labels: Option<&[Option<String>]>,
+    scheme: &ColorScheme,
+    show_row_numbers: bool,
+) -> String {
+    let header_style = format!("{} color: {}; font-weight: bold;", CELL_STYLE, scheme.header);
+    let mut html = format!(
+        "<table style=\"{} background: {};\">\n<thead>\n",
+        TABLE_STYLE, scheme.background
+    );
+
+    if let Some(labels) = group_labels {
+        html.push_str("<tr>");
+        if show_row_numbers {
+            html.push_str(&format!("<th style=\"{}\"></th>", header_style));
+        }
+        let mut idx = 0;
+        while idx < labels.len() {
+            let span = match &labels[idx] {
+                Some(label) => labels[idx..]
+                    .iter()
+                    .take_while(|other| other.as_ref() == Some(label))
+                    .count(),
+                None => 1,
+            };
+            let label = labels[idx].as_deref().unwrap_or("");
+            html.push_str(&format!(
+                "<th colspan=\"{}\" style=\"{}\">{}</th>",
+                span,
+                header_style,
+                escape_html(label)
+            ));
+            idx += span;
+        }
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("<tr>");
+    if show_row_numbers {
+        html.push_str(&format!("<th style=\"{}\">#</th>", header_style));
+    }
+    for name in column_names(headers, records) {
+        html.push_str(&format!("<th style=\"{}\">{}</th>", header_style, escape_html(&name)));
+    }
+    html.push_str("</tr>\n</thead>\n<tbody>\n");
+
+    for (row_idx, record) in records.iter().enumerate() {
+        html.push_str("<tr>");
+        if show_row_numbers {
+            html.push_str(&format!(
+                "<td style=\"{} color: {};\">{}</td>",
+                CELL_STYLE,
+                scheme.header,
+                row_idx + 1
+            ));
+        }
+        for val in record {
+            let color = scheme.cell_hex(&detect_data_type_cached(val));
+            html.push_str(&format!(
+                "<td style=\"{} color: {};\">{}</td>",
+                CELL_STYLE,
+                color,
+                escape_html(val)
+            ));
+        }
+        html.push_str("</tr>\n");
+    }
+
+    html.push_str("</tbody>\n</table>");
+    html
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+}
+
+pub struct CsvDialect {
+    pub delimiter: u8,
+    pub quote_all: bool,
+    pub line_ending: LineEnding,
+}
+
+// `;`, a tab as `\t` or `tab`, or any other single ASCII character
+pub fn parse_delimiter(val: &str) -> Result<u8, String> {
+    match val {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ if val.len() == 1 && val.is_ascii() => Ok(val.as_bytes()[0]),
+        _ => Err(format!(
+            "invalid delimiter '{}', expected a single ASCII character",
+            val
+        )),
+    }
+}
+
+pub fn write_csv<W: Write>(
+    writer: W,
+    headers: Option<&[String]>,
+    records: &[Vec<Field>],
+    show_row_numbers: bool,
+    dialect: &CsvDialect,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let terminator = match dialect.line_ending {
+        LineEnding::Lf => csv::Terminator::Any(b'\n'),
+        LineEnding::Crlf => csv::Terminator::CRLF,
+    };
+    let quote_style = if dialect.quote_all {
+        csv::QuoteStyle::Always
+    } else {
+        csv::QuoteStyle::Necessary
+    };
+    let mut wtr = csv::WriterBuilder::new()
+        .flexible(true)
+        .delimiter(dialect.delimiter)
+        .quote_style(quote_style)
+        .terminator(terminator)
+        .from_writer(writer);
+
+    if let Some(h) = headers {
+        if show_row_numbers {
+            wtr.write_record(std::iter::once("#").chain(h.iter().map(|name| name.as_str())))?;
+        } else {
+            wtr.write_record(h)?;
+        }
+    }
+    for (row_idx, record) in records.iter().enumerate() {
+        if show_row_numbers {
+            let number = (row_idx + 1).to_string();
+            wtr.write_record(
+                std::iter::once(number.as_str()).chain(record.iter().map(|val| val.as_str())),
+            )?;
+        } else {
+            wtr.write_record(record)?;
+        }
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn types_json_by_column() {
+        let records: Vec<Vec<Field>> = [["007", "02134", "1.5", "12"], ["8", "10001", "2", "abc"]]
+            .iter()
+            .map(|record| record.iter().map(|&val| Field::from(val)).collect())
+            .collect();
+        let json = records_to_json_arrays(&records).unwrap();
+        let rows: Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(rows, serde_json::json!([["007", "02134", 1.5, "12"], ["8", "10001", 2.0, "abc"]]));
+    }
+}
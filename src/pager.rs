@@ -1,15 +1,27 @@
 use crossterm::{
     cursor,
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
-    execute,
-    style::{self, Color},
+    event::{self, Event, KeyCode, KeyEvent},
+    execute, queue,
+    style::{self, Attribute, Color},
     terminal::{self, ClearType},
 };
-use std::io::{self, stdout, Write};
-use std::sync::mpsc;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, stdout, IsTerminal, Write};
+use std::ops::{Range, RangeInclusive};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc};
 use std::thread;
-use std::time::Duration;
-use crate::config::PagerConfig;
+use std::time::{Duration, Instant, SystemTime};
+use crate::clipboard;
+use crate::output;
+use crate::config::{HeaderStyle, PagerConfig};
+use crate::filter::RowFilter;
+use crate::meta::Sidecar;
+use crate::store::Field;
+use crate::{detect_builtin_type, detect_data_type_cached, hides_text, is_null, plain, plain_amount, plain_number, raw_text, DataType};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 #[derive(Debug, Clone)]
 pub struct PagerState {
@@ -20,122 +32,2798 @@ pub struct PagerState {
     pub current_row: usize,
     pub terminal_height: u16,
     pub terminal_width: u16,
+    // Columns scrolled past to the right of the frozen ones, or steps of
+    // SCROLL_STEP for layouts without columns
+    pub columns_scrolled: usize,
+}
+
+// The smallest screen the pager draws on: one column, and a line of
+// content above the status bar. A terminal reporting less, as some do
+// while being resized, is drawn as if it were this size.
+const MIN_SIZE: (u16, u16) = (1, 2);
+
+fn usable_size(width: u16, height: u16) -> (u16, u16) {
+    (width.max(MIN_SIZE.0), height.max(MIN_SIZE.1))
 }
 
 impl PagerState {
     pub fn new(total_rows: usize) -> io::Result<Self> {
-        let (terminal_width, terminal_height) = terminal::size()?;
+        // Without a terminal, as when --keys runs headless, the screen is
+        // the classic 80x24
+        let (terminal_width, terminal_height) = terminal::size().unwrap_or((80, 24));
+        let (terminal_width, terminal_height) = usable_size(terminal_width, terminal_height);
         // Use full terminal height
         let rows_per_page = terminal_height as usize;
         let total_pages = if rows_per_page > 0 {
-            (total_rows + rows_per_page - 1) / rows_per_page
+            total_rows.div_ceil(rows_per_page)
+        } else {
+            1
+        };
+
+        Ok(Self {
+            current_page: 0,
+            total_pages,
+            rows_per_page,
+            total_rows,
+            current_row: 0,
+            terminal_height,
+            terminal_width,
+            columns_scrolled: 0,
+        })
+    }
+
+    pub fn go_to_page(&mut self, page: usize) {
+        self.current_page = page.min(self.total_pages.saturating_sub(1));
+        self.current_row = self.current_page * self.rows_per_page;
+    }
+
+    pub fn go_to_first(&mut self) {
+        self.go_to_page(0);
+    }
+
+    pub fn go_to_last(&mut self) {
+        self.go_to_page(self.total_pages.saturating_sub(1));
+    }
+
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.current_row = (self.current_row + lines).min(self.total_rows.saturating_sub(1));
+        // Update page to keep current row visible
+        self.current_page = self.current_row / self.rows_per_page;
+    }
+
+    pub fn scroll_up(&mut self, lines: usize) {
+        self.current_row = self.current_row.saturating_sub(lines);
+        // Update page to keep current row visible
+        self.current_page = self.current_row / self.rows_per_page;
+    }
+
+    pub fn set_total_rows(&mut self, total_rows: usize) {
+        self.total_rows = total_rows;
+        self.total_pages = if self.rows_per_page > 0 {
+            total_rows.div_ceil(self.rows_per_page)
+        } else {
+            1
+        };
+        self.current_row = self.current_row.min(total_rows.saturating_sub(1));
+        self.current_page = self.current_row / self.rows_per_page.max(1);
+    }
+
+    pub fn go_to_line(&mut self, line: usize) {
+        self.current_row = line.min(self.total_rows.saturating_sub(1));
+        self.current_page = self.current_row / self.rows_per_page.max(1);
+    }
+
+    pub fn get_viewport_start(&self) -> usize {
+        self.current_row
+    }
+
+    pub fn get_viewport_end(&self) -> usize {
+        (self.current_row + self.rows_per_page).min(self.total_rows)
+    }
+}
+
+// Line index where each data row of a rendered UTF8_FULL table starts.
+// Rows begin after the header rule (`╞`) and after every row separator.
+pub fn row_start_lines(lines: &[String]) -> Vec<usize> {
+    let body_start = lines
+        .iter()
+        .position(|line| line.starts_with('╞'))
+        .map_or(1, |idx| idx + 1);
+    let mut starts = Vec::new();
+    let mut next_is_row = true;
+    for (idx, line) in lines.iter().enumerate().skip(body_start) {
+        if line.starts_with('├') {
+            next_is_row = true;
+        } else if line.starts_with('└') {
+            break;
+        } else if next_is_row {
+            starts.push(idx);
+            next_is_row = false;
+        }
+    }
+    starts
+}
+
+// Display columns a layout without columns scrolls by, like the vertical one
+const SCROLL_STEP: usize = 8;
+
+// Length of the escape sequence `text` starts with: CSI up to its final
+// byte, OSC up to BEL or ESC \, or ESC and one character
+fn escape_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    match bytes.get(1) {
+        Some(b'[') => bytes[2..]
+            .iter()
+            .position(|b| (0x40..=0x7e).contains(b))
+            .map_or(bytes.len(), |end| end + 3),
+        Some(b']') => bytes[2..]
+            .iter()
+            .enumerate()
+            .find_map(|(idx, &b)| match b {
+                0x07 => Some(idx + 3),
+                0x1b => Some((idx + 4).min(bytes.len())),
+                _ => None,
+            })
+            .unwrap_or(bytes.len()),
+        Some(_) => 1 + text[1..].chars().next().map_or(0, char::len_utf8),
+        None => 1,
+    }
+}
+
+// A rendered line split into escape sequences and single characters
+fn pieces(line: &str) -> impl Iterator<Item = &str> {
+    let mut rest = line;
+    std::iter::from_fn(move || {
+        let c = rest.chars().next()?;
+        let len = if c == '\x1b' { escape_len(rest) } else { c.len_utf8() };
+        let (piece, tail) = rest.split_at(len);
+        rest = tail;
+        Some(piece)
+    })
+}
+
+fn piece_width(piece: &str) -> Option<usize> {
+    match piece.chars().next() {
+        Some('\x1b') | None => None,
+        Some(c) => Some(c.width().unwrap_or(0)),
+    }
+}
+
+// The display columns `from..from + width` of a rendered line. Escape
+// sequences are all kept, so colors carry over into the part shown, and a
+// wide character cut in half becomes spaces.
+pub fn cut_line(line: &str, from: usize, width: usize) -> String {
+    let end = from.saturating_add(width);
+    let mut out = String::new();
+    let mut x = 0;
+    for piece in pieces(line) {
+        let Some(w) = piece_width(piece) else {
+            out.push_str(piece);
+            continue;
+        };
+        if x >= from && x + w <= end {
+            out.push_str(piece);
+        } else if x < end && x + w > from {
+            out.push_str(&" ".repeat((x + w).min(end) - x.max(from)));
+        }
+        x += w;
+    }
+    out
+}
+
+// Text broken into lines of at most `width` columns, at its own line breaks
+// and wherever a line runs out of room, after the last space if it has one.
+// Tabs become spaces and other control characters are spelled out so they
+// can't move the cursor.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for part in text.split('\n') {
+        let (mut line, mut x) = (String::new(), 0);
+        let shown = part.chars().flat_map(|c| match c {
+            '\t' => "    ".chars().collect::<Vec<_>>(),
+            c if c.is_control() => c.escape_debug().collect(),
+            c => vec![c],
+        });
+        for c in shown {
+            let w = c.width().unwrap_or(0);
+            if x + w > width && x > 0 {
+                let rest = match line.rfind(' ') {
+                    Some(space) if space > 0 => line.split_off(space + 1),
+                    _ => String::new(),
+                };
+                lines.push(std::mem::replace(&mut line, rest));
+                x = UnicodeWidthStr::width(line.as_str());
+            }
+            line.push(c);
+            x += w;
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+// A JSON object or array laid out over several lines, or the text as it is
+fn pretty_json(text: &str) -> String {
+    match serde_json::from_str::<serde_json::Value>(text.trim()) {
+        Ok(value @ (serde_json::Value::Object(_) | serde_json::Value::Array(_))) => {
+            serde_json::to_string_pretty(&value).unwrap_or_else(|_| text.to_string())
+        }
+        _ => text.to_string(),
+    }
+}
+
+// A rendered line with display columns `from..to` in reverse video
+fn reverse_span(line: &str, from: usize, to: usize) -> String {
+    format!(
+        "{}\x1b[7m{}\x1b[27m{}",
+        cut_line(line, 0, from),
+        cut_line(line, from, to - from),
+        cut_line(line, to, usize::MAX)
+    )
+}
+
+// `COLSxROWS`, the size of the screen a headless pager draws
+pub fn parse_size(text: &str) -> Result<(u16, u16), String> {
+    let invalid = || format!("invalid size '{}', expected COLUMNSxROWS like 120x40", text);
+    let (columns, rows) = text.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let columns: u16 = columns.parse().map_err(|_| invalid())?;
+    let rows: u16 = rows.parse().map_err(|_| invalid())?;
+    if (columns, rows) != usable_size(columns, rows) {
+        return Err(format!(
+            "size '{}' is too small, it needs at least {} column and {} rows",
+            text, MIN_SIZE.0, MIN_SIZE.1
+        ));
+    }
+    Ok((columns, rows))
+}
+
+// Where the pager opens, given the way less takes it: `+N` for a row,
+// `+G` for the end and `+/pattern` for the first row matching a pattern
+#[derive(Debug, Clone)]
+pub enum StartAt {
+    Row(usize),
+    End,
+    Match(String),
+}
+
+pub fn parse_start(arg: &str) -> Result<StartAt, String> {
+    let invalid = || format!("invalid startup position '{}', expected +N, +G or +/PATTERN", arg);
+    let position = arg.strip_prefix('+').ok_or_else(invalid)?;
+    if let Some(pattern) = position.strip_prefix('/') {
+        if pattern.is_empty() {
+            return Err(invalid());
+        }
+        return Ok(StartAt::Match(pattern.to_string()));
+    }
+    if position == "G" {
+        return Ok(StartAt::End);
+    }
+    position.parse().map(StartAt::Row).map_err(|_| invalid())
+}
+
+// The count of a column's non-empty values and, for the numbers among
+// them, their sum, mean, min and max, the way a spreadsheet's status bar
+// shows them. Sums keep as many decimals as the most precise value.
+fn aggregates<'a>(values: impl Iterator<Item = &'a str>) -> String {
+    // Amounts of money count as the numbers they are
+    let plain = |val: &str| match detect_builtin_type(val) {
+        DataType::IntNumber | DataType::FloatNumber => Some(plain_number(val).trim().to_string()),
+        DataType::Currency => plain_amount(val),
+        _ => None,
+    };
+    let mut count = 0;
+    let mut numbers: Vec<(f64, &str)> = Vec::new();
+    for val in values.filter(|val| !is_null(val)) {
+        count += 1;
+        if let Some(num) = plain(val).and_then(|plain| plain.parse::<f64>().ok()) {
+            numbers.push((num, val));
+        }
+    }
+    let (Some(min), Some(max)) = (
+        numbers.iter().min_by(|a, b| a.0.total_cmp(&b.0)),
+        numbers.iter().max_by(|a, b| a.0.total_cmp(&b.0)),
+    ) else {
+        return format!("count {}, no numbers", count);
+    };
+    let decimals = numbers
+        .iter()
+        .filter_map(|(_, val)| plain(val))
+        .map(|plain| plain.split_once('.').map_or(0, |(_, fraction)| fraction.len()))
+        .max()
+        .unwrap_or(0);
+    let sum: f64 = numbers.iter().map(|(num, _)| num).sum();
+    let mean = sum / numbers.len() as f64;
+    let numeric = if numbers.len() < count {
+        format!(" ({} numbers)", numbers.len())
+    } else {
+        String::new()
+    };
+    format!(
+        "sum {:.*}  mean {:.*}  min {}  max {}  count {}{}",
+        decimals,
+        sum,
+        decimals + 2,
+        mean,
+        min.1.trim(),
+        max.1.trim(),
+        count,
+        numeric
+    )
+}
+
+// Text in reverse video, as the boxes and bars drawn over the content are
+fn reversed(text: &str) -> String {
+    format!("\x1b[7m{}\x1b[27m", text)
+}
+
+// One frame of the pager: the line for every row of the terminal, with
+// boxes drawn over them, shown all at once
+struct Screen {
+    lines: Vec<String>,
+}
+
+impl Screen {
+    fn new(height: usize) -> Self {
+        Screen {
+            lines: vec![String::new(); height],
+        }
+    }
+
+    fn set(&mut self, y: usize, line: String) {
+        if let Some(slot) = self.lines.get_mut(y) {
+            *slot = line;
+        }
+    }
+
+    // Draws text over a line from display column `x` on, keeping what is
+    // left and right of it
+    fn put(&mut self, x: usize, y: usize, text: &str) {
+        let Some(line) = self.lines.get_mut(y) else {
+            return;
+        };
+        let left = cut_line(line, 0, x);
+        let pad = x.saturating_sub(display_width(&left));
+        let right = cut_line(line, x + display_width(text), usize::MAX);
+        *line = format!("{}{}\x1b[0m{}\x1b[0m{}", left, " ".repeat(pad), text, right);
+    }
+
+    fn show(&self) -> io::Result<()> {
+        let mut out = stdout();
+        queue!(out, terminal::Clear(ClearType::All))?;
+        for (y, line) in self.lines.iter().enumerate() {
+            queue!(out, cursor::MoveTo(0, y as u16))?;
+            write!(out, "{}\x1b[0m", line)?;
+        }
+        out.flush()
+    }
+}
+
+// A record as one line of CSV, quoted where it needs to be
+fn csv_line(record: &[Field]) -> String {
+    let mut wtr = csv::Writer::from_writer(Vec::new());
+    let bytes = wtr
+        .write_record(record)
+        .ok()
+        .and_then(|_| wtr.into_inner().ok())
+        .unwrap_or_default();
+    String::from_utf8_lossy(&bytes).trim_end_matches('\n').to_string()
+}
+
+fn display_width(line: &str) -> usize {
+    pieces(line).filter_map(piece_width).sum()
+}
+
+// Formats the lines of a range of records, on rayon's worker threads,
+// which are as many as there are cores however many chunks are waiting
+pub type RenderFn = Arc<dyn Fn(Range<usize>) -> Vec<String> + Send + Sync>;
+
+// Records formatted together when a lazy layout scrolls into view
+const CHUNK_RECORDS: usize = 500;
+// Chunks kept on either side of the screen before they are dropped again
+const CHUNK_WINDOW: usize = 4;
+// How long a render waits for the chunks on screen before showing them as loading
+const CHUNK_WAIT: Duration = Duration::from_millis(50);
+
+// The records of a layout that is formatted a chunk at a time. Only the
+// line each record starts at is known up front.
+struct LazyBody {
+    render: RenderFn,
+    // The first line after the last record, where `tail` begins
+    body_end: usize,
+    tail: Vec<String>,
+    chunks: HashMap<usize, Vec<String>>,
+    pending: HashSet<usize>,
+    sender: mpsc::Sender<(usize, Vec<String>)>,
+    receiver: mpsc::Receiver<(usize, Vec<String>)>,
+}
+
+// Rendered lines plus the line each data record starts at. Lazy content
+// keeps only the lines above the records (the table header) in `lines`.
+#[derive(Default)]
+pub struct PagerContent {
+    lines: Vec<String>,
+    pub row_starts: Vec<usize>,
+    // Where the border left of each data column sits, plus the right
+    // edge; empty for layouts without columns. Anything left of the first
+    // (row numbers) stays in place when scrolling sideways.
+    pub column_edges: Vec<usize>,
+    // Whether each record but the last ends in a border line, as in the
+    // table layout, and the last is followed by the table's bottom edge
+    pub bordered: bool,
+    // Names of the columns in `column_edges`, for jumping to one
+    pub column_names: Vec<String>,
+    // The field of the records behind each of those columns, and the names
+    // of all fields, hidden ones included
+    pub fields: Vec<usize>,
+    pub field_names: Vec<String>,
+    // The cells behind the layout, for the cell cursor and copying
+    pub records: Option<Arc<Vec<Vec<Field>>>>,
+    lazy: Option<LazyBody>,
+}
+
+impl PagerContent {
+    // The value a record has in one of the columns of `column_edges`
+    fn cell(&self, record: usize, column: usize) -> Option<&str> {
+        let record = self.records.as_ref()?.get(record)?;
+        let field = self.fields.get(column).copied().unwrap_or(column);
+        Some(record.get(field).map_or("", |val| val.as_str()))
+    }
+
+    pub fn from_table_lines(lines: Vec<String>) -> Self {
+        let row_starts = row_start_lines(&lines);
+        Self {
+            lines,
+            row_starts,
+            column_edges: Vec::new(),
+            bordered: true,
+            column_names: Vec::new(),
+            fields: Vec::new(),
+            field_names: Vec::new(),
+            records: None,
+            lazy: None,
+        }
+    }
+
+    // `render` must return exactly the lines from the start of the first
+    // record in the range to the start of the one after it (or `body_end`)
+    pub fn lazy(
+        head: Vec<String>,
+        row_starts: Vec<usize>,
+        body_end: usize,
+        tail: Vec<String>,
+        render: RenderFn,
+    ) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            lines: head,
+            row_starts,
+            column_edges: Vec::new(),
+            bordered: false,
+            column_names: Vec::new(),
+            fields: Vec::new(),
+            field_names: Vec::new(),
+            records: None,
+            lazy: Some(LazyBody {
+                render,
+                body_end,
+                tail,
+                chunks: HashMap::new(),
+                pending: HashSet::new(),
+                sender,
+                receiver,
+            }),
+        }
+    }
+
+    pub fn line_count(&self) -> usize {
+        match &self.lazy {
+            Some(body) => body.body_end + body.tail.len(),
+            None => self.lines.len(),
+        }
+    }
+
+    // Index of the record shown at `line`
+    fn record_at(&self, line: usize) -> usize {
+        self.row_starts
+            .partition_point(|&start| start <= line)
+            .saturating_sub(1)
+    }
+
+    // The line after the contents of record `idx`, leaving out the border
+    // below it
+    fn record_end(&self, idx: usize) -> usize {
+        if let Some(&next) = self.row_starts.get(idx + 1) {
+            return next - usize::from(self.bordered);
+        }
+        match &self.lazy {
+            Some(body) => body.body_end,
+            None => {
+                let start = self.row_starts.get(idx).copied().unwrap_or(0).min(self.lines.len());
+                let edge = self.lines[start..]
+                    .iter()
+                    .position(|line| self.bordered && line.starts_with('└'));
+                edge.map_or(self.lines.len(), |offset| start + offset)
+            }
+        }
+    }
+
+    // Lines `start..end`, with `None` for those still being formatted.
+    // Missing chunks are sent off to be rendered and far away ones dropped.
+    fn visible_lines(&mut self, start: usize, end: usize) -> Vec<Option<String>> {
+        let head = self.lines.len();
+        let Some(body) = &mut self.lazy else {
+            return self.lines[start.min(head)..end.min(head)]
+                .iter()
+                .cloned()
+                .map(Some)
+                .collect();
+        };
+
+        let row_starts = &self.row_starts;
+        let chunk_of = |line: usize| {
+            row_starts.partition_point(|&start| start <= line).saturating_sub(1) / CHUNK_RECORDS
+        };
+        // The chunks holding the records on screen, if any are
+        let (body_start, body_stop) = (start.max(head), end.min(body.body_end));
+        let shown = (body_start < body_stop).then(|| chunk_of(body_start)..=chunk_of(body_stop - 1));
+
+        if let Some(shown) = shown.clone() {
+            let (first, last) = (*shown.start(), *shown.end());
+            body.chunks
+                .retain(|&chunk, _| chunk + CHUNK_WINDOW >= first && chunk <= last + CHUNK_WINDOW);
+            // The chunks just past either edge too, so scrolling finds them ready
+            let record_count = row_starts.len();
+            let wanted = first.saturating_sub(1)..=(last + 1).min((record_count - 1) / CHUNK_RECORDS);
+            for chunk in wanted {
+                if body.chunks.contains_key(&chunk) || !body.pending.insert(chunk) {
+                    continue;
+                }
+                let (render, sender) = (Arc::clone(&body.render), body.sender.clone());
+                let records = chunk * CHUNK_RECORDS..((chunk + 1) * CHUNK_RECORDS).min(record_count);
+                rayon::spawn(move || {
+                    let _ = sender.send((chunk, render(records)));
+                });
+            }
+
+            let deadline = Instant::now() + CHUNK_WAIT;
+            while shown.clone().any(|chunk| !body.chunks.contains_key(&chunk)) {
+                let wait = deadline.saturating_duration_since(Instant::now());
+                match body.receiver.recv_timeout(wait) {
+                    Ok((chunk, lines)) => {
+                        body.pending.remove(&chunk);
+                        body.chunks.insert(chunk, lines);
+                    }
+                    Err(_) => break,
+                }
+            }
+        }
+
+        (start..end)
+            .map(|line| {
+                if line < head {
+                    return self.lines.get(line).cloned();
+                }
+                if line >= body.body_end {
+                    return body.tail.get(line - body.body_end).cloned();
+                }
+                let chunk = chunk_of(line);
+                let chunk_start = row_starts[chunk * CHUNK_RECORDS];
+                body.chunks
+                    .get(&chunk)
+                    .and_then(|lines| lines.get(line - chunk_start))
+                    .cloned()
+            })
+            .collect()
+    }
+
+    // Takes in chunks that finished rendering; true if any arrived
+    fn receive_chunks(&mut self) -> bool {
+        let Some(body) = &mut self.lazy else {
+            return false;
+        };
+        let mut received = false;
+        while let Ok((chunk, lines)) = body.receiver.try_recv() {
+            body.pending.remove(&chunk);
+            body.chunks.insert(chunk, lines);
+            received = true;
+        }
+        received
+    }
+
+    fn is_loading(&self) -> bool {
+        self.lazy.as_ref().is_some_and(|body| !body.pending.is_empty())
+    }
+}
+
+// A named record, such as the first row of a group
+pub struct JumpTarget {
+    pub label: String,
+    pub record: usize,
+}
+
+// Everything the pager shows for one snapshot of the data
+pub struct PagerLayouts {
+    pub content: PagerContent,
+    pub alternate: PagerContent,
+    pub jumps: Vec<JumpTarget>,
+    // Rebuilds the table layout with another header style, if it can be
+    pub restyle: Option<RestyleFn>,
+    // Narrows the layouts down to the records matching an expression
+    pub refilter: Option<FilterFn>,
+    // Something about the reading to show with the reload's status
+    pub note: Option<String>,
+}
+
+// The layouts of the records the filters typed at `&` let through, with
+// where each of them is among all `total` records
+pub struct FilteredLayouts {
+    pub layouts: PagerLayouts,
+    pub rows: Vec<usize>,
+    pub total: usize,
+}
+
+pub type FilterFn = Rc<dyn Fn(&FilterStack) -> Result<FilteredLayouts, String>>;
+
+// One expression typed at `&`, kept until it is removed in the `F` list.
+// It can be switched off for a while, or negated to let through the rows
+// it doesn't match.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackedFilter {
+    pub expr: String,
+    pub enabled: bool,
+    pub negated: bool,
+}
+
+// Every filter of the session, and whether a row is shown when any of the
+// ones switched on matches it instead of all of them
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FilterStack {
+    pub filters: Vec<StackedFilter>,
+    pub any: bool,
+}
+
+impl FilterStack {
+    pub fn active(&self) -> impl Iterator<Item = &StackedFilter> {
+        self.filters.iter().filter(|filter| filter.enabled)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active().next().is_some()
+    }
+
+    // The filters switched on as one expression, like `a & !b`
+    fn summary(&self) -> String {
+        let joint = if self.any { " | " } else { " & " };
+        self.active()
+            .map(|filter| format!("{}{}", if filter.negated { "!" } else { "" }, filter.expr))
+            .collect::<Vec<_>>()
+            .join(joint)
+    }
+}
+
+// How the table layout is drawn: the header style, whether cells show
+// their raw text, and the fields left out
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableStyle {
+    pub header: HeaderStyle,
+    pub raw: bool,
+    pub hidden: Vec<usize>,
+    // The screen width the columns are narrowed to fit
+    pub width: Option<usize>,
+}
+
+impl TableStyle {
+    pub fn new(header: HeaderStyle) -> Self {
+        TableStyle {
+            header,
+            raw: false,
+            hidden: Vec::new(),
+            width: None,
+        }
+    }
+}
+
+pub type RestyleFn = Rc<dyn Fn(TableStyle) -> PagerContent>;
+
+// Re-reads the input with the original filters and sorts applied
+pub type ReloadFn = Box<dyn FnMut() -> Result<PagerLayouts, String>>;
+
+// The rest of a file still being read on another thread: None until all
+// of it is
+pub type ReadingFn = Box<dyn FnMut() -> Option<Result<PagerLayouts, String>>>;
+
+type FileStamp = (u64, SystemTime);
+
+fn file_stamp(path: &str) -> Option<FileStamp> {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+fn note_suffix(note: Option<String>) -> String {
+    note.map(|note| format!("; {}", note)).unwrap_or_default()
+}
+
+// The fields a sidecar hides, as long as that leaves one to show
+fn hidden_fields(sidecar: &Sidecar, content: &PagerContent) -> Vec<usize> {
+    let names = &content.field_names;
+    let hidden: Vec<usize> = (0..names.len())
+        .filter(|&field| sidecar.meta.hidden.contains(&names[field]))
+        .collect();
+    if hidden.len() < names.len() {
+        hidden
+    } else {
+        Vec::new()
+    }
+}
+
+// Whether the screen is narrower than the table's narrowest column, with
+// the row numbers left of it
+fn too_narrow(content: &PagerContent, width: usize) -> bool {
+    let edges = &content.column_edges;
+    let narrowest = edges.windows(2).map(|pair| pair[1] - pair[0]).min();
+    narrowest.is_some_and(|column| edges[0] + column + 1 > width)
+}
+
+// A file that is open in the background, with everything needed to come
+// back to it where it was left
+struct Tab {
+    state: PagerState,
+    content: PagerContent,
+    alternate: Option<PagerContent>,
+    jumps: Vec<JumpTarget>,
+    reload: Option<ReloadFn>,
+    reading: Option<ReadingFn>,
+    restyle: Option<RestyleFn>,
+    table_style: TableStyle,
+    toggled: bool,
+    narrowed: bool,
+    refilter: Option<FilterFn>,
+    filters: FilterStack,
+    filtered: Option<RowsFilter>,
+    marked: Vec<usize>,
+}
+
+// The full value of a cell, opened with `o` and scrolled a line at a time
+struct CellPopup {
+    title: String,
+    text: String,
+    scroll: usize,
+}
+
+// The `C` list of every column, to show or hide them, and the note being
+// written for the selected one after `n`
+struct ColumnList {
+    selected: usize,
+    note: Option<String>,
+}
+
+struct JumpMenu {
+    query: String,
+    selected: usize,
+}
+
+// The `c` prompt for a column to jump to. Tab cycles `completion` through
+// the names matching what was typed.
+struct ColumnPrompt {
+    typed: String,
+    completion: Option<usize>,
+}
+
+// How long a column jumped to stays highlighted
+const HIGHLIGHT_TIME: Duration = Duration::from_millis(1500);
+
+fn row_count(count: usize) -> String {
+    match count {
+        1 => "1 row".to_string(),
+        count => format!("{} rows", count),
+    }
+}
+
+// What the rows picked with `V` are copied or saved as
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExportFormat {
+    Csv,
+    Tsv,
+    Markdown,
+    Json,
+}
+
+impl ExportFormat {
+    // The format a file name's extension stands for, CSV for any other
+    fn from_path(path: &str) -> Self {
+        let ext = Path::new(path).extension().and_then(|ext| ext.to_str()).unwrap_or_default();
+        match ext.to_ascii_lowercase().as_str() {
+            "tsv" | "tab" => ExportFormat::Tsv,
+            "md" | "markdown" => ExportFormat::Markdown,
+            "json" => ExportFormat::Json,
+            _ => ExportFormat::Csv,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Tsv => "TSV",
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Json => "JSON",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+// The prompt `y` opens on a selection: the format to copy it in, or with
+// `to_file` the format and then the file to save it to
+struct ExportPrompt {
+    rows: RangeInclusive<usize>,
+    to_file: bool,
+    path: Option<(ExportFormat, String)>,
+}
+
+// The rows the filters kept: which of all `total` records each one is
+struct RowsFilter {
+    rows: Vec<usize>,
+    total: usize,
+}
+
+// The `F` list of the filters typed at `&`
+struct FilterList {
+    selected: usize,
+}
+
+pub struct Pager {
+    state: PagerState,
+    content: PagerContent,
+    // The other layout, switched to with `v`
+    alternate: Option<PagerContent>,
+    header: Option<String>,
+    config: PagerConfig,
+    jumps: Vec<JumpTarget>,
+    jump_menu: Option<JumpMenu>,
+    // What is being typed at `:`, a row number or a command
+    command_prompt: Option<String>,
+    column_prompt: Option<ColumnPrompt>,
+    // What is being typed at `&`
+    filter_prompt: Option<String>,
+    // What is being typed at `/`, and the text last searched for, which
+    // `n` and `N` look for again
+    search_prompt: Option<String>,
+    search: Option<String>,
+    // The column last jumped to, and since when
+    highlight: Option<(usize, Instant)>,
+    // Digits typed before a key, like the 123 of `123g`
+    count: Option<usize>,
+    // Whether `y` waits for what to copy: `y` the row or cell, `c` the
+    // cell and `C` the column
+    yanking: bool,
+    // Whether the `?` key list is open
+    help: bool,
+    // The record and column of the cell picked with the cursor `Enter`
+    // turns on
+    cursor: Option<(usize, usize)>,
+    popup: Option<CellPopup>,
+    column_list: Option<ColumnList>,
+    reload: Option<ReloadFn>,
+    // Shows the whole file once it is read, while its first rows are shown
+    reading: Option<ReadingFn>,
+    restyle: Option<RestyleFn>,
+    // How the table's header is shown, switched with `H`, whether its
+    // cells are raw, switched with `R`, and the columns hidden with `-`
+    table_style: TableStyle,
+    // Whether `v` has swapped the layouts
+    toggled: bool,
+    // Whether they were swapped because no column of the table fits on
+    // screen, to swap back once one does
+    narrowed: bool,
+    refilter: Option<FilterFn>,
+    // The filters typed at `&`, and the rows shown while any is switched on
+    filters: FilterStack,
+    filtered: Option<RowsFilter>,
+    filter_list: Option<FilterList>,
+    // The columns marked with `m` in the `C` list, for `=` to aggregate
+    marked: Vec<usize>,
+    // The record a `V` selection started at; it runs to the cursor's, or
+    // without the cursor to the first one on screen
+    visual: Option<usize>,
+    export_prompt: Option<ExportPrompt>,
+    // Whether selections are copied and saved with the header, switched
+    // with `h` in the export prompt
+    export_headers: bool,
+    // Shown on the bottom line until the next key press
+    status: Option<String>,
+    // One name per open file; only the active one isn't parked
+    tab_names: Vec<String>,
+    parked: Vec<Option<Tab>>,
+    active: usize,
+    // With --follow, the size and modification time each file was last seen with
+    follow: bool,
+    watched: Vec<Option<FileStamp>>,
+    // The notes and hidden columns kept next to each file
+    sidecars: Vec<Option<Sidecar>>,
+    // Columns pinned on the left while scrolling sideways, when `frozen`
+    freeze_cols: usize,
+    frozen: bool,
+    // The filter, sort and columns the rows were picked with, for the
+    // status bar
+    view_summary: Option<String>,
+    // Background of the status bar; without one it is drawn in reverse video
+    status_color: Option<String>,
+    // Keys from --keys, pressed before any typed ones
+    script: Option<Vec<KeyEvent>>,
+    // Whether the screen is printed instead of shown on the terminal
+    headless: bool,
+}
+
+impl Pager {
+    pub fn new(content: PagerContent, header: Option<String>, config: PagerConfig) -> io::Result<Self> {
+        let state = PagerState::new(content.line_count())?;
+        let mut pager = Self {
+            state,
+            content,
+            alternate: None,
+            header,
+            config,
+            jumps: Vec::new(),
+            jump_menu: None,
+            command_prompt: None,
+            column_prompt: None,
+            filter_prompt: None,
+            search_prompt: None,
+            search: None,
+            highlight: None,
+            count: None,
+            yanking: false,
+            help: false,
+            cursor: None,
+            popup: None,
+            column_list: None,
+            reload: None,
+            reading: None,
+            restyle: None,
+            table_style: TableStyle::new(config.header),
+            toggled: false,
+            narrowed: false,
+            refilter: None,
+            filters: FilterStack::default(),
+            filtered: None,
+            filter_list: None,
+            marked: Vec::new(),
+            visual: None,
+            export_prompt: None,
+            export_headers: true,
+            status: None,
+            tab_names: vec![String::new()],
+            parked: vec![None],
+            active: 0,
+            follow: false,
+            watched: vec![None],
+            sidecars: vec![None],
+            freeze_cols: 1,
+            frozen: false,
+            view_summary: None,
+            status_color: None,
+            script: None,
+            headless: false,
+        };
+        pager.fit_to_terminal();
+        Ok(pager)
+    }
+
+    pub fn set_jumps(&mut self, jumps: Vec<JumpTarget>) {
+        self.jumps = jumps;
+    }
+
+    pub fn set_alternate(&mut self, alternate: PagerContent) {
+        self.alternate = Some(alternate);
+    }
+
+    pub fn set_reload(&mut self, reload: ReloadFn) {
+        self.reload = Some(reload);
+    }
+
+    pub fn set_reading(&mut self, reading: ReadingFn) {
+        self.reading = Some(reading);
+    }
+
+    pub fn set_restyle(&mut self, restyle: RestyleFn) {
+        self.restyle = Some(restyle);
+    }
+
+    pub fn set_refilter(&mut self, refilter: FilterFn) {
+        self.refilter = Some(refilter);
+    }
+
+    // Pins the first `columns` columns from the start, as --freeze-cols
+    pub fn set_freeze_cols(&mut self, columns: usize) {
+        self.freeze_cols = columns.max(1);
+        self.frozen = columns > 0;
+    }
+
+    pub fn set_view_summary(&mut self, summary: String) {
+        self.view_summary = Some(summary);
+    }
+
+    // The SGR parameters of the status bar's background
+    pub fn set_status_color(&mut self, sgr: String) {
+        self.status_color = Some(sgr);
+    }
+
+    pub fn set_keys(&mut self, keys: Vec<KeyEvent>) {
+        self.script = Some(keys);
+    }
+
+    // Prints the screen instead of taking over the terminal, drawn at the
+    // given size rather than the terminal's
+    pub fn set_headless(&mut self, size: Option<(u16, u16)>) {
+        self.headless = true;
+        if let Some((width, height)) = size {
+            self.state.terminal_width = width;
+            self.state.terminal_height = height;
+            self.fit_to_terminal();
+        }
+    }
+
+    pub fn set_tab_name(&mut self, name: String) {
+        self.tab_names[self.active] = name;
+    }
+
+    // The sidecar of the active file, with the columns it hides hidden
+    pub fn set_sidecar(&mut self, sidecar: Sidecar) {
+        let mut style = self.table_style.clone();
+        style.hidden = hidden_fields(&sidecar, &self.content);
+        self.sidecars[self.active] = Some(sidecar);
+        if style.hidden != self.table_style.hidden {
+            self.restyle_table(style);
+        }
+    }
+
+    // Opens another file in the background, switched to with Tab
+    pub fn add_tab(
+        &mut self,
+        name: String,
+        layouts: PagerLayouts,
+        reload: Option<ReloadFn>,
+        reading: Option<ReadingFn>,
+        sidecar: Option<Sidecar>,
+    ) {
+        let mut table_style = TableStyle::new(self.config.header);
+        let mut content = layouts.content;
+        if let (Some(sidecar), Some(restyle)) = (&sidecar, &layouts.restyle) {
+            table_style.hidden = hidden_fields(sidecar, &content);
+            if !table_style.hidden.is_empty() {
+                content = restyle(table_style.clone());
+            }
+        }
+        let mut state = self.state.clone();
+        state.current_row = 0;
+        state.current_page = 0;
+        state.columns_scrolled = 0;
+        state.set_total_rows(content.line_count());
+        self.tab_names.push(name);
+        self.watched.push(None);
+        self.sidecars.push(sidecar);
+        self.parked.push(Some(Tab {
+            state,
+            content,
+            alternate: Some(layouts.alternate),
+            jumps: layouts.jumps,
+            reload,
+            reading,
+            restyle: layouts.restyle,
+            table_style,
+            toggled: false,
+            narrowed: false,
+            refilter: layouts.refilter,
+            filters: FilterStack::default(),
+            filtered: None,
+            marked: Vec::new(),
+        }));
+        self.fit_to_terminal();
+    }
+
+    // Lines taken from the top of the screen by the tab bar
+    fn chrome_lines(&self) -> usize {
+        usize::from(self.tab_names.len() > 1)
+    }
+
+    // Rows left for the content between the tab bar and the status bar
+    fn fit_to_terminal(&mut self) {
+        self.state.rows_per_page =
+            (self.state.terminal_height as usize).saturating_sub(self.chrome_lines() + 1).max(1);
+        self.state.set_total_rows(self.content.line_count());
+    }
+
+    fn switch_tab(&mut self, index: usize) {
+        if index == self.active {
+            return;
+        }
+        let Some(tab) = self.parked[index].take() else {
+            return;
+        };
+        let (width, height) = (self.state.terminal_width, self.state.terminal_height);
+        let current = Tab {
+            state: std::mem::replace(&mut self.state, tab.state),
+            content: std::mem::replace(&mut self.content, tab.content),
+            alternate: std::mem::replace(&mut self.alternate, tab.alternate),
+            jumps: std::mem::replace(&mut self.jumps, tab.jumps),
+            reload: std::mem::replace(&mut self.reload, tab.reload),
+            reading: std::mem::replace(&mut self.reading, tab.reading),
+            restyle: std::mem::replace(&mut self.restyle, tab.restyle),
+            table_style: std::mem::replace(&mut self.table_style, tab.table_style),
+            toggled: std::mem::replace(&mut self.toggled, tab.toggled),
+            narrowed: std::mem::replace(&mut self.narrowed, tab.narrowed),
+            refilter: std::mem::replace(&mut self.refilter, tab.refilter),
+            filters: std::mem::replace(&mut self.filters, tab.filters),
+            filtered: std::mem::replace(&mut self.filtered, tab.filtered),
+            marked: std::mem::replace(&mut self.marked, tab.marked),
+        };
+        self.parked[self.active] = Some(current);
+        self.active = index;
+        self.cursor = None;
+        self.visual = None;
+        // The terminal may have been resized while the tab was parked
+        self.state.terminal_width = width;
+        self.state.terminal_height = height;
+        self.fit_to_terminal();
+        self.fit_layout();
+    }
+
+    fn go_to_record(&mut self, record: usize) {
+        if let Some(&line) = self.content.row_starts.get(record) {
+            self.state.go_to_line(line);
+        }
+    }
+
+    // Opens at a startup position, saying where it went
+    pub fn start_at(&mut self, start: &StartAt) {
+        match start {
+            StartAt::Row(row) => self.go_to_row(*row),
+            StartAt::End => self.state.go_to_last(),
+            StartAt::Match(pattern) => {
+                let filter = match RowFilter::parse(pattern, Some(&self.content.field_names)) {
+                    Ok(filter) => filter,
+                    Err(e) => {
+                        self.status = Some(e);
+                        return;
+                    }
+                };
+                let found = self
+                    .content
+                    .records
+                    .as_ref()
+                    .and_then(|records| records.iter().position(|record| filter.matches(record)));
+                match found {
+                    Some(record) => {
+                        self.go_to_record(record);
+                        self.status = Some(format!("First match of '{}' is row {}", pattern, record + 1));
+                    }
+                    None => self.status = Some(format!("No row matches '{}'", pattern)),
+                }
+            }
+        }
+    }
+
+    // Goes to a data row, numbered from 1 like `--show-row-numbers`, and
+    // says where it went. Rows past either end go to the first or last one.
+    fn go_to_row(&mut self, row: usize) {
+        let rows = self.content.row_starts.len();
+        if rows == 0 {
+            self.status = Some("No rows to go to".to_string());
+            return;
+        }
+        let target = row.clamp(1, rows);
+        self.go_to_record(target - 1);
+        self.status = Some(if target == row {
+            format!("Row {} of {}", row, rows)
+        } else {
+            format!("Row {} is out of range, showing row {} of {}", row, target, rows)
+        });
+    }
+
+    fn handle_command_prompt_key(&mut self, key_event: KeyEvent) {
+        let Some(input) = &mut self.command_prompt else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Esc => self.command_prompt = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Enter => {
+                let input = std::mem::take(input);
+                self.command_prompt = None;
+                self.run_command(input.trim());
+            }
+            _ => {}
+        }
+    }
+
+    // A line typed at `:`: a row number to go to, or `export FILE` to save
+    // the rows picked with `V`, or else all rows shown, to a file in the
+    // format its extension names
+    fn run_command(&mut self, command: &str) {
+        if command.is_empty() {
+            return;
+        }
+        if command.bytes().all(|byte| byte.is_ascii_digit()) {
+            // Too many digits for a row number is past the last row
+            self.go_to_row(command.parse().unwrap_or(usize::MAX));
+            return;
+        }
+        let (name, argument) = command.split_once(char::is_whitespace).unwrap_or((command, ""));
+        match (name, argument.trim()) {
+            ("export", "") => self.status = Some("Save the rows to which file? :export FILE".to_string()),
+            ("export", path) => {
+                if self.reading.is_some() && self.selection().is_none() {
+                    self.status = Some("The file is still being read, export it once it is".to_string());
+                    return;
+                }
+                let rows = match self.selection() {
+                    Some(rows) => rows,
+                    None if self.content.row_starts.is_empty() => {
+                        self.status = Some("No rows to save".to_string());
+                        return;
+                    }
+                    None => 0..=self.content.row_starts.len() - 1,
+                };
+                self.export(rows, ExportFormat::from_path(path), Some(path));
+            }
+            _ => self.status = Some(format!("Unknown command ':{}', type a row number or export FILE", name)),
+        }
+    }
+
+    fn handle_search_prompt_key(&mut self, key_event: KeyEvent) {
+        let Some(input) = &mut self.search_prompt else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Esc => self.search_prompt = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Enter => {
+                let input = std::mem::take(input);
+                self.search_prompt = None;
+                // Entered empty, it searches for the same text again
+                if !input.is_empty() {
+                    self.search = Some(input);
+                }
+                self.search_next(true);
+            }
+            _ => {}
+        }
+    }
+
+    // The first column on screen whose value in the record holds the text;
+    // in the vertical layout, without columns, the first such field
+    fn matching_column(&self, record: usize, text: &str, ignore_case: bool) -> Option<usize> {
+        let columns = match self.content.fields.len() {
+            0 => self.content.records.as_ref()?.get(record)?.len(),
+            columns => columns,
+        };
+        (0..columns).find(|&column| {
+            let val = self.content.cell(record, column).unwrap_or_default();
+            if ignore_case {
+                val.to_lowercase().contains(text)
+            } else {
+                val.contains(text)
+            }
+        })
+    }
+
+    // Goes to the next row after the current one, or with `forward` unset
+    // the one before it, that has the text searched for in one of its
+    // cells, wrapping around at either end. Text in lower case matches
+    // either case, as in less with -i.
+    fn search_next(&mut self, forward: bool) {
+        let Some(search) = self.search.clone() else {
+            self.status = Some("Nothing to search for yet, type / first".to_string());
+            return;
+        };
+        let count = self.content.records.as_ref().map_or(0, |records| records.len());
+        if count == 0 {
+            self.status = Some("No rows to search".to_string());
+            return;
+        }
+        let ignore_case = !search.chars().any(char::is_uppercase);
+        let text = if ignore_case { search.to_lowercase() } else { search.clone() };
+        let matches: Vec<(usize, usize)> = (0..count)
+            .filter_map(|record| Some((record, self.matching_column(record, &text, ignore_case)?)))
+            .collect();
+        let current = self.cursor.map_or_else(|| self.first_record_on_screen(), |(record, _)| record);
+        let found = if forward {
+            let after = matches.iter().position(|&(record, _)| record > current);
+            after.or((!matches.is_empty()).then_some(0))
+        } else {
+            let before = matches.iter().rposition(|&(record, _)| record < current);
+            before.or(matches.len().checked_sub(1))
+        };
+        let Some(found) = found else {
+            self.status = Some(format!("No row has '{}'", search));
+            return;
+        };
+        let (record, column) = matches[found];
+        if self.cursor.is_some() {
+            self.cursor = Some((record, column));
+            self.move_cursor(0, 0);
+        } else {
+            self.go_to_record(record);
+            if !self.content.column_edges.is_empty() {
+                self.reveal_column(column);
+                self.highlight = Some((column, Instant::now()));
+            }
+        }
+        let wrapped = match (forward, record.cmp(&current)) {
+            (true, Ordering::Less | Ordering::Equal) => " (from the top)",
+            (false, Ordering::Greater | Ordering::Equal) => " (from the bottom)",
+            _ => "",
+        };
+        self.status = Some(format!(
+            "'{}' in row {}, match {} of {}{}",
+            search,
+            record + 1,
+            found + 1,
+            matches.len(),
+            wrapped
+        ));
+    }
+
+    // Columns whose name starts with `typed`, then those that contain it,
+    // ignoring case
+    fn matching_columns(&self, typed: &str) -> Vec<usize> {
+        let typed = typed.to_lowercase();
+        let names: Vec<String> = self
+            .content
+            .column_names
+            .iter()
+            .map(|name| name.to_lowercase())
+            .collect();
+        let starting = (0..names.len()).filter(|&idx| names[idx].starts_with(&typed));
+        let containing = (0..names.len())
+            .filter(|&idx| !names[idx].starts_with(&typed) && names[idx].contains(&typed));
+        starting.chain(containing).collect()
+    }
+
+    // Scrolls sideways until the column is on screen, making it the first
+    // one after the frozen ones if it wasn't
+    fn reveal_column(&mut self, column: usize) {
+        let frozen = self.frozen_columns();
+        let edges = &self.content.column_edges;
+        let width = self.state.terminal_width as usize;
+        let first = frozen + self.state.columns_scrolled;
+        let room = width.saturating_sub(edges[frozen]);
+        let shown = column < frozen || (column >= first && edges[column + 1] - edges[first] <= room);
+        if !shown {
+            let max = self.max_columns_scrolled();
+            self.state.columns_scrolled = (column - frozen).min(max);
+        }
+    }
+
+    // Brings a column into view and highlights it
+    fn go_to_column(&mut self, column: usize) {
+        self.reveal_column(column);
+        self.highlight = Some((column, Instant::now()));
+        let mut status = format!("Column: {}", self.content.column_names[column]);
+        if let Some(note) = self.column_note(column) {
+            status.push_str(&format!(" · {}", note));
+        }
+        self.status = Some(status);
+    }
+
+    // The screen columns the highlighted column spans inside its borders
+    fn highlight_span(&self) -> Option<(usize, usize)> {
+        self.column_span(self.highlight?.0)
+    }
+
+    // The screen columns a column spans inside its borders, if it is shown
+    fn column_span(&self, column: usize) -> Option<(usize, usize)> {
+        let edges = &self.content.column_edges;
+        let frozen = self.frozen_columns();
+        let width = self.state.terminal_width as usize;
+        let x = if column < frozen {
+            edges[column]
+        } else {
+            let first = frozen + self.state.columns_scrolled;
+            (edges[frozen] + edges[column]).checked_sub(edges[first])?
+        };
+        // A followed file may have lost the column since
+        let end = (x + edges.get(column + 1)? - edges[column]).min(width);
+        (x + 1 < end).then_some((x + 1, end))
+    }
+
+    // Turns the cell cursor on at the first record on screen, or off
+    fn toggle_cursor(&mut self) {
+        if self.cursor.take().is_some() {
+            return;
+        }
+        let Some(records) = self.content.records.as_ref().filter(|_| !self.content.column_edges.is_empty()) else {
+            self.status = Some("The cell cursor works in the table layout".to_string());
+            return;
+        };
+        if records.is_empty() {
+            self.status = Some("No cells to move through".to_string());
+            return;
+        }
+        let column = self.frozen_columns() + self.state.columns_scrolled;
+        self.cursor = Some((self.first_record_on_screen(), column));
+    }
+
+    // The first record that starts on screen, or the one running through it
+    fn first_record_on_screen(&self) -> usize {
+        let top = self.state.get_viewport_start();
+        let record = self.content.record_at(top);
+        let starts = &self.content.row_starts;
+        match (starts.get(record), starts.get(record + 1)) {
+            (Some(&start), Some(&next)) if start < top && next < self.state.get_viewport_end() => record + 1,
+            _ => record,
+        }
+    }
+
+    // Moves the cursor by records and columns, scrolling to keep it shown
+    fn move_cursor(&mut self, records: isize, columns: isize) {
+        let Some((record, column)) = self.cursor else {
+            return;
+        };
+        let last_record = self.content.row_starts.len().saturating_sub(1);
+        let last_column = self.content.column_edges.len().saturating_sub(2);
+        let record = record.saturating_add_signed(records).min(last_record);
+        let column = column.saturating_add_signed(columns).min(last_column);
+        self.cursor = Some((record, column));
+        self.reveal_column(column);
+        let (start, end) = (self.content.row_starts[record], self.content.record_end(record));
+        let top = self.state.get_viewport_start();
+        if start < top {
+            self.state.go_to_line(start);
+        } else if end > top + self.state.rows_per_page {
+            self.state.go_to_line(end.saturating_sub(self.state.rows_per_page).min(start));
+        }
+    }
+
+    // After scrolling some other way, the cursor moves to a record on screen
+    fn keep_cursor_on_screen(&mut self) {
+        let Some((record, column)) = self.cursor else {
+            return;
+        };
+        let (top, bottom) = (self.state.get_viewport_start(), self.state.get_viewport_end());
+        let start = self.content.row_starts.get(record).copied();
+        if start.is_none_or(|start| start < top || start >= bottom) {
+            self.cursor = Some((self.first_record_on_screen(), column));
+        }
+    }
+
+    // The picked cell for the status bar: `name (type): value`
+    fn cursor_summary(&self) -> Option<String> {
+        let (record, column) = self.cursor?;
+        let val = self.content.cell(record, column)?;
+        let name = self.content.column_names.get(column)?;
+        let ty = detect_data_type_cached(val);
+        let shown = if val.contains('\n') || hides_text(val) {
+            raw_text(val)
+        } else {
+            val.to_string()
+        };
+        let mut summary = format!("{} ({}): {}", name, ty.spoken(), shown);
+        if let Some(note) = self.column_note(column) {
+            summary.push_str(&format!(" · {}", note));
+        }
+        Some(summary)
+    }
+
+    // The note kept for one of the columns on screen
+    fn column_note(&self, column: usize) -> Option<&str> {
+        let field = *self.content.fields.get(column)?;
+        self.field_note(field)
+    }
+
+    fn field_note(&self, field: usize) -> Option<&str> {
+        let name = self.content.field_names.get(field)?;
+        self.sidecars[self.active].as_ref()?.note(name)
+    }
+
+    // The records there are to copy from, or None with the reason why not
+    // on the status line
+    fn records_to_copy(&mut self) -> Option<Arc<Vec<Vec<Field>>>> {
+        let Some(records) = self.content.records.clone() else {
+            self.status = Some("Nothing to copy in this layout".to_string());
+            return None;
+        };
+        if records.is_empty() {
+            self.status = Some("No rows to copy".to_string());
+            return None;
+        }
+        Some(records)
+    }
+
+    // The record and column of the cell under the cursor, or else of the
+    // first cell on screen that isn't frozen
+    fn current_cell(&self) -> (usize, usize) {
+        self.cursor
+            .unwrap_or_else(|| (self.first_record_on_screen(), self.frozen_columns() + self.state.columns_scrolled))
+    }
+
+    fn copy_text(&mut self, text: &str, done: String) {
+        self.status = Some(match clipboard::copy(text) {
+            Ok(()) => done,
+            Err(e) => format!("Copy failed: {}", e),
+        });
+    }
+
+    // Copies the cell under the cursor, or else the first record on screen
+    // as a line of CSV
+    fn yank(&mut self) {
+        if self.cursor.is_some() {
+            return self.yank_cell();
+        }
+        let Some(records) = self.records_to_copy() else {
+            return;
+        };
+        let record = self.first_record_on_screen();
+        let fields = &self.content.fields;
+        // Only the columns shown, when some are hidden
+        let shown: Vec<Field> = if fields.is_empty() {
+            records[record].clone()
+        } else {
+            fields.iter().filter_map(|&field| records[record].get(field).cloned()).collect()
+        };
+        self.copy_text(&csv_line(&shown), format!("Copied row {} as CSV", record + 1));
+    }
+
+    // Copies the exact value of the current cell
+    fn yank_cell(&mut self) {
+        if self.records_to_copy().is_none() || !self.in_table("Cells are copied") {
+            return;
+        }
+        let (record, column) = self.current_cell();
+        let val = self.content.cell(record, column).unwrap_or_default().to_string();
+        let name = self.content.column_names.get(column).map_or("", String::as_str);
+        let done = format!("Copied {} of row {}", name, record + 1);
+        self.copy_text(&val, done);
+    }
+
+    // Copies every value of the current column in the rows shown, one to
+    // a line
+    fn yank_column(&mut self) {
+        let Some(records) = self.records_to_copy().filter(|_| self.in_table("Columns are copied")) else {
+            return;
+        };
+        let (_, column) = self.current_cell();
+        let Some(&field) = self.content.fields.get(column) else {
+            return;
+        };
+        let values: Vec<&str> = records.iter().map(|record| record.get(field).map_or("", |val| val.as_str())).collect();
+        let done = format!("Copied {} values of {}", values.len(), self.content.column_names[column]);
+        self.copy_text(&values.join("\n"), done);
+    }
+
+    // Whether the table layout is shown, saying on the status line what
+    // waits for it when it isn't
+    fn in_table(&mut self, what: &str) -> bool {
+        if self.content.column_edges.is_empty() {
+            self.status = Some(format!("{} from the table layout", what));
+            return false;
+        }
+        true
+    }
+
+    // The records from where `V` started to where the selection is now
+    fn selection(&self) -> Option<RangeInclusive<usize>> {
+        let anchor = self.visual?;
+        let end = self.cursor.map_or_else(|| self.first_record_on_screen(), |(record, _)| record);
+        Some(anchor.min(end)..=anchor.max(end))
+    }
+
+    fn start_visual(&mut self) {
+        if self.content.records.as_ref().is_none_or(|records| records.is_empty()) {
+            self.status = Some("No rows to select".to_string());
+            return;
+        }
+        let record = self.cursor.map_or_else(|| self.first_record_on_screen(), |(record, _)| record);
+        self.visual = Some(record);
+    }
+
+    fn visual_summary(&self) -> Option<String> {
+        let rows = self.selection()?;
+        let count = rows.end() - rows.start() + 1;
+        Some(format!(
+            "-- VISUAL -- rows {}-{} ({}), y: copy or save, V/Esc: end",
+            rows.start() + 1,
+            rows.end() + 1,
+            row_count(count)
+        ))
+    }
+
+    // The selected rows in a format, only their shown columns, with or
+    // without the header
+    fn export_text(&self, rows: RangeInclusive<usize>, format: ExportFormat) -> Result<String, String> {
+        let records = self.content.records.clone().unwrap_or_default();
+        let records = records.get(rows).unwrap_or_default();
+        // The vertical layout doesn't list the columns, the table does
+        let names = match self.alternate.as_ref() {
+            Some(table) if self.content.field_names.is_empty() => &table.field_names,
+            _ => &self.content.field_names,
+        };
+        let hidden = &self.table_style.hidden;
+        let fields: Vec<usize> = (0..names.len().max(records.iter().map(Vec::len).max().unwrap_or(0)))
+            .filter(|field| !hidden.contains(field))
+            .collect();
+        let records: Vec<Vec<Field>> = records
+            .iter()
+            .map(|record| fields.iter().filter_map(|&field| record.get(field).cloned()).collect())
+            .collect();
+        let names: Option<Vec<String>> = (self.export_headers && !names.is_empty())
+            .then(|| fields.iter().map(|&field| names.get(field).cloned().unwrap_or_default()).collect());
+        let names = names.as_deref();
+        match format {
+            ExportFormat::Csv | ExportFormat::Tsv => {
+                let dialect = output::CsvDialect {
+                    delimiter: if format == ExportFormat::Tsv { b'\t' } else { b',' },
+                    quote_all: false,
+                    line_ending: output::LineEnding::Lf,
+                };
+                let mut bytes = Vec::new();
+                output::write_csv(&mut bytes, names, &records, false, &dialect).map_err(|e| e.to_string())?;
+                Ok(String::from_utf8_lossy(&bytes).into_owned())
+            }
+            ExportFormat::Markdown => Ok(output::records_to_markdown(names, &records, false) + "\n"),
+            ExportFormat::Json => match names {
+                Some(names) => output::records_to_json(Some(names), &records),
+                None => output::records_to_json_arrays(&records),
+            }
+            .map(|json| json + "\n")
+            .map_err(|e| e.to_string()),
+        }
+    }
+
+    fn open_export_prompt(&mut self) {
+        if let Some(rows) = self.selection() {
+            self.export_prompt = Some(ExportPrompt {
+                rows,
+                to_file: false,
+                path: None,
+            });
+        }
+    }
+
+    // Copies or saves the selection, and ends it if that worked
+    fn export(&mut self, rows: RangeInclusive<usize>, format: ExportFormat, path: Option<&str>) {
+        let count = row_count(rows.end() - rows.start() + 1);
+        let text = match self.export_text(rows, format) {
+            Ok(text) => text,
+            Err(e) => {
+                self.status = Some(format!("Couldn't write the rows as {}: {}", format.name(), e));
+                return;
+            }
+        };
+        let headers = if self.export_headers { "with" } else { "without" };
+        let done = match path {
+            Some(path) => std::fs::write(path, text)
+                .map(|()| format!("Saved {} as {} {} the header to {}", count, format.name(), headers, path))
+                .map_err(|e| format!("Couldn't save to {}: {}", path, e)),
+            None => clipboard::copy(&text)
+                .map(|()| format!("Copied {} as {} {} the header", count, format.name(), headers))
+                .map_err(|e| format!("Copy failed: {}", e)),
+        };
+        if done.is_ok() {
+            self.visual = None;
+        }
+        self.status = Some(done.unwrap_or_else(|e| e));
+    }
+
+    fn handle_export_prompt_key(&mut self, key_event: KeyEvent) {
+        let Some(prompt) = &mut self.export_prompt else {
+            return;
+        };
+        if let Some((format, path)) = &mut prompt.path {
+            match key_event.code {
+                KeyCode::Esc => prompt.path = None,
+                KeyCode::Backspace => {
+                    path.pop();
+                }
+                KeyCode::Char(c) => path.push(c),
+                KeyCode::Enter if !path.is_empty() => {
+                    let (format, path) = (*format, path.clone());
+                    let rows = prompt.rows.clone();
+                    self.export_prompt = None;
+                    self.export(rows, format, Some(&path));
+                }
+                _ => {}
+            }
+            return;
+        }
+        let format = match key_event.code {
+            KeyCode::Char('c') => ExportFormat::Csv,
+            KeyCode::Char('t') => ExportFormat::Tsv,
+            KeyCode::Char('m') => ExportFormat::Markdown,
+            KeyCode::Char('j') => ExportFormat::Json,
+            KeyCode::Char('h') => {
+                self.export_headers = !self.export_headers;
+                return;
+            }
+            KeyCode::Char('w') => {
+                prompt.to_file = !prompt.to_file;
+                return;
+            }
+            KeyCode::Esc | KeyCode::Char('q') => {
+                self.export_prompt = None;
+                return;
+            }
+            _ => return,
+        };
+        if prompt.to_file {
+            prompt.path = Some((format, format!("selection.{}", format.extension())));
+            return;
+        }
+        let rows = prompt.rows.clone();
+        self.export_prompt = None;
+        self.export(rows, format, None);
+    }
+
+    fn export_prompt_line(&self, prompt: &ExportPrompt) -> String {
+        let count = row_count(prompt.rows.end() - prompt.rows.start() + 1);
+        if let Some((format, path)) = &prompt.path {
+            return format!("Save {} as {} to: {}", count, format.name(), path);
+        }
+        format!(
+            "{} {} as c:CSV t:TSV m:Markdown j:JSON · h:headers {} · w:{}",
+            if prompt.to_file { "Save" } else { "Copy" },
+            count,
+            if self.export_headers { "on" } else { "off" },
+            if prompt.to_file { "copy instead" } else { "save to a file" }
+        )
+    }
+
+    // Opens the whole value of the cell under the cursor
+    fn open_popup(&mut self) {
+        let Some((record, column)) = self.cursor else {
+            self.status = Some("Pick a cell with the cursor (Enter) first".to_string());
+            return;
+        };
+        let Some(val) = self.content.cell(record, column) else {
+            return;
+        };
+        let name = self.content.column_names.get(column).map_or("", String::as_str);
+        self.popup = Some(CellPopup {
+            title: format!("{}, row {}", name, record + 1),
+            text: pretty_json(val),
+            scroll: 0,
+        });
+    }
+
+    // The popup's box: its left column, width and the lines of text it has
+    // room for under the title
+    fn popup_area(&self) -> (usize, usize, usize) {
+        let width = (self.state.terminal_width as usize).saturating_sub(4).max(1);
+        let height = (self.state.terminal_height as usize).saturating_sub(3).max(2);
+        (2, width, height - 1)
+    }
+
+    fn handle_popup_key(&mut self, key_event: KeyEvent) {
+        let (_, width, room) = self.popup_area();
+        let Some(popup) = &mut self.popup else {
+            return;
+        };
+        let max = wrap(&popup.text, width.saturating_sub(2)).len().saturating_sub(room);
+        popup.scroll = match key_event.code {
+            KeyCode::Char('q' | 'o' | '=') | KeyCode::Esc => {
+                self.popup = None;
+                return;
+            }
+            KeyCode::Char('j') | KeyCode::Down => popup.scroll + 1,
+            KeyCode::Char('k') | KeyCode::Up => popup.scroll.saturating_sub(1),
+            KeyCode::Char(' ') | KeyCode::PageDown => popup.scroll + room,
+            KeyCode::Char('b') | KeyCode::PageUp => popup.scroll.saturating_sub(room),
+            KeyCode::Char('g') | KeyCode::Home => 0,
+            KeyCode::Char('G') | KeyCode::End => max,
+            _ => popup.scroll,
+        }
+        .min(max);
+    }
+
+    fn draw_popup(&self, popup: &CellPopup, screen: &mut Screen) {
+        let (x, width, room) = self.popup_area();
+        let lines = wrap(&popup.text, width.saturating_sub(2));
+        let fit = |text: &str| {
+            let text = cut_line(text, 0, width);
+            let pad = width.saturating_sub(display_width(&text));
+            format!("{}{}", text, " ".repeat(pad))
+        };
+
+        let shown = (popup.scroll + room).min(lines.len());
+        let title = format!("{} (lines {}-{} of {})", popup.title, popup.scroll + 1, shown, lines.len());
+        screen.put(x, 1, &reversed(&fit(&title)));
+        for offset in 0..room {
+            let line = lines.get(popup.scroll + offset).map_or("", String::as_str);
+            screen.put(x, 2 + offset, &fit(&format!(" {}", line)));
+        }
+    }
+
+    // Drops the highlight once its time is up; true if it did
+    fn expire_highlight(&mut self) -> bool {
+        let expired = self
+            .highlight
+            .is_some_and(|(_, since)| since.elapsed() >= HIGHLIGHT_TIME);
+        if expired {
+            self.highlight = None;
+        }
+        expired
+    }
+
+    fn open_column_prompt(&mut self) {
+        if self.content.column_edges.is_empty() {
+            self.status = Some("Columns can only be jumped to in the table layout".to_string());
+            return;
+        }
+        self.column_prompt = Some(ColumnPrompt {
+            typed: String::new(),
+            completion: None,
+        });
+    }
+
+    fn handle_column_prompt_key(&mut self, key_event: KeyEvent) {
+        let Some(prompt) = &self.column_prompt else {
+            return;
+        };
+        let matches = self.matching_columns(&prompt.typed);
+        // Editing carries on from the completed name
+        let shown = prompt
+            .completion
+            .map_or(prompt.typed.clone(), |idx| self.content.column_names[matches[idx]].clone());
+        let Some(prompt) = &mut self.column_prompt else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Esc => self.column_prompt = None,
+            KeyCode::Tab if !matches.is_empty() => {
+                prompt.completion = Some(prompt.completion.map_or(0, |idx| (idx + 1) % matches.len()));
+            }
+            KeyCode::BackTab if !matches.is_empty() => {
+                let count = matches.len();
+                prompt.completion = Some(prompt.completion.map_or(count - 1, |idx| (idx + count - 1) % count));
+            }
+            KeyCode::Backspace => {
+                prompt.typed = shown;
+                prompt.typed.pop();
+                prompt.completion = None;
+            }
+            KeyCode::Char(c) => {
+                prompt.typed = shown;
+                prompt.typed.push(c);
+                prompt.completion = None;
+            }
+            KeyCode::Enter => {
+                self.column_prompt = None;
+                let names = &self.content.column_names;
+                let exact = names.iter().position(|name| name.eq_ignore_ascii_case(&shown));
+                match exact.or_else(|| self.matching_columns(&shown).first().copied()) {
+                    Some(column) => self.go_to_column(column),
+                    None => self.status = Some(format!("No column matches '{}'", shown)),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // The prompt line: what was typed or completed, then the other names
+    // Tab would go through
+    fn note_prompt_line(&self) -> Option<String> {
+        let list = self.column_list.as_ref()?;
+        let note = list.note.as_ref()?;
+        let name = self.content.field_names.get(list.selected)?;
+        Some(format!("Note on {}: {}", name, note))
+    }
+
+    fn column_prompt_line(&self, prompt: &ColumnPrompt) -> String {
+        let matches = self.matching_columns(&prompt.typed);
+        let names = &self.content.column_names;
+        let shown = prompt.completion.map_or(prompt.typed.as_str(), |idx| &names[matches[idx]]);
+        let mut line = format!("Column: {}", shown);
+        if !prompt.typed.is_empty() && !matches.is_empty() {
+            let listed: Vec<String> = matches
+                .iter()
+                .enumerate()
+                .map(|(idx, &column)| match prompt.completion {
+                    Some(selected) if selected == idx => format!("[{}]", names[column]),
+                    _ => names[column].clone(),
+                })
+                .collect();
+            line.push_str(&format!("   {}", listed.join(" ")));
+        }
+        line
+    }
+
+    // Moves down a screen of records: the first one that didn't fit on the
+    // screen goes to the top. Records taller than the screen, and the lines
+    // past the last record, scroll by lines instead.
+    fn page_down(&mut self) {
+        let top = self.state.current_row;
+        let bottom = top + self.state.rows_per_page;
+        let content = &self.content;
+        let records = content.row_starts.len();
+        let cut = (content.record_at(top)..records)
+            .find(|&idx| content.record_end(idx) > bottom)
+            .map(|idx| content.row_starts[idx]);
+        match cut {
+            Some(start) if start > top => self.state.go_to_line(start),
+            _ => self.state.scroll_down(self.state.rows_per_page),
+        }
+    }
+
+    // Moves up so the records above the top one fill the screen, ending
+    // right where the current screen begins
+    fn page_up(&mut self) {
+        let top = self.state.current_row;
+        let above = top.saturating_sub(self.state.rows_per_page);
+        let starts = &self.content.row_starts;
+        match starts.first() {
+            // The lines above the first record (the table header) come along
+            Some(&first) if above < first && top > first => self.state.go_to_line(0),
+            Some(_) => {
+                let start = starts[starts.partition_point(|&start| start < above).min(starts.len() - 1)];
+                if start < top {
+                    self.state.go_to_line(start);
+                } else {
+                    self.state.scroll_up(self.state.rows_per_page);
+                }
+            }
+            None => self.state.scroll_up(self.state.rows_per_page),
+        }
+    }
+
+    // Swaps layouts, keeping the record at the top of the screen in view
+    fn toggle_layout(&mut self) {
+        let Some(alternate) = self.alternate.take() else {
+            return;
+        };
+        let record = self.content.record_at(self.state.current_row);
+        self.alternate = Some(std::mem::replace(&mut self.content, alternate));
+        self.toggled = !self.toggled;
+        self.cursor = None;
+        self.state.set_total_rows(self.content.line_count());
+        self.state.columns_scrolled = 0;
+        self.state.go_to_line(0);
+        self.go_to_record(record);
+    }
+
+    // Shows the vertical layout while not even one column of the table fits
+    // on screen, and the table again once one does. `v` still switches to
+    // the table, which then stays until the screen is resized.
+    fn fit_layout(&mut self) {
+        self.fit_columns();
+        let width = self.state.terminal_width as usize;
+        if self.narrowed {
+            if self.alternate.as_ref().is_some_and(|table| !too_narrow(table, width)) {
+                self.toggle_layout();
+                self.narrowed = false;
+            }
+        } else if self.alternate.is_some() && too_narrow(&self.content, width) {
+            self.toggle_layout();
+            self.narrowed = true;
+            self.status = Some("Too narrow for the table, showing records (v: table)".to_string());
+        }
+    }
+
+    // Narrows the table's widest columns to the screen's width, as the
+    // config has them by default, again whenever the screen is resized.
+    // A table shown in place of the vertical layout later is fitted too.
+    fn fit_columns(&mut self) {
+        let width = Some(self.state.terminal_width as usize).filter(|_| self.config.fit_columns);
+        if self.table_style.width == width {
+            return;
+        }
+        let style = TableStyle {
+            width,
+            ..self.table_style.clone()
+        };
+        if self.restyle_table(style.clone()) {
+            return;
+        }
+        if let (Some(restyle), Some(table)) = (&self.restyle, &mut self.alternate) {
+            if !table.column_edges.is_empty() {
+                *table = restyle(style.clone());
+                self.table_style = style;
+            }
+        }
+    }
+
+    // Rebuilds the table in another style, keeping the same record at the
+    // top. Returns false outside the table layout.
+    fn restyle_table(&mut self, style: TableStyle) -> bool {
+        let Some(restyle) = self.restyle.clone().filter(|_| !self.content.column_edges.is_empty()) else {
+            return false;
+        };
+        self.table_style = style.clone();
+        let line = self.state.current_row;
+        let record = (self.content.row_starts.first().is_some_and(|&first| line >= first))
+            .then(|| self.content.record_at(line));
+        let field = self.cursor.and_then(|(_, column)| self.content.fields.get(column).copied());
+        self.content = restyle(style);
+        // The cursor stays on its column, or the one that took its place
+        if let Some((record, column)) = self.cursor {
+            let fields = &self.content.fields;
+            let column = fields
+                .iter()
+                .position(|&f| Some(f) == field)
+                .unwrap_or(column.min(fields.len().saturating_sub(1)));
+            self.cursor = Some((record, column));
+        }
+        self.state.set_total_rows(self.content.line_count());
+        let max = self.max_columns_scrolled();
+        self.state.columns_scrolled = self.state.columns_scrolled.min(max);
+        self.state.go_to_line(0);
+        if let Some(record) = record {
+            self.go_to_record(record);
+        }
+        true
+    }
+
+    // Switches the table's header between full names, shortened names and
+    // none at all
+    fn cycle_header(&mut self) {
+        let style = TableStyle {
+            header: self.table_style.header.next(),
+            ..self.table_style.clone()
+        };
+        let header = style.header;
+        if !self.restyle_table(style) {
+            self.status = Some("The header can only be changed in the table layout".to_string());
+            return;
+        }
+        self.status = Some(
+            match header {
+                HeaderStyle::Full => "Header: full names",
+                HeaderStyle::Compact => "Header: shortened names",
+                HeaderStyle::Hidden => "Header: hidden",
+            }
+            .to_string(),
+        );
+    }
+
+    // Switches the table between cell values and their raw text
+    fn toggle_raw(&mut self) {
+        let raw = !self.table_style.raw;
+        let style = TableStyle {
+            raw,
+            ..self.table_style.clone()
+        };
+        if !self.restyle_table(style) {
+            self.status = Some("Raw cells are shown in the table layout; the vertical layout lists them under each value".to_string());
+            return;
+        }
+        self.status = Some(if raw { "Showing raw cell text" } else { "Showing cell values" }.to_string());
+    }
+
+    // Leaves a field out of the table, or puts it back
+    fn set_hidden(&mut self, field: usize, hidden: bool) {
+        let mut style = self.table_style.clone();
+        style.hidden.retain(|&f| f != field);
+        if hidden {
+            style.hidden.push(field);
+            style.hidden.sort_unstable();
+        }
+        if self.restyle_table(style) {
+            self.remember_hidden();
+        }
+    }
+
+    // Updates the sidecar with the columns now hidden
+    fn remember_hidden(&mut self) {
+        let names: Vec<String> = self
+            .table_style
+            .hidden
+            .iter()
+            .filter_map(|&field| self.content.field_names.get(field).cloned())
+            .collect();
+        if let Some(sidecar) = &mut self.sidecars[self.active] {
+            if let Err(e) = sidecar.set_hidden(names) {
+                self.status = Some(format!("Couldn't save the hidden columns: {}", e));
+            }
+        }
+    }
+
+    fn can_hide(&mut self) -> bool {
+        if self.restyle.is_none() || self.content.column_edges.is_empty() {
+            self.status = Some("Columns can only be hidden in the table layout".to_string());
+            return false;
+        }
+        true
+    }
+
+    // Hides the column under the cursor, or else the first one scrolled to
+    fn hide_column(&mut self) {
+        if !self.can_hide() {
+            return;
+        }
+        if self.content.fields.len() < 2 {
+            self.status = Some("Can't hide the last column".to_string());
+            return;
+        }
+        let column = self
+            .cursor
+            .map_or(self.frozen_columns() + self.state.columns_scrolled, |(_, column)| column);
+        let Some(&field) = self.content.fields.get(column) else {
+            return;
+        };
+        let name = self.content.column_names[column].clone();
+        self.status = Some(format!("Hid {} (C to show it again)", name));
+        self.set_hidden(field, true);
+    }
+
+    fn open_column_list(&mut self) {
+        if !self.can_hide() {
+            return;
+        }
+        let column = self.cursor.map_or(self.frozen_columns() + self.state.columns_scrolled, |(_, column)| column);
+        self.column_list = Some(ColumnList {
+            selected: self.content.fields.get(column).copied().unwrap_or(0),
+            note: None,
+        });
+    }
+
+    fn handle_column_list_key(&mut self, key_event: KeyEvent) {
+        let current_note = self
+            .column_list
+            .as_ref()
+            .and_then(|list| self.field_note(list.selected))
+            .map(str::to_string);
+        let Some(list) = &mut self.column_list else {
+            return;
+        };
+        if let Some(note) = &mut list.note {
+            match key_event.code {
+                KeyCode::Esc => list.note = None,
+                KeyCode::Backspace => {
+                    note.pop();
+                }
+                KeyCode::Char(c) => note.push(c),
+                KeyCode::Enter => {
+                    let note = list.note.take().unwrap_or_default();
+                    let field = list.selected;
+                    self.save_note(field, &note);
+                }
+                _ => {}
+            }
+            return;
+        }
+        let last = self.content.field_names.len().saturating_sub(1);
+        match key_event.code {
+            KeyCode::Char('q' | 'C') | KeyCode::Esc | KeyCode::Enter => self.column_list = None,
+            KeyCode::Char('n') => {
+                if self.sidecars[self.active].is_none() {
+                    self.status = Some("Notes can't be kept for standard input, remote or encrypted files".to_string());
+                } else {
+                    list.note = Some(current_note.unwrap_or_default());
+                }
+            }
+            KeyCode::Char('j') | KeyCode::Down => list.selected = (list.selected + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => list.selected = list.selected.saturating_sub(1),
+            KeyCode::Char('g') | KeyCode::Home => list.selected = 0,
+            KeyCode::Char('G') | KeyCode::End => list.selected = last,
+            KeyCode::Char(' ') => {
+                let field = list.selected;
+                let hidden = self.table_style.hidden.contains(&field);
+                if !hidden && self.content.fields.len() < 2 {
+                    self.status = Some("Can't hide the last column".to_string());
+                } else {
+                    self.set_hidden(field, !hidden);
+                }
+            }
+            KeyCode::Char('m') => {
+                let field = list.selected;
+                match self.marked.iter().position(|&marked| marked == field) {
+                    Some(idx) => {
+                        self.marked.remove(idx);
+                    }
+                    None => {
+                        self.marked.push(field);
+                        self.marked.sort_unstable();
+                    }
+                }
+            }
+            KeyCode::Char('a') => {
+                let style = TableStyle {
+                    hidden: Vec::new(),
+                    ..self.table_style.clone()
+                };
+                if self.restyle_table(style) {
+                    self.remember_hidden();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    // Keeps a note in the file's sidecar, or removes it when blank
+    fn save_note(&mut self, field: usize, note: &str) {
+        let Some(name) = self.content.field_names.get(field).cloned() else {
+            return;
+        };
+        let Some(sidecar) = &mut self.sidecars[self.active] else {
+            return;
+        };
+        self.status = Some(match sidecar.set_note(&name, note) {
+            Ok(()) if note.trim().is_empty() => format!("Removed the note on {}", name),
+            Ok(()) => format!("Saved the note on {} to {}", name, sidecar.path().display()),
+            Err(e) => format!("Couldn't save the note: {}", e),
+        });
+    }
+
+    fn draw_column_list(&self, list: &ColumnList, screen: &mut Screen) {
+        let names = &self.content.field_names;
+        let width = (self.state.terminal_width as usize).saturating_sub(4).clamp(1, 60);
+        let height = (self.state.terminal_height as usize).saturating_sub(3).max(2);
+        let visible = height - 1;
+        // Scroll the list so the selection stays visible
+        let first = list.selected.saturating_sub(visible - 1);
+        let fit = |text: &str| {
+            let text = cut_line(text, 0, width);
+            let pad = width.saturating_sub(display_width(&text));
+            format!("{}{}", text, " ".repeat(pad))
+        };
+
+        let shown = names.len() - self.table_style.hidden.iter().filter(|&&f| f < names.len()).count();
+        let title = format!(
+            "Columns, {} of {} shown (Space: show/hide, m: mark, a: all, n: note)",
+            shown,
+            names.len()
+        );
+        screen.put(2, 1, &reversed(&fit(&title)));
+        for (offset, name) in names.iter().enumerate().skip(first).take(visible) {
+            let mark = if self.table_style.hidden.contains(&offset) { ' ' } else { 'x' };
+            let marked = if self.marked.contains(&offset) { '=' } else { ' ' };
+            let text = match self.field_note(offset) {
+                Some(note) => fit(&format!(" [{}] {} {} · {}", mark, marked, name, note)),
+                None => fit(&format!(" [{}] {} {}", mark, marked, name)),
+            };
+            let text = if offset == list.selected { reversed(&text) } else { text };
+            screen.put(2, 2 + offset - first, &text);
+        }
+    }
+
+    // Opens the sum, mean, min, max and count of the marked columns, or
+    // else of the one under the cursor or first scrolled to, over the rows
+    // shown (just the matching ones while `&` filters them)
+    fn open_aggregates(&mut self) {
+        let Some(records) = self.content.records.clone() else {
+            self.status = Some("Nothing to aggregate in this layout".to_string());
+            return;
+        };
+        let fields = if self.marked.is_empty() {
+            let column = self.cursor.map_or(self.frozen_columns() + self.state.columns_scrolled, |(_, column)| column);
+            match self.content.fields.get(column) {
+                Some(&field) => vec![field],
+                None => {
+                    self.status = Some("Mark columns with m in the column list (C) first".to_string());
+                    return;
+                }
+            }
         } else {
-            1
+            self.marked.clone()
+        };
+        let names = &self.content.field_names;
+        let width = fields
+            .iter()
+            .map(|&field| names.get(field).map_or(0, |name| display_width(name)))
+            .max()
+            .unwrap_or(0);
+        let text = fields
+            .iter()
+            .map(|&field| {
+                let name = names.get(field).map_or("", String::as_str);
+                let pad = " ".repeat(width - display_width(name));
+                let values = records
+                    .iter()
+                    .map(|record| record.get(field).map_or("", |val| val.as_str()));
+                format!("{}{}  {}", name, pad, aggregates(values))
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let rows = match &self.filtered {
+            Some(filter) => format!("{} of {} rows", records.len(), filter.total),
+            None => format!("{} rows", records.len()),
         };
+        self.popup = Some(CellPopup {
+            title: format!("Aggregates over {}", rows),
+            text,
+            scroll: 0,
+        });
+    }
 
-        Ok(Self {
-            current_page: 0,
-            total_pages,
-            rows_per_page,
-            total_rows,
-            current_row: 0,
-            terminal_height,
-            terminal_width,
-        })
+    // Columns pinned on the left right now, leaving at least one to scroll
+    fn frozen_columns(&self) -> usize {
+        let edges = &self.content.column_edges;
+        if !self.frozen {
+            return 0;
+        }
+        let mut frozen = self.freeze_cols.min(edges.len().saturating_sub(2));
+        // Pinned columns that fill the screen would leave nothing to scroll
+        while frozen > 0 && edges[frozen] + 1 >= self.state.terminal_width as usize {
+            frozen -= 1;
+        }
+        frozen
     }
 
-    pub fn go_to_page(&mut self, page: usize) {
-        self.current_page = page.min(self.total_pages.saturating_sub(1));
-        self.current_row = self.current_page * self.rows_per_page;
+    // How far right the view can scroll: up to the last column, or until
+    // the widest line on screen ends
+    fn max_columns_scrolled(&mut self) -> usize {
+        let columns = self.content.column_edges.len().saturating_sub(1);
+        if columns > 0 {
+            return columns - 1 - self.frozen_columns();
+        }
+        let (start, end) = (self.state.get_viewport_start(), self.state.get_viewport_end());
+        let widest = self
+            .content
+            .visible_lines(start, end)
+            .iter()
+            .flatten()
+            .map(|line| display_width(line))
+            .max()
+            .unwrap_or(0);
+        widest
+            .saturating_sub(self.state.terminal_width as usize)
+            .div_ceil(SCROLL_STEP)
+    }
+
+    fn scroll_right(&mut self) {
+        let max = self.max_columns_scrolled();
+        self.state.columns_scrolled = (self.state.columns_scrolled + 1).min(max);
     }
 
-    pub fn next_page(&mut self) {
-        if self.current_page < self.total_pages.saturating_sub(1) {
-            self.go_to_page(self.current_page + 1);
+    fn toggle_freeze(&mut self) {
+        if self.content.column_edges.is_empty() {
+            self.status = Some("Columns can only be frozen in the table layout".to_string());
+            return;
         }
+        self.frozen = !self.frozen;
+        let max = self.max_columns_scrolled();
+        self.state.columns_scrolled = self.state.columns_scrolled.min(max);
+        self.status = Some(match self.frozen_columns() {
+            0 => "Columns unfrozen".to_string(),
+            1 => "Frozen: 1 column".to_string(),
+            n => format!("Frozen: {} columns", n),
+        });
     }
 
-    pub fn prev_page(&mut self) {
-        if self.current_page > 0 {
-            self.go_to_page(self.current_page.saturating_sub(1));
+    // The part of a line that fits on screen: the frozen columns, then the
+    // rest from wherever it is scrolled to
+    fn shifted(&self, line: &str) -> String {
+        let width = self.state.terminal_width as usize;
+        let edges = &self.content.column_edges;
+        if edges.is_empty() {
+            return cut_line(line, self.state.columns_scrolled * SCROLL_STEP, width);
         }
+        let frozen = self.frozen_columns();
+        let pinned = edges[frozen].min(width);
+        let from = edges[(frozen + self.state.columns_scrolled).min(edges.len().saturating_sub(2))];
+        let mut shown = cut_line(line, 0, pinned);
+        shown.push_str(&cut_line(line, from, width - pinned));
+        shown
+    }
+
+    // Swaps in a fresh snapshot. The old one stays on screen if reading
+    // fails, and the same record stays at the top when the new one loads.
+    fn reload(&mut self) {
+        self.status = Some(match self.swap_snapshot() {
+            Ok((before, after, note)) => {
+                let change = match after.cmp(&before) {
+                    std::cmp::Ordering::Greater => format!("+{}", after - before),
+                    std::cmp::Ordering::Less => format!("-{}", before - after),
+                    std::cmp::Ordering::Equal => "unchanged".to_string(),
+                };
+                format!("Reloaded: {} -> {} rows ({}){}", before, after, change, note_suffix(note))
+            }
+            Err(e) => e,
+        });
     }
 
-    pub fn next_row(&mut self) {
-        if self.current_row < self.total_rows.saturating_sub(1) {
-            self.current_row += 1;
-            self.current_page = self.current_row / self.rows_per_page;
+    // Swaps in a fresh snapshot and returns the record counts before and
+    // after, with the note read along with it. The old one stays on screen
+    // if reading fails, and the same record stays at the top when the new
+    // one loads.
+    fn swap_snapshot(&mut self) -> Result<(usize, usize, Option<String>), String> {
+        let Some(reload) = &mut self.reload else {
+            return Err("Reload is not available for standard input".to_string());
+        };
+        let layouts = reload().map_err(|e| format!("Reload failed: {}", e))?;
+        // What was still being read is older than this
+        self.reading = None;
+        self.swap_in(layouts).map_err(|e| format!("Reload failed: {}", e))
+    }
+
+    // Shows the rows of the whole file once it has been read in the
+    // background, where the first ones were. Returns whether it was.
+    fn poll_reading(&mut self) -> bool {
+        let Some(read) = self.reading.as_mut().and_then(|reading| reading()) else {
+            return false;
+        };
+        self.reading = None;
+        if let Err(e) = read.and_then(|layouts| self.swap_in(layouts)) {
+            self.status = Some(format!("Only the first rows could be read: {}", e));
         }
+        true
     }
 
-    pub fn prev_row(&mut self) {
-        if self.current_row > 0 {
-            self.current_row = self.current_row.saturating_sub(1);
-            self.current_page = self.current_row / self.rows_per_page;
+    // Swaps in new layouts of the file with the filters, the record at the
+    // top and the cursor's cell kept
+    fn swap_in(&mut self, mut layouts: PagerLayouts) -> Result<(usize, usize, Option<String>), String> {
+        let note = layouts.note.take();
+        // The filters typed at `&` apply to the new rows too, if their
+        // columns are still there
+        let refilter = layouts.refilter.take();
+        if let (Some(refilter), Some(filtered)) = (&refilter, &mut self.filtered) {
+            let narrowed = refilter(&self.filters)?;
+            filtered.rows = narrowed.rows;
+            filtered.total = narrowed.total;
+            layouts = narrowed.layouts;
+        }
+        self.refilter = refilter;
+
+        let line = self.state.current_row;
+        let position = self
+            .content
+            .row_starts
+            .first()
+            .filter(|&&first| line >= first)
+            .map(|_| {
+                let record = self.content.record_at(line);
+                (record, line - self.content.row_starts[record])
+            });
+        let before = self.content.row_starts.len();
+        self.install_layouts(layouts);
+        match position {
+            Some((record, offset)) => {
+                let record = record.min(self.content.row_starts.len().saturating_sub(1));
+                if let Some(&start) = self.content.row_starts.get(record) {
+                    self.state.go_to_line(start + offset);
+                }
+            }
+            None => self.state.go_to_line(line),
         }
+        // The cursor stays on the same cell if the file still has it
+        let records = self.content.records.as_ref().map_or(0, |records| records.len());
+        let columns = self.content.column_edges.len().saturating_sub(1);
+        self.cursor = self
+            .cursor
+            .filter(|_| records > 0 && columns > 0)
+            .map(|(record, column)| (record.min(records - 1), column.min(columns - 1)));
+        Ok((before, self.content.row_starts.len(), note))
     }
 
-    pub fn go_to_first(&mut self) {
-        self.go_to_page(0);
+    // Shows new layouts the way the old ones were shown: the same one of
+    // the two, and the table in the same style
+    fn install_layouts(&mut self, layouts: PagerLayouts) {
+        let (mut content, mut alternate) = if self.toggled {
+            (layouts.alternate, layouts.content)
+        } else {
+            (layouts.content, layouts.alternate)
+        };
+        // The snapshot comes with the header as configured
+        self.restyle = layouts.restyle;
+        if let Some(restyle) = self
+            .restyle
+            .as_ref()
+            .filter(|_| self.table_style != TableStyle::new(self.config.header))
+        {
+            let table = if content.column_edges.is_empty() {
+                &mut alternate
+            } else {
+                &mut content
+            };
+            *table = restyle(self.table_style.clone());
+        }
+        self.content = content;
+        self.alternate = Some(alternate);
+        self.jumps = layouts.jumps;
+        self.state.set_total_rows(self.content.line_count());
+        // The records picked may not be there any more
+        self.visual = None;
     }
 
-    pub fn go_to_last(&mut self) {
-        self.go_to_page(self.total_pages.saturating_sub(1));
+    // Which of all records a shown one is
+    fn unfiltered_record(&self, record: usize) -> usize {
+        self.filtered.as_ref().map_or(record, |filtered| filtered.rows.get(record).copied().unwrap_or(record))
     }
 
-    pub fn get_page_start(&self) -> usize {
-        self.current_page * self.rows_per_page
+    // Adds a filter typed at `&` to the stack, or with an empty one clears
+    // the stack to show all rows again
+    fn push_filter(&mut self, expr: String) {
+        if self.refilter.is_none() {
+            self.status = Some("Rows can't be filtered here".to_string());
+            return;
+        }
+        let before = self.filters.clone();
+        if expr.is_empty() {
+            self.filters.filters.clear();
+        } else {
+            self.filters.filters.push(StackedFilter {
+                expr,
+                enabled: true,
+                negated: false,
+            });
+        }
+        if !self.apply_filters() {
+            self.filters = before;
+        }
     }
 
-    pub fn get_page_end(&self) -> usize {
-        (self.get_page_start() + self.rows_per_page).min(self.total_rows)
+    // Shows only the records the filters switched on let through, or all
+    // of them when none is. The record at the top stays there, or the next
+    // one shown after it does if it was filtered out. False if a filter
+    // can't be applied, which the status bar says why.
+    fn apply_filters(&mut self) -> bool {
+        let Some(refilter) = self.refilter.clone() else {
+            return false;
+        };
+        let active = self.filters.is_active();
+        if !active && self.filtered.is_none() {
+            return true;
+        }
+        let line = self.state.current_row;
+        let top = (self.content.row_starts.first().is_some_and(|&first| line >= first))
+            .then(|| self.unfiltered_record(self.content.record_at(line)));
+        let cursor = self.cursor.map(|(record, column)| (self.unfiltered_record(record), column));
+
+        let narrowed = match refilter(&self.filters) {
+            Ok(narrowed) => narrowed,
+            Err(e) => {
+                self.status = Some(e);
+                return false;
+            }
+        };
+        let shown = narrowed.rows.len();
+        let total = narrowed.total;
+        // Where a record of all of them lands among the ones shown now
+        let place = |record: usize| {
+            let rows = &narrowed.rows;
+            rows.partition_point(|&row| row < record).min(rows.len().saturating_sub(1))
+        };
+        let top = top.map(place);
+        let cursor = cursor.filter(|_| shown > 0).map(|(record, column)| (place(record), column));
+        self.filtered = active.then_some(RowsFilter {
+            rows: narrowed.rows,
+            total,
+        });
+        self.install_layouts(narrowed.layouts);
+        self.state.go_to_line(0);
+        if let Some(record) = top {
+            self.go_to_record(record);
+        }
+        self.cursor = cursor;
+        self.keep_cursor_on_screen();
+        self.status = Some(if active {
+            format!("{} of {} rows match {}", shown, total, self.filters.summary())
+        } else {
+            format!("No filters on, {} rows", total)
+        });
+        true
     }
 
-    pub fn scroll_down(&mut self, lines: usize) {
-        self.current_row = (self.current_row + lines).min(self.total_rows.saturating_sub(1));
-        // Update page to keep current row visible
-        self.current_page = self.current_row / self.rows_per_page;
+    fn open_filter_list(&mut self) {
+        if self.refilter.is_none() {
+            self.status = Some("Rows can't be filtered here".to_string());
+            return;
+        }
+        self.filter_list = Some(FilterList {
+            selected: self.filters.filters.len().saturating_sub(1),
+        });
     }
 
-    pub fn scroll_up(&mut self, lines: usize) {
-        self.current_row = self.current_row.saturating_sub(lines);
-        // Update page to keep current row visible
-        self.current_page = self.current_row / self.rows_per_page;
+    // Each change to the stack shows its rows right away
+    fn handle_filter_list_key(&mut self, key_event: KeyEvent) {
+        let Some(list) = &mut self.filter_list else {
+            return;
+        };
+        let before = self.filters.clone();
+        let filters = &mut self.filters.filters;
+        let last = filters.len().saturating_sub(1);
+        match key_event.code {
+            KeyCode::Char('q' | 'F') | KeyCode::Esc | KeyCode::Enter => self.filter_list = None,
+            KeyCode::Char('j') | KeyCode::Down => list.selected = (list.selected + 1).min(last),
+            KeyCode::Char('k') | KeyCode::Up => list.selected = list.selected.saturating_sub(1),
+            KeyCode::Char('g') | KeyCode::Home => list.selected = 0,
+            KeyCode::Char('G') | KeyCode::End => list.selected = last,
+            KeyCode::Char('o') => self.filters.any = !self.filters.any,
+            KeyCode::Char(' ') if !filters.is_empty() => {
+                filters[list.selected].enabled = !filters[list.selected].enabled;
+            }
+            KeyCode::Char('!') if !filters.is_empty() => {
+                filters[list.selected].negated = !filters[list.selected].negated;
+            }
+            KeyCode::Char('d') | KeyCode::Delete if !filters.is_empty() => {
+                filters.remove(list.selected);
+                list.selected = list.selected.min(filters.len().saturating_sub(1));
+            }
+            _ => {}
+        }
+        if self.filters != before && !self.apply_filters() {
+            self.filters = before;
+        }
     }
 
-    pub fn get_viewport_start(&self) -> usize {
-        self.current_row
+    fn draw_filter_list(&self, list: &FilterList, screen: &mut Screen) {
+        let filters = &self.filters.filters;
+        let width = (self.state.terminal_width as usize).saturating_sub(4).clamp(1, 60);
+        let height = (self.state.terminal_height as usize).saturating_sub(3).max(2);
+        let visible = height - 1;
+        // Scroll the list so the selection stays visible
+        let first = list.selected.saturating_sub(visible - 1);
+        let fit = |text: &str| {
+            let text = cut_line(text, 0, width);
+            let pad = width.saturating_sub(display_width(&text));
+            format!("{}{}", text, " ".repeat(pad))
+        };
+
+        let title = format!(
+            "Filters, rows matching {} (Space: on/off, !: negate, d: remove, o: all/any)",
+            if self.filters.any { "any" } else { "all" }
+        );
+        screen.put(2, 1, &reversed(&fit(&title)));
+        if filters.is_empty() {
+            screen.put(2, 2, &fit(" no filters yet; & adds one"));
+        }
+        let joint = if self.filters.any { "or " } else { "and" };
+        for (offset, filter) in filters.iter().enumerate().skip(first).take(visible) {
+            let mark = if filter.enabled { 'x' } else { ' ' };
+            let joint = if offset == 0 { "   " } else { joint };
+            let not = if filter.negated { "not " } else { "" };
+            let text = fit(&format!(" [{}] {} {}{}", mark, joint, not, filter.expr));
+            let text = if offset == list.selected { reversed(&text) } else { text };
+            screen.put(2, 2 + offset - first, &text);
+        }
     }
 
-    pub fn get_viewport_end(&self) -> usize {
-        (self.current_row + self.rows_per_page).min(self.total_rows)
+    fn handle_filter_prompt_key(&mut self, key_event: KeyEvent) {
+        let Some(input) = &mut self.filter_prompt else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Esc => self.filter_prompt = None,
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            KeyCode::Enter => {
+                let expr = std::mem::take(input);
+                self.filter_prompt = None;
+                self.push_filter(expr);
+            }
+            _ => {}
+        }
     }
-}
 
-pub struct Pager {
-    state: PagerState,
-    content: Vec<String>,
-    header: Option<String>,
-    config: PagerConfig,
-}
+    // Watches every open file for changes from now on
+    pub fn set_follow(&mut self) {
+        self.watched = self.tab_names.iter().map(|name| file_stamp(name)).collect();
+        self.follow = true;
+    }
 
-impl Pager {
-    pub fn new(content: Vec<String>, header: Option<String>, total_rows: usize, config: PagerConfig) -> io::Result<Self> {
-        let state = PagerState::new(total_rows)?;
-        Ok(Self {
-            state,
-            content,
-            header,
-            config,
-        })
+    // Reloads the active file when it changed on disk, staying at the
+    // bottom if that is where the view was. Returns whether it reloaded.
+    fn poll_follow(&mut self) -> bool {
+        if !self.follow {
+            return false;
+        }
+        let stamp = file_stamp(&self.tab_names[self.active]);
+        if stamp.is_none() || stamp == self.watched[self.active] {
+            return false;
+        }
+        self.watched[self.active] = stamp;
+
+        let at_end = self.state.get_viewport_end() >= self.state.total_rows;
+        match self.swap_snapshot() {
+            Ok((before, after, note)) => {
+                if at_end {
+                    self.state.go_to_line(self.state.total_rows.saturating_sub(self.state.rows_per_page));
+                }
+                if after > before || note.is_some() {
+                    let added = after.saturating_sub(before);
+                    self.status = Some(format!("Following: {} rows (+{}){}", after, added, note_suffix(note)));
+                }
+            }
+            Err(e) => self.status = Some(e),
+        }
+        true
+    }
+
+    fn matching_jumps(&self, query: &str) -> Vec<&JumpTarget> {
+        let query = query.to_lowercase();
+        self.jumps
+            .iter()
+            .filter(|jump| jump.label.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    fn handle_jump_menu_key(&mut self, key_event: KeyEvent) {
+        let Some(menu) = &mut self.jump_menu else {
+            return;
+        };
+        match key_event.code {
+            KeyCode::Esc => self.jump_menu = None,
+            KeyCode::Up => menu.selected = menu.selected.saturating_sub(1),
+            KeyCode::Down => menu.selected += 1,
+            KeyCode::Backspace => {
+                menu.query.pop();
+                menu.selected = 0;
+            }
+            KeyCode::Char(c) => {
+                menu.query.push(c);
+                menu.selected = 0;
+            }
+            KeyCode::Enter => {
+                let query = menu.query.clone();
+                let selected = menu.selected;
+                let record = self
+                    .matching_jumps(&query)
+                    .get(selected)
+                    .map(|jump| jump.record);
+                if let Some(record) = record {
+                    self.go_to_record(record);
+                }
+                self.jump_menu = None;
+            }
+            _ => {}
+        }
+        // Keep the selection on an existing entry
+        if let Some(menu) = &self.jump_menu {
+            let count = self.matching_jumps(&menu.query).len();
+            if let Some(menu) = &mut self.jump_menu {
+                menu.selected = menu.selected.min(count.saturating_sub(1));
+            }
+        }
+    }
+
+    // Presses the --keys without a terminal to draw on, then prints the
+    // screen they ended on (the one the quitting key was pressed on, if
+    // they quit), exactly as it would be shown, colors and all
+    fn run_headless(&mut self) -> io::Result<()> {
+        self.fit_layout();
+        for key_event in self.script.take().unwrap_or_default() {
+            if self.handle_key_event(key_event)? {
+                break;
+            }
+        }
+        let screen = loop {
+            let screen = self.draw();
+            if !self.content.is_loading() {
+                break screen;
+            }
+            thread::sleep(Duration::from_millis(5));
+            self.content.receive_chunks();
+        };
+        let mut out = stdout();
+        for line in &screen.lines {
+            if plain() {
+                // Reverse video and all, for logs that show escapes as is
+                let text: String = pieces(line).filter(|piece| !piece.starts_with('\x1b')).collect();
+                writeln!(out, "{}", text)?;
+            } else {
+                writeln!(out, "{}\x1b[0m", line)?;
+            }
+        }
+        out.flush()
     }
 
     pub fn run(&mut self) -> io::Result<()> {
+        if self.headless || (self.script.is_some() && !stdout().is_terminal()) {
+            return self.run_headless();
+        }
         terminal::enable_raw_mode()?;
         execute!(stdout(), terminal::EnterAlternateScreen)?;
 
         let (tx, rx) = mpsc::channel();
         let tx_clone = tx.clone();
+        // Keys from --keys come first, as if they had been typed
+        for key_event in self.script.take().unwrap_or_default() {
+            let _ = tx.send(Event::Key(key_event));
+        }
 
         // Spawn input handler thread
         thread::spawn(move || {
@@ -149,11 +2837,14 @@ impl Pager {
         });
 
         // Initial render
+        self.fit_layout();
         self.render()?;
 
         // Main event loop
         loop {
-            match rx.recv_timeout(Duration::from_millis(100)) {
+            // Check back soon while chunks of the layout are still rendering
+            let timeout = if self.content.is_loading() { 15 } else { 100 };
+            match rx.recv_timeout(Duration::from_millis(timeout)) {
                 Ok(Event::Key(key_event)) => {
                     if self.handle_key_event(key_event)? {
                         break;
@@ -161,20 +2852,23 @@ impl Pager {
                     self.render()?;
                 }
                 Ok(Event::Resize(width, height)) => {
-                    self.state.terminal_width = width;
-                    self.state.terminal_height = height;
-                    self.state.rows_per_page = height as usize;
-                    self.state.total_pages = if self.state.rows_per_page > 0 {
-                        (self.state.total_rows + self.state.rows_per_page - 1) / self.state.rows_per_page
-                    } else {
-                        1
-                    };
+                    (self.state.terminal_width, self.state.terminal_height) = usable_size(width, height);
+                    self.fit_to_terminal();
+                    self.fit_layout();
                     self.state.go_to_page(self.state.current_page.min(self.state.total_pages.saturating_sub(1)));
                     self.render()?;
                 }
                 Ok(_) => {}
                 Err(_) => {
-                    // Timeout, continue
+                    // Timeout, look for rendered chunks, the rest of a file being
+                    // read and changes to a followed file
+                    if self.content.receive_chunks()
+                        | self.poll_reading()
+                        | self.poll_follow()
+                        | self.expire_highlight()
+                    {
+                        self.render()?;
+                    }
                 }
             }
         }
@@ -186,15 +2880,107 @@ impl Pager {
     }
 
     fn handle_key_event(&mut self, key_event: KeyEvent) -> io::Result<bool> {
+        self.status = None;
+        self.highlight = None;
+        // Any key closes the help
+        if self.help {
+            self.help = false;
+            return Ok(false);
+        }
+        if self.popup.is_some() {
+            self.handle_popup_key(key_event);
+            return Ok(false);
+        }
+        if self.column_list.is_some() {
+            self.handle_column_list_key(key_event);
+            return Ok(false);
+        }
+        if self.filter_list.is_some() {
+            self.handle_filter_list_key(key_event);
+            return Ok(false);
+        }
+        if self.jump_menu.is_some() {
+            self.handle_jump_menu_key(key_event);
+            return Ok(false);
+        }
+        if self.command_prompt.is_some() {
+            self.handle_command_prompt_key(key_event);
+            return Ok(false);
+        }
+        if self.search_prompt.is_some() {
+            self.handle_search_prompt_key(key_event);
+            return Ok(false);
+        }
+        if self.column_prompt.is_some() {
+            self.handle_column_prompt_key(key_event);
+            return Ok(false);
+        }
+        if self.filter_prompt.is_some() {
+            self.handle_filter_prompt_key(key_event);
+            return Ok(false);
+        }
+        if self.export_prompt.is_some() {
+            self.handle_export_prompt_key(key_event);
+            return Ok(false);
+        }
+        if self.visual.is_some() {
+            match key_event.code {
+                KeyCode::Char('V') | KeyCode::Esc => {
+                    self.visual = None;
+                    return Ok(false);
+                }
+                KeyCode::Char('y') => {
+                    self.open_export_prompt();
+                    return Ok(false);
+                }
+                _ => {}
+            }
+        }
+        if std::mem::take(&mut self.yanking) {
+            match key_event.code {
+                KeyCode::Char('y') => self.yank(),
+                KeyCode::Char('c') => self.yank_cell(),
+                KeyCode::Char('C') => self.yank_column(),
+                // Anything else, Esc included, copies nothing
+                _ => {}
+            }
+            return Ok(false);
+        }
+
+        if self.cursor.is_some() {
+            match key_event.code {
+                KeyCode::Enter | KeyCode::Esc => self.cursor = None,
+                KeyCode::Char('j') | KeyCode::Down => self.move_cursor(1, 0),
+                KeyCode::Char('k') | KeyCode::Up => self.move_cursor(-1, 0),
+                KeyCode::Char('l') | KeyCode::Right => self.move_cursor(0, 1),
+                KeyCode::Char('h') | KeyCode::Left => self.move_cursor(0, -1),
+                _ => {
+                    let quit = self.handle_view_key(key_event);
+                    self.keep_cursor_on_screen();
+                    return quit;
+                }
+            }
+            return Ok(false);
+        }
+        self.handle_view_key(key_event)
+    }
+
+    // Keys that act on the view, with or without the cell cursor
+    fn handle_view_key(&mut self, key_event: KeyEvent) -> io::Result<bool> {
+        let count = self.count.take();
         match key_event.code {
+            // A count for the next key
+            KeyCode::Char(c) if c.is_ascii_digit() => {
+                let digit = c as usize - '0' as usize;
+                let count = count.unwrap_or(0).saturating_mul(10).saturating_add(digit);
+                self.count = Some(count);
+                self.status = Some(count.to_string());
+            }
+            KeyCode::Esc if count.is_some() => {}
             KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
             // Page-based scrolling (like less)
-            KeyCode::Char(' ') | KeyCode::PageDown => {
-                self.state.scroll_down(self.state.rows_per_page);
-            }
-            KeyCode::Char('b') | KeyCode::PageUp => {
-                self.state.scroll_up(self.state.rows_per_page);
-            }
+            KeyCode::Char(' ') | KeyCode::PageDown => self.page_down(),
+            KeyCode::Char('b') | KeyCode::PageUp => self.page_up(),
             // Configurable line scrolling
             KeyCode::Char('j') | KeyCode::Down => self.state.scroll_down(self.config.scroll_single_line),
             KeyCode::Char('J') => self.state.scroll_down(self.config.scroll_multi_line),
@@ -208,59 +2994,327 @@ impl Pager {
                 self.state.scroll_up(self.state.rows_per_page / 2);
             }
             // Navigation
-            KeyCode::Char('g') => {
-                if key_event.modifiers.contains(KeyModifiers::CONTROL) {
-                    self.state.go_to_first();
-                } else {
-                    self.state.go_to_first();
-                }
-            }
+            KeyCode::Char('g' | 'G') if count.is_some() => self.go_to_row(count.unwrap_or_default()),
+            KeyCode::Char('g') => self.state.go_to_first(),
             KeyCode::Char('G') => self.state.go_to_last(),
+            KeyCode::Char(':') => self.command_prompt = Some(String::new()),
             // Home and End keys
             KeyCode::Home => self.state.go_to_first(),
             KeyCode::End => self.state.go_to_last(),
-            KeyCode::Char('/') => {
-                // TODO: Implement search functionality
+            // Table / vertical record layout
+            KeyCode::Char('v') => {
+                self.narrowed = false;
+                self.toggle_layout();
             }
-            KeyCode::Char('n') => {
-                // TODO: Implement next search result
+            // Sideways, a column at a time
+            KeyCode::Char('l') | KeyCode::Right => self.scroll_right(),
+            KeyCode::Char('h') | KeyCode::Left => {
+                self.state.columns_scrolled = self.state.columns_scrolled.saturating_sub(1);
+            }
+            KeyCode::Char('f') => self.toggle_freeze(),
+            KeyCode::Char('c') => self.open_column_prompt(),
+            KeyCode::Char('H') => self.cycle_header(),
+            KeyCode::Char('R') => self.toggle_raw(),
+            KeyCode::Char('-') => self.hide_column(),
+            KeyCode::Char('&') => self.filter_prompt = Some(String::new()),
+            KeyCode::Char('F') => self.open_filter_list(),
+            KeyCode::Char('C') => self.open_column_list(),
+            KeyCode::Char('=') => self.open_aggregates(),
+            KeyCode::Enter => self.toggle_cursor(),
+            KeyCode::Char('y') => {
+                self.yanking = true;
+                self.status = Some("Copy y: row or cell, c: cell, C: column".to_string());
+            }
+            KeyCode::Char('V') => self.start_visual(),
+            KeyCode::Char('o') => self.open_popup(),
+            KeyCode::Char('r') => self.reload(),
+            // Open files
+            KeyCode::Tab => self.switch_tab((self.active + 1) % self.tab_names.len()),
+            KeyCode::BackTab => {
+                let count = self.tab_names.len();
+                self.switch_tab((self.active + count - 1) % count);
             }
-            KeyCode::Char('N') => {
-                // TODO: Implement previous search result
+            // Group jump list
+            KeyCode::Char('t') if !self.jumps.is_empty() => {
+                self.jump_menu = Some(JumpMenu {
+                    query: String::new(),
+                    selected: 0,
+                });
             }
+            KeyCode::Char('?') => self.help = true,
+            // Searching the cells
+            KeyCode::Char('/') => self.search_prompt = Some(String::new()),
+            KeyCode::Char('n') => self.search_next(true),
+            KeyCode::Char('N') => self.search_next(false),
             _ => {}
         }
         Ok(false)
     }
 
     fn render(&mut self) -> io::Result<()> {
-        execute!(stdout(), terminal::Clear(ClearType::All))?;
-        execute!(stdout(), cursor::MoveTo(0, 0))?;
+        self.draw().show()
+    }
 
+    // Everything on the screen right now
+    fn draw(&mut self) -> Screen {
+        let mut screen = Screen::new(self.state.terminal_height as usize);
         let mut y = 0;
 
+        if self.chrome_lines() > 0 {
+            screen.set(0, self.tab_bar());
+            y += 1;
+        }
+
         // Render header if present
         if let Some(header) = &self.header {
-            execute!(stdout(), style::SetForegroundColor(Color::Cyan))?;
-            println!("{}", header);
+            screen.set(y as usize, format!("{}{}{}", style::SetForegroundColor(Color::Cyan), header, style::ResetColor));
             y += 1;
         }
 
         // Render content for current viewport
         let start = self.state.get_viewport_start();
         let end = self.state.get_viewport_end();
+        let highlight = self.highlight_span();
+        // The lines and screen columns of the cell under the cursor
+        let cursor = self.cursor.and_then(|(record, column)| {
+            let lines = self.content.row_starts[record]..self.content.record_end(record);
+            Some((lines, self.column_span(column)?))
+        });
+        // The lines of the rows picked with `V`
+        let selected = self
+            .selection()
+            .map(|rows| self.content.row_starts[*rows.start()]..self.content.record_end(*rows.end()));
 
-        for (_i, line) in self.content.iter().enumerate().skip(start).take(end - start) {
-            if y >= self.state.terminal_height {
+        for (offset, line) in self.content.visible_lines(start, end).into_iter().enumerate() {
+            if y + 1 >= self.state.terminal_height {
                 break;
             }
-            execute!(stdout(), cursor::MoveTo(0, y))?;
-            println!("{}", line);
+            let line = match line {
+                Some(line) => {
+                    let mut line = self.shifted(&line);
+                    if let Some((from, to)) = highlight {
+                        line = reverse_span(&line, from, to);
+                    }
+                    if let Some((lines, (from, to))) = &cursor {
+                        if lines.contains(&(start + offset)) {
+                            line = reverse_span(&line, *from, *to);
+                        }
+                    }
+                    if selected.as_ref().is_some_and(|lines| lines.contains(&(start + offset))) {
+                        line = reverse_span(&line, 0, self.state.terminal_width as usize);
+                    }
+                    line
+                }
+                None => format!("{}  loading…{}", style::SetAttribute(Attribute::Dim), style::SetAttribute(Attribute::Reset)),
+            };
+            screen.set(y as usize, line);
             y += 1;
         }
 
-        stdout().flush()?;
-        Ok(())
+        if let Some(menu) = &self.jump_menu {
+            self.draw_jump_menu(menu, &mut screen);
+        }
+        if let Some(popup) = &self.popup {
+            self.draw_popup(popup, &mut screen);
+        }
+        if let Some(list) = &self.column_list {
+            self.draw_column_list(list, &mut screen);
+        }
+        if let Some(list) = &self.filter_list {
+            self.draw_filter_list(list, &mut screen);
+        }
+        if self.help {
+            self.draw_help(&mut screen);
+        }
+
+        let bottom = (self.state.terminal_height as usize).saturating_sub(1);
+        screen.set(bottom, self.status_line());
+        screen
+    }
+
+    // Where the screen is: the record at the top, how many there are, and
+    // how far through them the bottom of the screen is
+    fn position(&self) -> String {
+        let records = self.content.row_starts.len();
+        if records == 0 {
+            return "No rows".to_string();
+        }
+        let top = self.content.record_at(self.state.get_viewport_start()) + 1;
+        let bottom = self.content.record_at(self.state.get_viewport_end().saturating_sub(1)) + 1;
+        format!("Row {}/{} {}%", top, records, bottom * 100 / records)
+    }
+
+    // The bottom line: an open prompt, or a message or the file name and how
+    // its rows were picked on the left, with the position on the right
+    fn status_line(&self) -> String {
+        let width = self.state.terminal_width as usize;
+        let prompt = match (&self.command_prompt, &self.column_prompt, &self.filter_prompt, &self.search_prompt) {
+            (Some(input), _, _, _) => Some(format!(":{}", input)),
+            (None, Some(prompt), _, _) => Some(self.column_prompt_line(prompt)),
+            (None, None, Some(input), _) => Some(format!("&{}", input)),
+            (None, None, None, Some(input)) => Some(format!("/{}", input)),
+            (None, None, None, None) => self
+                .export_prompt
+                .as_ref()
+                .map(|prompt| self.export_prompt_line(prompt))
+                .or_else(|| self.note_prompt_line()),
+        };
+        let (left, right) = match prompt {
+            Some(prompt) => (prompt, String::new()),
+            None => {
+                let left = self
+                    .status
+                    .clone()
+                    .or_else(|| self.visual_summary())
+                    .or_else(|| self.cursor_summary())
+                    .unwrap_or_else(|| {
+                    let name = match self.tab_names[self.active].as_str() {
+                        "-" => "standard input",
+                        name => name,
+                    };
+                    let mut left = name.to_string();
+                    if let Some(summary) = &self.view_summary {
+                        left = format!("{} · {}", left, summary);
+                    }
+                    if let Some(filtered) = &self.filtered {
+                        left.push_str(&format!(
+                            " · &{}: {} of {} rows",
+                            self.filters.summary(),
+                            filtered.rows.len(),
+                            filtered.total
+                        ));
+                    }
+                    if self.table_style.raw {
+                        left.push_str(" · raw");
+                    }
+                    if self.reading.is_some() {
+                        left.push_str(" · reading…");
+                    }
+                    left
+                });
+                (left, format!("{}  q:quit ?:help", self.position()))
+            }
+        };
+        let right = cut_line(&right, 0, width);
+        // A space before the message, and at least one between it and the
+        // position
+        let room = width.saturating_sub(display_width(&right) + 2);
+        let left = cut_line(&left, 0, room);
+        let gap = width.saturating_sub(display_width(&left) + display_width(&right) + 1).max(1);
+        let line = format!(" {}{}{}", left, " ".repeat(gap), right);
+        let line = cut_line(&line, 0, width);
+        match &self.status_color {
+            Some(sgr) => format!("\x1b[{}m{}\x1b[49m", sgr, line),
+            None => reversed(&line),
+        }
+    }
+
+    fn tab_bar(&self) -> String {
+        let mut bar = String::new();
+        let mut width = 0;
+        for (idx, name) in self.tab_names.iter().enumerate() {
+            let label = format!(" {}:{} ", idx + 1, name);
+            width += label.chars().count();
+            if width > self.state.terminal_width as usize {
+                break;
+            }
+            if idx == self.active {
+                bar.push_str(&reversed(&label));
+            } else {
+                bar.push_str(&label);
+            }
+        }
+        bar
+    }
+
+    fn draw_jump_menu(&self, menu: &JumpMenu, screen: &mut Screen) {
+        let matches = self.matching_jumps(&menu.query);
+        let width = (self.state.terminal_width as usize).saturating_sub(4).clamp(1, 60);
+        let height = (self.state.terminal_height as usize).saturating_sub(4).max(1);
+        let visible = height.saturating_sub(1);
+        // Scroll the list so the selection stays visible
+        let first = menu.selected.saturating_sub(visible.saturating_sub(1));
+
+        let fit = |text: &str| {
+            let text: String = text.chars().take(width).collect();
+            format!("{:<width$}", text, width = width)
+        };
+
+        screen.put(2, 1, &reversed(&fit(&format!("Jump to group: {}", menu.query))));
+
+        for (offset, jump) in matches.iter().skip(first).take(visible).enumerate() {
+            let text = fit(&format!(" {}", jump.label));
+            let text = if first + offset == menu.selected { reversed(&text) } else { text };
+            screen.put(2, 2 + offset, &text);
+        }
+        if matches.is_empty() {
+            screen.put(2, 2, &fit(" no matching groups"));
+        }
+    }
+
+    // Every key and what it does, with the scroll amounts as configured
+    fn help_lines(&self) -> Vec<(&'static str, String)> {
+        let lines = |count: usize| match count {
+            1 => "1 line".to_string(),
+            count => format!("{} lines", count),
+        };
+        vec![
+            ("j / ↓", format!("Scroll down {}", lines(self.config.scroll_single_line))),
+            ("k / ↑", format!("Scroll up {}", lines(self.config.scroll_single_line))),
+            ("J / K", format!("Scroll down / up {}", lines(self.config.scroll_multi_line))),
+            ("Space / PgDn", "Next screen of rows".to_string()),
+            ("b / PgUp", "Previous screen of rows".to_string()),
+            ("d / u", format!("Scroll down / up {}", lines(self.state.rows_per_page / 2))),
+            ("g / Home", "Go to the beginning".to_string()),
+            ("G / End", "Go to the end".to_string()),
+            (": / 123g", "Go to a row by number".to_string()),
+            (":export FILE", "Save the rows shown (or selected) to a file".to_string()),
+            ("/, n / N", "Search the cells, next / previous match".to_string()),
+            ("h / ←, l / →", "Scroll left / right by a column".to_string()),
+            ("f", "Freeze or unfreeze the first columns".to_string()),
+            ("c", "Jump to a column by name".to_string()),
+            ("v", "Switch the table and vertical layouts".to_string()),
+            ("H", "Show the full header, short names or none".to_string()),
+            ("R", "Show the raw text of cells".to_string()),
+            ("&", "Add a filter for the rows shown (empty: clear)".to_string()),
+            ("F", "List the filters to switch, negate or remove".to_string()),
+            ("-", "Hide the column (under the cursor)".to_string()),
+            ("C", "List the columns to show, hide, mark or note".to_string()),
+            ("=", "Sum, mean, min, max of the marked columns".to_string()),
+            ("yy", "Copy the row, or the cell under the cursor".to_string()),
+            ("yc / yC", "Copy the cell / every value of the column".to_string()),
+            ("V", "Select rows to copy or save (then y)".to_string()),
+            ("o", "Open the whole value of the cell".to_string()),
+            ("Enter", "Cell cursor (arrows move, Enter/Esc end)".to_string()),
+            ("t", "Jump to a group (with --sort)".to_string()),
+            ("r", "Reload the file".to_string()),
+            ("Tab / Shift-Tab", "Next / previous file".to_string()),
+            ("?", "Show this help".to_string()),
+            ("q / Esc", "Quit".to_string()),
+        ]
+    }
+
+    fn draw_help(&self, screen: &mut Screen) {
+        let lines = self.help_lines();
+        let key_width = lines.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+        let width = (self.state.terminal_width as usize).saturating_sub(4).clamp(1, 60);
+        // Leave the status bar below the box
+        let height = (self.state.terminal_height as usize).saturating_sub(3).max(1);
+        let fit = |text: &str| {
+            let text: String = text.chars().take(width).collect();
+            format!("{:<width$}", text, width = width)
+        };
+
+        screen.put(2, 1, &reversed(&fit("Keys (press any key to close)")));
+        let room = height.saturating_sub(1);
+        for (offset, (key, action)) in lines.iter().take(room).enumerate() {
+            let text = if offset + 1 == room && lines.len() > room {
+                " …".to_string()
+            } else {
+                format!(" {:<key_width$}  {}", key, action, key_width = key_width)
+            };
+            screen.put(2, 2 + offset, &fit(&text));
+        }
     }
 
 
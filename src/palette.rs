@@ -0,0 +1,152 @@
+use comfy_table::Color;
+use std::env;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+// How many colors the terminal can show. Colors are written as RGB and
+// brought down to the nearest one the terminal has.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ColorDepth {
+    #[value(name = "truecolor")]
+    TrueColor,
+    #[value(name = "256")]
+    Ansi256,
+    #[value(name = "16")]
+    Ansi16,
+}
+
+// Truecolor until a program says otherwise, so library users get exactly
+// the colors of their scheme
+static DEPTH: AtomicU8 = AtomicU8::new(0);
+
+pub fn set_depth(depth: ColorDepth) {
+    DEPTH.store(depth as u8, Ordering::Relaxed);
+}
+
+pub fn depth() -> ColorDepth {
+    match DEPTH.load(Ordering::Relaxed) {
+        1 => ColorDepth::Ansi256,
+        2 => ColorDepth::Ansi16,
+        _ => ColorDepth::TrueColor,
+    }
+}
+
+// What the environment says the terminal supports: `COLORTERM` for 24-bit
+// color, otherwise the `TERM` name as in its terminfo entry
+pub fn detect() -> ColorDepth {
+    let colorterm = env::var("COLORTERM").unwrap_or_default();
+    if matches!(colorterm.as_str(), "truecolor" | "24bit") || env::var_os("WT_SESSION").is_some() {
+        return ColorDepth::TrueColor;
+    }
+    let term = env::var("TERM").unwrap_or_default();
+    if term.ends_with("-direct") {
+        ColorDepth::TrueColor
+    } else if term.contains("256color") {
+        ColorDepth::Ansi256
+    } else {
+        ColorDepth::Ansi16
+    }
+}
+
+// An RGB color as the terminal can show it
+pub fn reduce(r: u8, g: u8, b: u8) -> Color {
+    match depth() {
+        ColorDepth::TrueColor => Color::Rgb { r, g, b },
+        ColorDepth::Ansi256 => Color::AnsiValue(nearest_256(r, g, b)),
+        ColorDepth::Ansi16 => nearest_16(r, g, b),
+    }
+}
+
+// The levels of each channel in the 6x6x6 color cube
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let channel = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+    channel(a.0, b.0) + channel(a.1, b.1) + channel(a.2, b.2)
+}
+
+// The closer of the nearest cube color and the nearest of the 24 grays
+fn nearest_256(r: u8, g: u8, b: u8) -> u8 {
+    let level = |x: u8| {
+        (0..CUBE.len())
+            .min_by_key(|&idx| (CUBE[idx] as i32 - x as i32).abs())
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (CUBE[ri], CUBE[gi], CUBE[bi]);
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let gray_idx = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 8 + gray_idx * 10;
+    if distance((r, g, b), (gray, gray, gray)) < distance((r, g, b), cube) {
+        232 + gray_idx
+    } else {
+        16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8
+    }
+}
+
+// The nearest of the basic colors by hue, with grays for colors that are
+// barely tinted. Going by distance alone would turn every pastel gray.
+fn nearest_16(r: u8, g: u8, b: u8) -> Color {
+    let (max, min) = (r.max(g).max(b) as i32, r.min(g).min(b) as i32);
+    let lightness = (max + min) / 2;
+    if max - min < 40 {
+        return match lightness {
+            0..=47 => Color::Black,
+            48..=143 => Color::DarkGrey,
+            144..=223 => Color::Grey,
+            _ => Color::White,
+        };
+    }
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let delta = max - min;
+    let hue = if max == r {
+        60 * (g - b) / delta
+    } else if max == g {
+        60 * (b - r) / delta + 120
+    } else {
+        60 * (r - g) / delta + 240
+    }
+    .rem_euclid(360);
+    let bright = lightness >= 128;
+    match (hue, bright) {
+        (0..=17 | 330..=359, true) => Color::Red,
+        (0..=17 | 330..=359, false) => Color::DarkRed,
+        (18..=74, true) => Color::Yellow,
+        (18..=74, false) => Color::DarkYellow,
+        (75..=149, true) => Color::Green,
+        (75..=149, false) => Color::DarkGreen,
+        (150..=209, true) => Color::Cyan,
+        (150..=209, false) => Color::DarkCyan,
+        (210..=269, true) => Color::Blue,
+        (210..=269, false) => Color::DarkBlue,
+        (_, true) => Color::Magenta,
+        (_, false) => Color::DarkMagenta,
+    }
+}
+
+// The SGR parameters that set a color as the text's foreground or its
+// background, for output drawn without comfy-table
+pub fn sgr(color: Color, background: bool) -> Option<String> {
+    let (extended, base) = if background { (48, 40) } else { (38, 30) };
+    let basic = |idx: u8| Some(if idx < 8 { base + idx } else { base + 60 + idx - 8 }.to_string());
+    match color {
+        Color::Rgb { r, g, b } => Some(format!("{};2;{};{};{}", extended, r, g, b)),
+        Color::AnsiValue(idx) => Some(format!("{};5;{}", extended, idx)),
+        Color::Black => basic(0),
+        Color::DarkRed => basic(1),
+        Color::DarkGreen => basic(2),
+        Color::DarkYellow => basic(3),
+        Color::DarkBlue => basic(4),
+        Color::DarkMagenta => basic(5),
+        Color::DarkCyan => basic(6),
+        Color::Grey => basic(7),
+        Color::DarkGrey => basic(8),
+        Color::Red => basic(9),
+        Color::Green => basic(10),
+        Color::Yellow => basic(11),
+        Color::Blue => basic(12),
+        Color::Magenta => basic(13),
+        Color::Cyan => basic(14),
+        Color::White => basic(15),
+        Color::Reset => None,
+    }
+}
@@ -0,0 +1,140 @@
+use crate::compression::Compression;
+use crate::config::ColorScheme;
+use crate::encryption::Encryption;
+use crate::input::{Encoding, InputFormat, ReadOptions};
+use crate::pager::cut_line;
+use crate::{create_table, plain, read_csv_data, read_csv_source, render_table, shape_view, source, styled, view_of, Args, CsvData};
+use std::fs::File;
+use std::io::{self, Read, Write};
+
+// Rows shown when neither the environment nor --max-rows says how many
+const DEFAULT_ROWS: usize = 50;
+const DEFAULT_WIDTH: usize = 80;
+// What is read of a file at a time until enough lines have come in
+const CHUNK_LEN: usize = 1 << 16;
+
+// The first variable that is set to a number. Pickers say how big their
+// preview window is, and PCSV_PREVIEW_* lets any other tool say so too.
+fn env_size(names: &[&str]) -> Option<usize> {
+    names
+        .iter()
+        .find_map(|name| std::env::var(name).ok()?.trim().parse().ok())
+        .filter(|&size| size > 0)
+}
+
+fn preview_width() -> usize {
+    env_size(&["PCSV_PREVIEW_WIDTH", "FZF_PREVIEW_COLUMNS"])
+        .or_else(|| crossterm::terminal::size().ok().map(|(width, _)| width as usize))
+        .unwrap_or(DEFAULT_WIDTH)
+}
+
+// As many rows as fit under the header and above the bottom border
+fn preview_rows() -> usize {
+    env_size(&["PCSV_PREVIEW_LINES", "FZF_PREVIEW_LINES"])
+        .map(|lines| lines.saturating_sub(4).max(1))
+        .unwrap_or(DEFAULT_ROWS)
+}
+
+// The first `rows` records of a plain CSV or TSV file, read only as far as
+// needed. Anything else (compressed, encrypted or other formats, standard
+// input, other encodings) is read whole and cut down.
+fn read_head(input: &str, rows: usize, options: &ReadOptions) -> Result<CsvData, Box<dyn std::error::Error>> {
+    if let Some((data, _)) = read_start(input, rows, options)? {
+        return Ok(data);
+    }
+    let (headers, mut records) = read_csv_data(input, options)?;
+    records.truncate(rows);
+    Ok((headers, records))
+}
+
+// The first `rows` records of a plain CSV or TSV file, read only as far as
+// needed, and whether they are all it has. None for any other input, which
+// can only be read whole.
+pub fn read_start(
+    input: &str,
+    rows: usize,
+    options: &ReadOptions,
+) -> Result<Option<(CsvData, bool)>, Box<dyn std::error::Error>> {
+    let format = options.format.unwrap_or_else(|| InputFormat::detect(input, &[]));
+    if input == "-"
+        || source::is_remote(input)
+        || !matches!(format, InputFormat::Csv | InputFormat::Tsv)
+        || !matches!(options.encoding, None | Some(Encoding::Utf8))
+        || options.query.is_some()
+        || options.repair.is_some()
+    {
+        return Ok(None);
+    }
+
+    let mut file = File::open(input)?;
+    let mut bytes = Vec::new();
+    let mut chunk = vec![0; CHUNK_LEN];
+    let mut lines = 0;
+    // The header and one line more than the rows, so the last row is whole
+    let finished = loop {
+        let len = file.read(&mut chunk)?;
+        if len == 0 {
+            break true;
+        }
+        lines += chunk[..len].iter().filter(|&&b| b == b'\n').count();
+        bytes.extend_from_slice(&chunk[..len]);
+        if Compression::detect(&bytes, Some(input)).is_some() || Encryption::detect(&bytes).is_some() {
+            return Ok(None);
+        }
+        if lines > rows + 1 {
+            break false;
+        }
+    };
+    if !finished {
+        let end = bytes.iter().rposition(|&b| b == b'\n').map_or(0, |idx| idx + 1);
+        bytes.truncate(end);
+    }
+    let (headers, mut records) = read_csv_source(input, source::Source::Owned(bytes), options)?;
+    // A value spanning lines may have been cut off with the last record
+    if !finished && records.len() <= rows {
+        records.pop();
+    }
+    let whole = finished && records.len() <= rows;
+    records.truncate(rows);
+    Ok(Some(((headers, records), whole)))
+}
+
+// A table sized for a picker's preview window: the first rows that fit,
+// without lines between them, and every line cut to the window's width
+pub fn run(mut args: Args, mut scheme: ColorScheme) -> Result<(), Box<dyn std::error::Error>> {
+    let [input] = args.inputs.as_slice() else {
+        return Err("--preview shows a single input".into());
+    };
+    let input = input.clone();
+    let width = preview_width();
+    let rows = args.max_rows.unwrap_or_else(preview_rows);
+    args.max_rows = Some(rows);
+    args.no_row_lines = true;
+    // One long value shouldn't push every other column out of view
+    scheme.truncate.max_width.get_or_insert((width / 2).max(1));
+
+    // Filtering and sorting look at every row, not only the first ones
+    let data = if args.filter.is_some() || args.sort.is_some() {
+        read_csv_data(&input, &args.read)?
+    } else {
+        read_head(&input, rows + usize::from(args.header_rows - 1), &args.read)?
+    };
+    let mut view = view_of(data, &scheme, &args)?;
+    shape_view(&mut view, &args)?;
+
+    // Pickers show colors even though they read from a pipe
+    let mut table = create_table(view.headers, view.records, &scheme, &args, styled());
+    if !plain() {
+        table.enforce_styling();
+    }
+    let rendered = render_table(&table, view.group_labels.as_deref(), &scheme, &args);
+    let mut out = io::stdout().lock();
+    for line in rendered.lines() {
+        match writeln!(out, "{}", cut_line(line, 0, width)) {
+            // The picker moved on to another file
+            Err(e) if e.kind() == io::ErrorKind::BrokenPipe => break,
+            result => result?,
+        }
+    }
+    Ok(())
+}
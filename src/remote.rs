@@ -0,0 +1,364 @@
+#[cfg(feature = "cloud")]
+use crate::cloud::Store;
+use crate::compression::Compression;
+use crate::encryption::Encryption;
+use crate::index::{Bound, Window};
+use crate::input::{Encoding, InputFormat, ReadOptions};
+use crate::source::Source;
+use crate::{read_records, CsvData};
+use std::cell::OnceCell;
+use std::io::{self, Read};
+use std::process::{Command, Stdio};
+
+// The first range fetched, doubled until it holds the rows asked for
+const FIRST_CHUNK: u64 = 256 * 1024;
+
+// Where a remote file is, and so how it is fetched. Files on other hosts
+// are read through the `ssh` command, which has to be installed, objects
+// in a bucket through the SDK of their store.
+#[derive(Debug, Clone)]
+enum Location {
+    // `ssh://[user@]host[:port]/path`. The path is absolute, or relative to
+    // the home directory when it starts with `/~/`.
+    Ssh {
+        host: String,
+        port: Option<u16>,
+        path: String,
+    },
+    // `s3://bucket/key`
+    #[cfg(feature = "cloud")]
+    S3 { bucket: String, key: String },
+    // `gs://bucket/object`
+    #[cfg(feature = "cloud")]
+    Gcs { bucket: String, object: String },
+}
+
+pub struct Remote {
+    location: Location,
+    // Asked for once, when a range needs it
+    size: OnceCell<u64>,
+    // The client for a bucket, made when it is first read from
+    #[cfg(feature = "cloud")]
+    store: OnceCell<Store>,
+}
+
+pub fn parse_remote(input: &str) -> Result<Remote, String> {
+    let (scheme, rest) = input
+        .split_once("://")
+        .ok_or_else(|| format!("'{}' is not a remote URL", input))?;
+    match scheme {
+        "ssh" | "sftp" => {}
+        "s3" | "gs" if cfg!(feature = "cloud") => {}
+        "s3" | "gs" => {
+            return Err(format!(
+                "reading {}:// URLs needs pcsv built with the `cloud` feature",
+                scheme
+            ));
+        }
+        _ => return Err(format!("'{}' is not an ssh://, sftp://, s3:// or gs:// URL", input)),
+    }
+    let (authority, path) = rest
+        .split_once('/')
+        .filter(|(authority, path)| !authority.is_empty() && !path.is_empty())
+        .ok_or_else(|| format!("'{}' is missing a host or a path, expected {}://HOST/PATH", input, scheme))?;
+    let location = match scheme {
+        #[cfg(feature = "cloud")]
+        "s3" => Location::S3 {
+            bucket: authority.to_string(),
+            key: path.to_string(),
+        },
+        #[cfg(feature = "cloud")]
+        "gs" => Location::Gcs {
+            bucket: authority.to_string(),
+            object: path.to_string(),
+        },
+        _ => {
+            let (host, port) = match authority.rsplit_once(':') {
+                Some((host, port)) => {
+                    let port = port
+                        .parse()
+                        .map_err(|_| format!("'{}' is not a port in '{}'", port, input))?;
+                    (host, Some(port))
+                }
+                None => (authority, None),
+            };
+            let path = match path.strip_prefix("~/") {
+                Some(home) => home.to_string(),
+                None => format!("/{}", path),
+            };
+            Location::Ssh {
+                host: host.to_string(),
+                port,
+                path,
+            }
+        }
+    };
+    Ok(Remote {
+        location,
+        size: OnceCell::new(),
+        #[cfg(feature = "cloud")]
+        store: OnceCell::new(),
+    })
+}
+
+// Quoted for the remote shell
+fn quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+// The output of a command. Prompts and errors go straight to the terminal.
+fn output(program: &str, args: &[String]) -> io::Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::inherit())
+        .spawn()
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => io::Error::new(e.kind(), format!("the {} command isn't installed", program)),
+            _ => e,
+        })?;
+    let mut output = Vec::new();
+    if let Some(mut stdout) = child.stdout.take() {
+        stdout.read_to_end(&mut output)?;
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(io::Error::other(format!("{} failed ({})", program, status)));
+    }
+    Ok(output)
+}
+
+fn strings(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+// Ranges of a file are fetched with `head -c` and `tail -c` over ssh, and
+// as HTTP ranges from object storage
+pub enum Range {
+    All,
+    Head(u64),
+    Tail(u64),
+}
+
+impl Remote {
+    // Through the ssh command and the SDKs of the object stores, so keys,
+    // agents, profiles and config files apply
+    fn fetch(&self, range: Range) -> io::Result<Vec<u8>> {
+        match &self.location {
+            Location::Ssh { host, port, path } => {
+                let script = match range {
+                    Range::All => format!("cat -- {}", quote(path)),
+                    Range::Head(bytes) => format!("head -c {} -- {}", bytes, quote(path)),
+                    Range::Tail(bytes) => format!("tail -c {} -- {}", bytes, quote(path)),
+                };
+                let mut args = Vec::new();
+                if let Some(port) = port {
+                    args.extend(strings(&["-p", &port.to_string()]));
+                }
+                args.extend(strings(&["--", host, &script]));
+                output("ssh", &args)
+            }
+            #[cfg(feature = "cloud")]
+            Location::S3 { bucket, key } => self.read_object(bucket, key, range),
+            #[cfg(feature = "cloud")]
+            Location::Gcs { bucket, object } => self.read_object(bucket, object, range),
+        }
+    }
+
+    // Streams the body of an object in, noting the size of all of it
+    #[cfg(feature = "cloud")]
+    fn read_object(&self, bucket: &str, object: &str, range: Range) -> io::Result<Vec<u8>> {
+        let store = match self.store.get() {
+            Some(store) => store,
+            None => {
+                let store = match self.location {
+                    Location::Gcs { .. } => Store::gcs()?,
+                    _ => Store::s3()?,
+                };
+                self.store.get_or_init(|| store)
+            }
+        };
+        let mut body = store.read(bucket, object, range)?;
+        let _ = self.size.set(body.size);
+        let mut bytes = Vec::new();
+        body.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn read_all(&self) -> io::Result<Vec<u8>> {
+        self.fetch(Range::All)
+    }
+
+    fn read_head(&self, bytes: u64) -> io::Result<Vec<u8>> {
+        self.fetch(Range::Head(bytes))
+    }
+
+    fn read_tail(&self, bytes: u64) -> io::Result<Vec<u8>> {
+        self.fetch(Range::Tail(bytes))
+    }
+
+    fn len(&self) -> io::Result<u64> {
+        if let Some(&len) = self.size.get() {
+            return Ok(len);
+        }
+        let (host, port, path) = match &self.location {
+            Location::Ssh { host, port, path } => (host, port, path),
+            // Any part of an object comes with the size of all of it
+            #[cfg(feature = "cloud")]
+            _ => {
+                self.read_head(1)?;
+                return self.size.get().copied().ok_or_else(|| io::Error::other("no size for the object"));
+            }
+        };
+        let mut args = Vec::new();
+        if let Some(port) = port {
+            args.extend(strings(&["-p", &port.to_string()]));
+        }
+        args.extend(strings(&["--", host, &format!("wc -c < {}", quote(path))]));
+        let len = String::from_utf8_lossy(&output("ssh", &args)?)
+            .trim()
+            .parse()
+            .map_err(|_| io::Error::other("no size for the remote file"))?;
+        Ok(*self.size.get_or_init(|| len))
+    }
+}
+
+pub fn open(input: &str) -> io::Result<Source> {
+    let remote = parse_remote(input).map_err(io::Error::other)?;
+    remote.read_all().map(Source::Owned)
+}
+
+// Where the records in `bytes` end: the line breaks outside quoted
+// values, for bytes that start inside one or not. A doubled quote within
+// a value leaves it quoted.
+fn record_ends(bytes: &[u8], quoted: bool) -> impl Iterator<Item = usize> + '_ {
+    bytes
+        .iter()
+        .enumerate()
+        .scan(quoted, |quoted, (idx, &b)| {
+            if b == b'"' {
+                *quoted = !*quoted;
+            }
+            Some((idx, b == b'\n' && !*quoted))
+        })
+        .filter_map(|(idx, end)| end.then_some(idx + 1))
+}
+
+// The bytes from the start of a file up to the end of its last whole record
+fn whole_records(bytes: &[u8]) -> &[u8] {
+    &bytes[..record_ends(bytes, false).last().unwrap_or(0)]
+}
+
+// Where the first whole record in the last bytes of a file starts. They
+// start inside a quoted value when they hold an odd number of quotes, as
+// the file ends outside of one.
+fn first_record(tail: &[u8]) -> Option<usize> {
+    let quoted = tail.iter().filter(|&&b| b == b'"').count() % 2 == 1;
+    record_ends(tail, quoted).next()
+}
+
+// Reads the rows of a `--tail` or a `--rows` range that counts from the
+// start by fetching only the bytes around them, from a host or a bucket. `None` when the window or
+// the file needs all of it: percentages, compressed, encrypted or other
+// formats than CSV and TSV.
+pub fn read_window(
+    input: &str,
+    window: Window,
+    leading: usize,
+    options: &ReadOptions,
+) -> Result<Option<CsvData>, Box<dyn std::error::Error>> {
+    let remote = parse_remote(input)?;
+    let head_rows = match window {
+        Window::Tail(_) => None,
+        Window::Rows(None | Some(Bound::Row(_)), Some(Bound::Row(end))) => Some(end),
+        Window::Rows(..) => return Ok(None),
+    };
+    if !matches!(options.encoding, None | Some(Encoding::Utf8))
+        || Encryption::from_extension(input).is_some()
+    {
+        return Ok(None);
+    }
+
+    // The header and the leading rows, and for a range from the start
+    // everything up to its end
+    let wanted = 1 + leading + head_rows.unwrap_or(0);
+    let mut chunk = FIRST_CHUNK;
+    let (prefix, complete) = loop {
+        let head = remote.read_head(chunk)?;
+        if (head.len() as u64) < chunk {
+            break (head, true);
+        }
+        if record_ends(&head, false).count() >= wanted {
+            break (head, false);
+        }
+        chunk *= 2;
+    };
+    let format = options.format.unwrap_or_else(|| InputFormat::detect(input, &prefix));
+    if !matches!(format, InputFormat::Csv | InputFormat::Tsv)
+        || Compression::detect(&prefix, Some(input)).is_some()
+        || Encryption::detect(&prefix).is_some()
+    {
+        return Ok(None);
+    }
+    let window_of = |(headers, mut records): CsvData| {
+        let leading = leading.min(records.len());
+        let range = window.range(records.len() - leading);
+        let mut rows: Vec<_> = records.drain(..leading).collect();
+        rows.extend(records.drain(range));
+        (headers, rows)
+    };
+    // A file fetched whole keeps a last line without a line break
+    let head_of = |prefix: Vec<u8>, complete: bool| {
+        let bytes = if complete { prefix } else { whole_records(&prefix).to_vec() };
+        read_records(input, Source::Owned(bytes), format, options)
+    };
+    if head_rows.is_some() || complete {
+        return Ok(Some(window_of(head_of(prefix, complete)?)));
+    }
+
+    // The header and the leading rows, then a tail of the file from its
+    // first whole record, grown until it holds one row more than asked for
+    let Window::Tail(count) = window else {
+        return Ok(None);
+    };
+    let header_end = record_ends(&prefix, false).nth(leading).unwrap_or(prefix.len());
+    let len = remote.len()?;
+    let mut chunk = FIRST_CHUNK;
+    loop {
+        if header_end as u64 + chunk >= len {
+            return Ok(Some(window_of(head_of(remote.read_all()?, true)?)));
+        }
+        let tail = remote.read_tail(chunk)?;
+        let Some(start) = first_record(&tail) else {
+            chunk *= 2;
+            continue;
+        };
+        let mut bytes = prefix[..header_end].to_vec();
+        bytes.extend_from_slice(&tail[start..]);
+        let (headers, mut records) = read_records(input, Source::Owned(bytes), format, options)?;
+        let leading = leading.min(records.len());
+        if records.len() - leading > count {
+            let first = records.len() - count;
+            let mut rows: Vec<_> = records.drain(..leading).collect();
+            rows.extend(records.drain(first..));
+            return Ok(Some((headers, rows)));
+        }
+        chunk *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_records_around_quoted_line_breaks() {
+        let file = b"id,note\n1,\"two\nlines\"\n2,\"say \"\"hi\"\"\n\"\n3,x\n";
+        let tail_from = |cut: usize| first_record(&file[cut..]).map(|start| &file[cut + start..]);
+        // Cut inside the first quoted value and inside the second
+        assert_eq!(tail_from(12), Some(&b"2,\"say \"\"hi\"\"\n\"\n3,x\n"[..]));
+        assert_eq!(tail_from(30), Some(&b"3,x\n"[..]));
+        assert_eq!(whole_records(&file[..33]), b"id,note\n1,\"two\nlines\"\n");
+    }
+}
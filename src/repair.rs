@@ -0,0 +1,204 @@
+use crate::stats::dominant_type;
+use crate::store::Field;
+use crate::{column_index, detect_builtin_type, DataType};
+
+// Records of the wrong length kept to work out what went wrong
+const SAMPLES: usize = 100;
+// Well-formed records looked at for the type of each column
+const TYPE_SAMPLE: usize = 1000;
+
+// What `--repair` does to records with more fields than the header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Repair {
+    // Drops the empty field a trailing delimiter leaves at the end
+    DropTrailing,
+    // Joins the extra fields back into a column whose values contain the
+    // delimiter unquoted
+    MergeExtraInto(String),
+}
+
+pub fn parse_repair(val: &str) -> Result<Repair, String> {
+    match val.split_once('=') {
+        _ if val == "drop-trailing" => Ok(Repair::DropTrailing),
+        Some(("merge-extra-into", column)) if !column.is_empty() => {
+            Ok(Repair::MergeExtraInto(column.to_string()))
+        }
+        _ => Err(format!(
+            "invalid repair '{}', expected drop-trailing or merge-extra-into=COLUMN",
+            val
+        )),
+    }
+}
+
+// A repair with its column looked up in the header
+pub enum Fix {
+    DropTrailing,
+    MergeExtraInto(usize),
+}
+
+impl Repair {
+    pub fn resolve(&self, headers: &[String]) -> Result<Fix, Box<dyn std::error::Error>> {
+        Ok(match self {
+            Repair::DropTrailing => Fix::DropTrailing,
+            Repair::MergeExtraInto(name) => Fix::MergeExtraInto(column_index(Some(headers), name)?),
+        })
+    }
+}
+
+impl Fix {
+    // The record with as many fields as the header, if this repair can get
+    // it there
+    pub fn apply(
+        &self,
+        fields: &[String],
+        header_len: usize,
+        delimiter: u8,
+    ) -> Option<Vec<String>> {
+        match *self {
+            Fix::DropTrailing => (fields.len() == header_len + 1 && fields[header_len].is_empty())
+                .then(|| fields[..header_len].to_vec()),
+            Fix::MergeExtraInto(column) => {
+                let extra = fields
+                    .len()
+                    .checked_sub(header_len)
+                    .filter(|&extra| extra > 0)?;
+                Some(merged(fields, column, extra, delimiter))
+            }
+        }
+    }
+}
+
+// Fields `column..=column + extra` joined back together by the delimiter
+fn merged(fields: &[String], column: usize, extra: usize, delimiter: u8) -> Vec<String> {
+    let mut repaired = fields[..column].to_vec();
+    repaired.push(fields[column..=column + extra].join(&(delimiter as char).to_string()));
+    repaired.extend_from_slice(&fields[column + extra + 1..]);
+    repaired
+}
+
+// Whether a value fits a column of the given type
+fn fits(val: &str, column_type: DataType) -> bool {
+    let ty = detect_builtin_type(val);
+    ty == column_type
+        || matches!(ty, DataType::Empty)
+        || matches!(column_type, DataType::Text | DataType::Empty)
+        || matches!(
+            (ty, column_type),
+            (DataType::IntNumber, DataType::FloatNumber)
+        )
+}
+
+// Records whose field count differs from the header's: how many, and the
+// first few to work out why
+#[derive(Default)]
+pub struct Mismatches {
+    count: usize,
+    samples: Vec<(u64, Vec<String>)>,
+}
+
+impl Mismatches {
+    pub fn add<'a>(&mut self, line: u64, fields: impl IntoIterator<Item = &'a [u8]>) {
+        self.count += 1;
+        if self.samples.len() < SAMPLES {
+            let fields = fields
+                .into_iter()
+                .map(|field| String::from_utf8_lossy(field).into_owned())
+                .collect();
+            self.samples.push((line, fields));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    // Says how many records are off and, when one repair fits all of the
+    // sampled ones, which `--repair` to pass
+    pub fn explain(&self, headers: &[String], records: &[Vec<Field>], delimiter: u8) -> String {
+        let header_len = headers.len();
+        let (line, first) = &self.samples[0];
+        let mut message = format!(
+            "{} of {} records don't have the {} fields of the header (the first is line {}, with {})",
+            self.count,
+            self.count + records.len(),
+            header_len,
+            line,
+            first.len()
+        );
+        let trailing = self.samples.iter().all(|(_, fields)| {
+            Fix::DropTrailing
+                .apply(fields, header_len, delimiter)
+                .is_some()
+        });
+        let shown = delimiter as char;
+        if trailing {
+            message.push_str(&format!(
+                ". Each ends in an extra empty field, as if its line had a trailing '{}'; pass --repair drop-trailing to drop it",
+                shown
+            ));
+        } else if let Some((column, extra)) = self.merge_column(headers, records, delimiter) {
+            message.push_str(&format!(
+                ". They have {} extra field{}, likely from an unquoted '{}' in the '{}' column; pass --repair merge-extra-into={} to join them back into it",
+                extra,
+                if extra == 1 { "" } else { "s" },
+                shown,
+                headers[column],
+                headers[column]
+            ));
+        } else {
+            message.push_str(". No single repair fits them all");
+        }
+        message
+    }
+
+    // The column that, with the extra fields merged into it, leaves the
+    // most values fitting the type of their column. Only when every sampled
+    // record has the same number of extra fields.
+    fn merge_column(
+        &self,
+        headers: &[String],
+        records: &[Vec<Field>],
+        delimiter: u8,
+    ) -> Option<(usize, usize)> {
+        let header_len = headers.len();
+        let extra = self.samples[0].1.len().checked_sub(header_len)?;
+        if extra == 0
+            || self
+                .samples
+                .iter()
+                .any(|(_, fields)| fields.len() != header_len + extra)
+        {
+            return None;
+        }
+        let sample = &records[..records.len().min(TYPE_SAMPLE)];
+        let types: Vec<DataType> = (0..header_len)
+            .map(|column| {
+                let values: Vec<&str> = sample
+                    .iter()
+                    .map(|record| record.get(column).map_or("", |val| val.as_str()))
+                    .collect();
+                dominant_type(&values)
+            })
+            .collect();
+        let score = |column: usize| -> usize {
+            self.samples
+                .iter()
+                .map(|(_, fields)| {
+                    merged(fields, column, extra, delimiter)
+                        .iter()
+                        .zip(&types)
+                        .filter(|(val, &ty)| fits(val, ty))
+                        .count()
+                })
+                .sum()
+        };
+        let mut best: Option<(usize, usize)> = None;
+        for column in 0..header_len {
+            let column_score = score(column);
+            if best.is_none_or(|(_, best_score)| column_score > best_score) {
+                best = Some((column, column_score));
+            }
+        }
+        best.map(|(column, _)| (column, extra))
+    }
+}
@@ -0,0 +1,132 @@
+use crate::config::{is_hex_color, ColorScheme, FormatRule, RuleValue};
+use comfy_table::Color;
+use pcsv::{detect_builtin_type, plain_amount, plain_number, DataType};
+use regex::Regex;
+
+// How a rule styles the cells it matches
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CellStyle {
+    // In place of the color of the cell's type
+    pub color: Option<Color>,
+    pub bold: bool,
+}
+
+#[derive(Clone)]
+struct Rule {
+    // The field the rule looks at, or every field
+    field: Option<usize>,
+    gt: Option<f64>,
+    ge: Option<f64>,
+    lt: Option<f64>,
+    le: Option<f64>,
+    eq: Option<RuleValue>,
+    ne: Option<RuleValue>,
+    matches: Option<Regex>,
+    style: CellStyle,
+    // The color as the config writes it, before it is fitted to the terminal
+    #[cfg(feature = "xlsx")]
+    hex: Option<String>,
+}
+
+// The config's `rules`, ready to test cells with. Rules naming a column the
+// file doesn't have are left out.
+#[derive(Clone, Default)]
+pub struct CellRules {
+    rules: Vec<Rule>,
+}
+
+fn same(value: &RuleValue, val: &str, number: Option<f64>) -> bool {
+    match value {
+        RuleValue::Number(expected) => number == Some(*expected),
+        RuleValue::Text(expected) => val == expected,
+    }
+}
+
+impl Rule {
+    fn matches(&self, val: &str, data_type: &DataType) -> bool {
+        // A custom type may also be a number
+        let data_type = match data_type {
+            DataType::Custom(_) => detect_builtin_type(val),
+            ty => *ty,
+        };
+        let number = match data_type {
+            DataType::IntNumber | DataType::FloatNumber => plain_number(val).trim().parse::<f64>().ok(),
+            DataType::Currency => plain_amount(val).and_then(|amount| amount.parse::<f64>().ok()),
+            _ => None,
+        };
+        let compare = |bound: Option<f64>, test: fn(f64, f64) -> bool| match (bound, number) {
+            (None, _) => true,
+            (Some(bound), Some(num)) => test(num, bound),
+            (Some(_), None) => false,
+        };
+        compare(self.gt, |num, bound| num > bound)
+            && compare(self.ge, |num, bound| num >= bound)
+            && compare(self.lt, |num, bound| num < bound)
+            && compare(self.le, |num, bound| num <= bound)
+            && self.eq.as_ref().is_none_or(|value| same(value, val, number))
+            && self.ne.as_ref().is_none_or(|value| !same(value, val, number))
+            && self.matches.as_ref().is_none_or(|re| re.is_match(val))
+    }
+}
+
+impl CellRules {
+    // Rules for a file with these field names. A bad regex or color is an
+    // error, whether or not the file has the rule's column.
+    pub fn new(rules: &[FormatRule], names: &[String]) -> Result<Self, String> {
+        let mut compiled = Vec::new();
+        for (idx, rule) in rules.iter().enumerate() {
+            let at = |message: String| format!("rule {}: {}", idx + 1, message);
+            let matches = match &rule.matches {
+                Some(pattern) => Some(Regex::new(pattern).map_err(|e| at(format!("invalid regex: {}", e)))?),
+                None => None,
+            };
+            let color = match &rule.color {
+                Some(hex) if !is_hex_color(hex) => {
+                    return Err(at(format!("expects a #RRGGBB color, got '{}'", hex)))
+                }
+                Some(hex) => Some(ColorScheme::hex_to_color(hex)),
+                None => None,
+            };
+            let field = match &rule.column {
+                Some(column) => match names.iter().position(|name| name == column) {
+                    Some(field) => Some(field),
+                    None => continue,
+                },
+                None => None,
+            };
+            compiled.push(Rule {
+                field,
+                gt: rule.gt,
+                ge: rule.ge,
+                lt: rule.lt,
+                le: rule.le,
+                eq: rule.eq.clone(),
+                ne: rule.ne.clone(),
+                matches,
+                style: CellStyle { color, bold: rule.bold },
+                #[cfg(feature = "xlsx")]
+                hex: rule.color.clone(),
+            });
+        }
+        Ok(CellRules { rules: compiled })
+    }
+
+    fn first(&self, field: usize, val: &str, data_type: &DataType) -> Option<&Rule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.field.is_none_or(|only| only == field))
+            .find(|rule| rule.matches(val, data_type))
+    }
+
+    // The style of the first rule a cell matches
+    pub fn style(&self, field: usize, val: &str, data_type: &DataType) -> Option<CellStyle> {
+        self.first(field, val, data_type).map(|rule| rule.style)
+    }
+
+    // The same with the color as written, for output that isn't limited to
+    // the terminal's colors
+    #[cfg(feature = "xlsx")]
+    pub fn hex_style(&self, field: usize, val: &str, data_type: &DataType) -> Option<(Option<&str>, bool)> {
+        self.first(field, val, data_type).map(|rule| (rule.hex.as_deref(), rule.style.bold))
+    }
+}
@@ -0,0 +1,197 @@
+use crate::config::ColorScheme;
+use crate::{create_table, layouts, output, read_csv_source, render_table, shape_view, source, view_of, Args, OutputFormat};
+use clap::Parser;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+#[derive(clap::Args)]
+pub struct ServeArgs {
+    #[arg(long, value_name = "PATH")]
+    pub socket: PathBuf,
+}
+
+// How a rendered table comes back
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum RenderFormat {
+    #[default]
+    Ansi,
+    Plain,
+    Html,
+}
+
+// One line of JSON from a client: a file to read or CSV text sent along,
+// and the command line options to show it with
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Request {
+    path: Option<String>,
+    csv: Option<String>,
+    #[serde(default)]
+    args: Vec<String>,
+    #[serde(default)]
+    format: RenderFormat,
+}
+
+// Answered on one line as well, with either field set
+#[derive(Serialize)]
+struct Response {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl Response {
+    fn new(result: Result<String, String>) -> Self {
+        match result {
+            Ok(output) => Response {
+                output: Some(output),
+                error: None,
+            },
+            Err(error) => Response {
+                output: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+// The options of a request, parsed like a command line. The config, theme
+// and number format were loaded when the server started, and what isn't a
+// table (reports, the pager, exports) has its own way to be asked for.
+fn request_args(request: &Request, input: &str) -> Result<Args, String> {
+    let argv = std::iter::once("pcsv")
+        .chain(request.args.iter().map(String::as_str))
+        .chain(std::iter::once(input));
+    let args = Args::try_parse_from(argv).map_err(|e| e.render().to_string().trim_end().to_string())?;
+    if args.config.is_some() || args.theme.is_some() || !args.overrides.is_empty() || args.number_format.is_some() {
+        return Err("--config, --theme, --set and --number-format are given when starting serve".to_string());
+    }
+    let is_report = !args.checks.is_empty()
+        || args.stats
+        || args.schema
+        || args.missing
+        || args.frequency.is_some()
+        || args.timeline.is_some();
+    if args.command.is_some() || args.pager || is_report || args.export.is_some() || args.output != OutputFormat::Table {
+        return Err("serve only renders tables; pick the output with the request's `format`".to_string());
+    }
+    if args.inputs.len() > 1 {
+        return Err("a request shows a single input".to_string());
+    }
+    Ok(args)
+}
+
+fn render(request: Request, scheme: &ColorScheme) -> Result<String, String> {
+    let (input, data) = match (&request.path, &request.csv) {
+        (Some(_), Some(_)) => return Err("a request has either a `path` or `csv`, not both".to_string()),
+        (None, None) => return Err("a request needs a `path` or `csv`".to_string()),
+        // The server's own standard input isn't the client's
+        (Some(path), None) if path == "-" => return Err("send standard input as `csv`".to_string()),
+        (Some(path), None) => (path.as_str(), None),
+        (None, Some(csv)) => ("-", Some(csv.as_bytes().to_vec())),
+    };
+    let args = request_args(&request, input)?;
+    let view = match data {
+        Some(_) if args.tail.is_some() || args.rows.is_some() => {
+            return Err("--tail and --rows need a `path`".to_string());
+        }
+        Some(bytes) => read_csv_source(input, source::Source::Owned(bytes), &args.read)
+            .and_then(|data| view_of(data, scheme, &args)),
+        None => crate::load_view(input, scheme, &args),
+    };
+    let mut view = view.map_err(|e| e.to_string())?;
+    shape_view(&mut view, &args).map_err(|e| e.to_string())?;
+
+    let styled = request.format == RenderFormat::Ansi;
+    let (headers, records) = (view.headers, view.records);
+    Ok(match request.format {
+        RenderFormat::Html => output::records_to_html(
+            headers.as_deref(),
+            &records,
+            view.group_labels.as_deref(),
+            scheme,
+            args.show_row_numbers,
+        ),
+        _ if args.a11y => layouts::accessible_lines(headers.as_deref(), &records).join("\n"),
+        _ if args.vertical => layouts::vertical_lines(headers.as_deref(), &records, scheme, styled, None).0.join("\n"),
+        _ => {
+            let mut table = create_table(headers, records, scheme, &args, styled);
+            // Colored or not by the request, whatever the server's output is
+            if styled {
+                table.enforce_styling();
+            } else {
+                table.force_no_tty();
+            }
+            render_table(&table, view.group_labels.as_deref(), scheme, &args)
+        }
+    })
+}
+
+#[cfg(unix)]
+pub fn run(args: &ServeArgs, scheme: &ColorScheme) -> Result<(), Box<dyn std::error::Error>> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::{UnixListener, UnixStream};
+    use std::sync::Arc;
+
+    // A socket left behind by a server that is gone is replaced, one still
+    // answering is not
+    if args.socket.exists() {
+        if UnixStream::connect(&args.socket).is_ok() {
+            return Err(format!("{} is already served", args.socket.display()).into());
+        }
+        std::fs::remove_file(&args.socket)?;
+    }
+    let listener = UnixListener::bind(&args.socket)
+        .map_err(|e| format!("failed to listen on {}: {}", args.socket.display(), e))?;
+    eprintln!("Serving on {}", args.socket.display());
+
+    // Each client on a thread of its own, sending any number of requests
+    let scheme = Arc::new(scheme.clone());
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let scheme = Arc::clone(&scheme);
+        std::thread::spawn(move || -> std::io::Result<()> {
+            let mut writer = stream.try_clone()?;
+            for line in BufReader::new(stream).lines() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let result = serde_json::from_str::<Request>(&line)
+                    .map_err(|e| format!("invalid request: {}", e))
+                    .and_then(|request| render(request, &scheme));
+                let response = serde_json::to_string(&Response::new(result))?;
+                writeln!(writer, "{}", response)?;
+            }
+            Ok(())
+        });
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn run(_args: &ServeArgs, _scheme: &ColorScheme) -> Result<(), Box<dyn std::error::Error>> {
+    Err("serve needs Unix domain sockets, which this platform doesn't have".into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(format: &str) -> Request {
+        let line = format!(r#"{{"csv": "name,site\nann,https://example.com\n", "format": "{}"}}"#, format);
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[test]
+    fn links_only_in_ansi_responses() {
+        let scheme = ColorScheme::default();
+        let plain = render(request("plain"), &scheme).unwrap();
+        assert!(!plain.contains('\x1b'), "{:?}", plain);
+        // Whatever the server's own output is
+        let ansi = render(request("ansi"), &scheme).unwrap();
+        assert!(ansi.contains("\x1b]8;;https://example.com\x1b\\"), "{:?}", ansi);
+    }
+}
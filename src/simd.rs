@@ -0,0 +1,77 @@
+use crate::input::{self, ReadOptions};
+use crate::store::{Interner, Text};
+use crate::{warn_truncated_record, CsvData};
+use simd_csv::{ByteRecord, TotalReaderBuilder};
+use std::sync::Arc;
+
+// The SIMD reader agrees with the csv crate on well-formed files. Files
+// with records ending at a lone `\r` (classic Mac line endings) or quotes
+// in the middle of a field, which the two read differently, are left to
+// the csv crate.
+pub fn supports(content: &str, delimiter: u8) -> bool {
+    let bytes = content.as_bytes();
+    let lone_cr = memchr::memchr_iter(b'\r', bytes).any(|pos| bytes.get(pos + 1) != Some(&b'\n'));
+    let boundary = |byte: Option<&u8>| {
+        byte.is_none_or(|&byte| matches!(byte, b'"' | b'\n' | b'\r') || byte == delimiter)
+    };
+    let stray_quote = memchr::memchr_iter(b'"', bytes).any(|pos| {
+        let before = pos.checked_sub(1).and_then(|prev| bytes.get(prev));
+        !boundary(before) && !boundary(bytes.get(pos + 1))
+    });
+    !lone_cr && !stray_quote
+}
+
+fn line_at(content: &str, byte: usize) -> usize {
+    1 + memchr::memchr_iter(b'\n', &content.as_bytes()[..byte]).count()
+}
+
+// Parses the whole file with SIMD-accelerated searching for delimiters,
+// quotes and newlines. A partial last record is skipped as `read_csv_data`
+// does. Returns `None` on other records with a different number of fields
+// than the header, which are left to the csv crate to explain or repair.
+pub fn read_records(
+    text: &Arc<Text>,
+    delimiter: u8,
+    options: &ReadOptions,
+) -> Result<Option<CsvData>, Box<dyn std::error::Error>> {
+    let content = text.as_str();
+    let mut rdr = TotalReaderBuilder::new()
+        .delimiter(delimiter)
+        .from_bytes(content.as_bytes());
+    input::check_record(rdr.byte_headers().iter(), 1, content, delimiter, options)?;
+    let headers: Vec<String> = rdr
+        .byte_headers()
+        .iter()
+        .map(|field| String::from_utf8_lossy(field).into_owned())
+        .collect();
+    let header_len = headers.len();
+    let unterminated = !content.is_empty() && !content.ends_with('\n');
+
+    let mut records = Vec::new();
+    let mut interner = Interner::default();
+    let mut record = ByteRecord::new();
+    let mut start = rdr.position() as usize;
+    while rdr.read_byte_record(&mut record) {
+        // Cut off inside quotes, or without its line ending and short of
+        // fields
+        let is_last = rdr.position() as usize >= content.len();
+        if is_last
+            && ((unterminated && record.len() < header_len)
+                || input::ends_in_quotes(&content.as_bytes()[start..], delimiter))
+        {
+            warn_truncated_record(Some(line_at(content, start) as u64), options);
+            break;
+        }
+        if record.len() != header_len {
+            return Ok(None);
+        }
+        // No field can be too large if all of them together aren't
+        if record.as_slice().len() > options.max_cell_size {
+            let line = line_at(content, start) as u64;
+            input::check_record(record.iter(), line, content, delimiter, options)?;
+        }
+        records.push(interner.record_in(text, start, record.iter(), delimiter));
+        start = rdr.position() as usize;
+    }
+    Ok(Some((Some(headers), records)))
+}
@@ -0,0 +1,340 @@
+use crate::config::ColorScheme;
+use crate::input::{self, InputFormat, ReadOptions};
+use crate::stats::dominant_type;
+use crate::{compression, detect_builtin_type, encryption, new_table, source, DataType};
+use clap::ValueEnum;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, Table};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+#[derive(clap::Args)]
+pub struct SniffArgs {
+    pub input: String,
+
+    #[arg(short, long, value_enum, default_value_t = SniffFormat::Table)]
+    pub output: SniffFormat,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SniffFormat {
+    Table,
+    Json,
+}
+
+// The start of the file the guesses are made from
+const SAMPLE_LEN: usize = 1 << 16;
+const SAMPLE_RECORDS: usize = 1000;
+const DELIMITERS: [u8; 4] = [b',', b'\t', b';', b'|'];
+
+#[derive(Serialize)]
+struct Finding {
+    property: &'static str,
+    detected: String,
+    // From 0 to 1
+    confidence: f64,
+}
+
+impl Finding {
+    fn new(property: &'static str, detected: impl Into<String>, confidence: f64) -> Self {
+        Finding {
+            property,
+            detected: detected.into(),
+            confidence: (confidence.clamp(0.0, 1.0) * 100.0).round() / 100.0,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Report {
+    findings: Vec<Finding>,
+    // How pcsv itself will read the file, and what to pass if that's wrong
+    hint: String,
+}
+
+fn delimiter_name(delimiter: u8) -> String {
+    match delimiter {
+        b'\t' => "tab".to_string(),
+        other => format!("'{}'", other as char),
+    }
+}
+
+// The text of the sample, and the encoding it seems to be in
+fn sniff_encoding(bytes: &[u8]) -> Result<(String, Finding), Finding> {
+    if let Some(rest) = bytes.strip_prefix(&[0xff, 0xfe]) {
+        return Ok((input::decode_utf16(rest, true), Finding::new("Encoding", "utf-16le (byte order mark)", 1.0)));
+    }
+    if let Some(rest) = bytes.strip_prefix(&[0xfe, 0xff]) {
+        return Ok((input::decode_utf16(rest, false), Finding::new("Encoding", "utf-16be (byte order mark)", 1.0)));
+    }
+    if let Some(reason) = input::binary_reason(bytes) {
+        return Err(Finding::new("Encoding", format!("binary ({})", reason), 0.9));
+    }
+    let (bytes, bom) = match bytes.strip_prefix(&[0xef, 0xbb, 0xbf]) {
+        Some(rest) => (rest, true),
+        None => (bytes, false),
+    };
+    // The sample may end in the middle of a character
+    let valid = match std::str::from_utf8(bytes) {
+        Ok(text) => Some(text),
+        Err(e) if e.error_len().is_none() => Some(std::str::from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default()),
+        Err(_) => None,
+    };
+    Ok(match valid {
+        Some(text) if bom => (text.to_string(), Finding::new("Encoding", "utf-8 (byte order mark)", 1.0)),
+        Some(text) if text.is_ascii() => (text.to_string(), Finding::new("Encoding", "ascii (so utf-8 too)", 1.0)),
+        // Other encodings rarely make valid multi-byte sequences by chance
+        Some(text) => (text.to_string(), Finding::new("Encoding", "utf-8", 0.95)),
+        None => (
+            bytes.iter().map(|&b| b as char).collect(),
+            Finding::new("Encoding", "latin1 (not valid utf-8, pass --encoding latin1)", 0.6),
+        ),
+    })
+}
+
+fn sniff_line_endings(text: &str) -> Finding {
+    let bytes = text.as_bytes();
+    let crlf = text.matches("\r\n").count();
+    let lf = bytes.iter().filter(|&&b| b == b'\n').count() - crlf;
+    let cr = bytes.iter().filter(|&&b| b == b'\r').count() - crlf;
+    let total = crlf + lf + cr;
+    if total == 0 {
+        return Finding::new("Line endings", "none (a single line)", 1.0);
+    }
+    let (name, count) = [("LF", lf), ("CRLF", crlf), ("CR", cr)]
+        .into_iter()
+        .max_by_key(|&(_, count)| count)
+        .unwrap_or(("LF", lf));
+    let name = if count < total { format!("{} (mixed)", name) } else { name.to_string() };
+    Finding::new("Line endings", name, count as f64 / total as f64)
+}
+
+// The records a delimiter splits the sample into
+fn split(text: &str, delimiter: u8) -> Vec<Vec<String>> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .delimiter(delimiter)
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(text.as_bytes());
+    rdr.records()
+        .take(SAMPLE_RECORDS)
+        .map_while(Result::ok)
+        .map(|record| record.iter().map(str::to_string).collect())
+        .collect()
+}
+
+// The most common field count and the share of records having it
+fn consistency(records: &[Vec<String>]) -> (usize, f64) {
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    for record in records {
+        *counts.entry(record.len()).or_insert(0) += 1;
+    }
+    let (columns, count) = counts
+        .into_iter()
+        .max_by_key(|&(columns, count)| (count, columns))
+        .unwrap_or((0, 0));
+    (columns, count as f64 / records.len().max(1) as f64)
+}
+
+// How fields are quoted, judged from the raw lines: any field starting
+// with a quote counts as quoted
+fn sniff_quoting(text: &str, delimiter: u8) -> Finding {
+    let mut fields = 0;
+    let mut quoted = 0;
+    let mut quoted_numbers = 0;
+    let mut bare_text = 0;
+    for line in text.lines().take(SAMPLE_RECORDS) {
+        for field in line.split(delimiter as char) {
+            let field = field.trim();
+            fields += 1;
+            let numeric = matches!(
+                detect_builtin_type(field.trim_matches('"')),
+                DataType::IntNumber | DataType::FloatNumber
+            );
+            if field.starts_with('"') {
+                quoted += 1;
+                quoted_numbers += usize::from(numeric);
+            } else if !numeric && !field.is_empty() {
+                bare_text += 1;
+            }
+        }
+    }
+    match quoted {
+        0 if text.contains('"') => Finding::new("Quoting", "none", 0.8),
+        0 => Finding::new("Quoting", "none", 1.0),
+        _ if quoted == fields => Finding::new("Quoting", "every field", 1.0),
+        _ if quoted_numbers == 0 && bare_text == 0 => Finding::new("Quoting", "every non-numeric field", 0.9),
+        _ => Finding::new("Quoting", "only where needed", 0.9),
+    }
+}
+
+// Whether the first record names the columns. A header reads as text
+// above columns of numbers or dates, and has no blank or repeated names.
+fn sniff_header(records: &[Vec<String>]) -> Finding {
+    let Some((first, rest)) = records.split_first() else {
+        return Finding::new("Header", "unknown (no records)", 0.0);
+    };
+    let names: HashSet<&str> = first.iter().map(|name| name.trim()).collect();
+    if first.iter().any(|name| name.trim().is_empty()) || names.len() < first.len() {
+        return Finding::new("Header", "no (blank or repeated names in the first row)", 0.7);
+    }
+    let mut typed_below = 0;
+    let mut typed_first = 0;
+    let mut repeated = 0;
+    for (column, name) in first.iter().enumerate() {
+        let values: Vec<&str> = rest
+            .iter()
+            .map(|record| record.get(column).map_or("", String::as_str))
+            .collect();
+        let below = dominant_type(&values);
+        let own = detect_builtin_type(name);
+        if own == DataType::Text && !matches!(below, DataType::Text | DataType::Empty) {
+            typed_below += 1;
+        }
+        if own != DataType::Text {
+            typed_first += 1;
+        }
+        if values.contains(&name.as_str()) {
+            repeated += 1;
+        }
+    }
+    let columns = first.len() as f64;
+    if typed_first > 0 {
+        return Finding::new("Header", "no (the first row has numbers or dates)", 0.5 + 0.5 * typed_first as f64 / columns);
+    }
+    if typed_below > 0 {
+        return Finding::new("Header", "yes", 0.6 + 0.4 * typed_below as f64 / columns);
+    }
+    // All text: names that show up again as values are likely data
+    if repeated > 0 {
+        return Finding::new("Header", "no (first-row values repeat below)", 0.5 + 0.3 * repeated as f64 / columns);
+    }
+    Finding::new("Header", "yes (all columns are text, so a guess)", 0.55)
+}
+
+fn sniff(input: &str, options: &ReadOptions) -> Result<Report, Box<dyn std::error::Error>> {
+    let path = (input != "-").then_some(input);
+    let bytes = encryption::decrypt(source::open(input)?, path, options.identity.as_deref())
+        .map_err(|e| format!("failed to decrypt {}: {}", input, e))?;
+    let bytes = compression::decompress(bytes, path)
+        .map_err(|e| format!("failed to decompress {}: {}", input, e))?;
+    let format = InputFormat::detect(input, &bytes);
+    let read_as = match format {
+        InputFormat::Csv => b',',
+        InputFormat::Tsv => b'\t',
+        other => {
+            let name = format!("{:?}", other).to_lowercase();
+            return Ok(Report {
+                findings: vec![Finding::new("Format", name.clone(), 1.0)],
+                hint: format!("Not a delimited text file; pcsv reads it as {}", name),
+            });
+        }
+    };
+    let truncated = bytes.len() > SAMPLE_LEN;
+    let sample = &bytes[..bytes.len().min(SAMPLE_LEN)];
+
+    let (mut text, encoding) = match sniff_encoding(sample) {
+        Ok(found) => found,
+        Err(encoding) => {
+            return Ok(Report {
+                findings: vec![encoding],
+                hint: "pcsv won't read it as text without --encoding, --format or --force".to_string(),
+            })
+        }
+    };
+    // A cut-off last line would throw the field counts off
+    if truncated {
+        if let Some(end) = text.rfind('\n') {
+            text.truncate(end + 1);
+        }
+    }
+
+    let mut candidates: Vec<(u8, Vec<Vec<String>>, usize, f64)> = DELIMITERS
+        .iter()
+        .map(|&delimiter| {
+            let records = split(&text, delimiter);
+            let (columns, share) = consistency(&records);
+            // A delimiter that doesn't split anything explains nothing
+            let score = if columns > 1 { share } else { 0.0 };
+            (delimiter, records, columns, score)
+        })
+        .collect();
+    // The most consistent first, then the one splitting into more columns
+    candidates.sort_by(|a, b| b.3.total_cmp(&a.3).then(b.2.cmp(&a.2)));
+    let runner_up = candidates.get(1).map_or(0.0, |candidate| candidate.3);
+    let (delimiter, records, columns, score) = candidates.swap_remove(0);
+
+    let mut findings = vec![Finding::new("Format", "delimited text", 1.0), encoding];
+    let mut hint = match read_as {
+        b'\t' => "pcsv reads it as tab-separated".to_string(),
+        _ => "pcsv reads it as comma-separated".to_string(),
+    };
+    if score == 0.0 {
+        findings.push(Finding::new("Delimiter", "none (a single column)", 0.5));
+        findings.push(Finding::new("Columns", "1", 0.5));
+    } else {
+        let others: Vec<String> = candidates
+            .iter()
+            .filter(|candidate| candidate.3 > 0.0)
+            .map(|candidate| format!("{} {:.0}%", delimiter_name(candidate.0), candidate.3 * 100.0))
+            .collect();
+        let detected = if others.is_empty() {
+            delimiter_name(delimiter)
+        } else {
+            format!("{} (also fits: {})", delimiter_name(delimiter), others.join(", "))
+        };
+        findings.push(Finding::new("Delimiter", detected, score * (1.0 - runner_up / 2.0)));
+        findings.push(Finding::new("Columns", columns.to_string(), score));
+        findings.push(sniff_quoting(&text, delimiter));
+        let header = sniff_header(&records);
+        let headerless = header.detected.starts_with("no");
+        findings.push(header);
+        hint = match (read_as, delimiter) {
+            (read, found) if read == found => format!("{}, which matches", hint),
+            (_, b'\t') => format!("{}, but it looks tab-separated: pass --format tsv", hint),
+            (_, b',') => format!("{}, but it looks comma-separated: pass --format csv", hint),
+            (_, found) => format!(
+                "{}, but it looks separated by {}, which pcsv can't read",
+                hint,
+                delimiter_name(found)
+            ),
+        };
+        if headerless {
+            hint.push_str("; it takes the first row as the header either way");
+        }
+    }
+    findings.push(sniff_line_endings(&text));
+    Ok(Report { findings, hint })
+}
+
+fn create_table(report: &Report, scheme: &ColorScheme) -> Table {
+    let mut table = new_table();
+    table.load_preset(UTF8_FULL);
+    table.set_header(
+        ["Property", "Detected", "Confidence"]
+            .iter()
+            .map(|name| Cell::new(name).fg(scheme.header_color())),
+    );
+    for finding in &report.findings {
+        table.add_row(vec![
+            Cell::new(finding.property).fg(scheme.header_color()),
+            Cell::new(&finding.detected).fg(scheme.cell_color(&DataType::Text)),
+            Cell::new(format!("{:.0}%", finding.confidence * 100.0)).fg(scheme.cell_color(&DataType::FloatNumber)),
+        ]);
+    }
+    table
+}
+
+// Reports how the file seems to be laid out, with how sure each guess is,
+// to check before trusting a big conversion
+pub fn run(args: &SniffArgs, options: &ReadOptions, scheme: &ColorScheme) -> Result<(), Box<dyn std::error::Error>> {
+    let report = sniff(&args.input, options)?;
+    match args.output {
+        SniffFormat::Table => {
+            println!("{}", create_table(&report, scheme));
+            println!("{}", report.hint);
+        }
+        SniffFormat::Json => println!("{}", serde_json::to_string_pretty(&report)?),
+    }
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use crate::store::Field;
+use crate::{detect_builtin_type, plain_amount, plain_number, DataType};
+use std::cmp::Ordering;
+
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub enum SortKey {
+    Number(f64),
+    Date([u32; 6]),
+    Text(String),
+    Empty,
+}
+
+// Splits a date matched by the detection patterns into
+// (year, month, day, hour, minute, second)
+pub fn date_key(val: &str) -> Option<[u32; 6]> {
+    let parts: Vec<&str> = val
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .collect();
+    if parts.len() < 3 {
+        return None;
+    }
+
+    let nums: Vec<u32> = parts.iter().filter_map(|part| part.parse().ok()).collect();
+    let mut key = [0; 6];
+    if parts[0].len() == 4 {
+        key[..3].copy_from_slice(&nums[..3]);
+    } else {
+        key[0] = nums[2];
+        key[1] = nums[0];
+        key[2] = nums[1];
+    }
+    for (slot, num) in key[3..].iter_mut().zip(nums.iter().skip(3)) {
+        *slot = *num;
+    }
+    Some(key)
+}
+
+pub fn sort_key(val: &str) -> SortKey {
+    match detect_builtin_type(val) {
+        DataType::Empty => SortKey::Empty,
+        DataType::IntNumber | DataType::FloatNumber => plain_number(val)
+            .parse::<f64>()
+            .map(SortKey::Number)
+            .unwrap_or_else(|_| SortKey::Text(val.to_string())),
+        DataType::Currency => plain_amount(val)
+            .and_then(|amount| amount.parse::<f64>().ok())
+            .map(SortKey::Number)
+            .unwrap_or_else(|| SortKey::Text(val.to_string())),
+        DataType::Date => date_key(val)
+            .map(SortKey::Date)
+            .unwrap_or_else(|| SortKey::Text(val.to_string())),
+        DataType::Boolean
+        | DataType::Text
+        | DataType::Url
+        | DataType::Email
+        | DataType::Uuid
+        | DataType::Network
+        | DataType::Custom(_) => SortKey::Text(val.to_lowercase()),
+    }
+}
+
+pub fn compare_keys(a: &SortKey, b: &SortKey, descending: bool) -> Ordering {
+    // Empty cells stay at the bottom regardless of direction
+    match (a, b) {
+        (SortKey::Empty, SortKey::Empty) => Ordering::Equal,
+        (SortKey::Empty, _) => Ordering::Greater,
+        (_, SortKey::Empty) => Ordering::Less,
+        _ => {
+            let ord = a.partial_cmp(b).unwrap_or(Ordering::Equal);
+            if descending {
+                ord.reverse()
+            } else {
+                ord
+            }
+        }
+    }
+}
+
+pub fn sort_records(records: Vec<Vec<Field>>, column: usize, descending: bool) -> Vec<Vec<Field>> {
+    let mut keyed: Vec<(SortKey, Vec<Field>)> = records
+        .into_iter()
+        .map(|record| {
+            let key = record.get(column).map_or(SortKey::Empty, |val| sort_key(val));
+            (key, record)
+        })
+        .collect();
+    keyed.sort_by(|a, b| compare_keys(&a.0, &b.0, descending));
+    keyed.into_iter().map(|(_, record)| record).collect()
+}
+
+// The first record index of every run of equal values in a sorted column,
+// labelled `column=value`
+pub fn group_starts(records: &[Vec<Field>], column: usize, name: &str) -> Vec<(String, usize)> {
+    let mut starts: Vec<(String, usize)> = Vec::new();
+    let mut previous: Option<&str> = None;
+    for (idx, record) in records.iter().enumerate() {
+        let val = record.get(column).map_or("", |val| val.as_str());
+        if previous != Some(val) {
+            let shown = if val.trim().is_empty() {
+                "(empty)"
+            } else {
+                val
+            };
+            starts.push((format!("{}={}", name, shown), idx));
+            previous = Some(val);
+        }
+    }
+    starts
+}
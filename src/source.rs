@@ -0,0 +1,92 @@
+use std::fs;
+use std::io::{self, Read};
+use std::ops::Deref;
+#[cfg(feature = "mmap")]
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Files smaller than this are simply read, mapping them isn't worth it
+#[cfg(feature = "mmap")]
+const MMAP_MIN_LEN: u64 = 1 << 20;
+
+// Cleared for the pager, which keeps cells for as long as it runs
+#[cfg(feature = "mmap")]
+static MAP_FILES: AtomicBool = AtomicBool::new(true);
+
+// Reads files into memory from now on. Cells point into the file they came
+// from, and a mapped file truncated by another process while they are
+// still shown would crash the reader rather than just look stale.
+pub fn read_into_memory() {
+    #[cfg(feature = "mmap")]
+    MAP_FILES.store(false, Ordering::Relaxed);
+}
+
+// The raw bytes of an input: standard input, a small file or decompressed
+// data in memory, or a large file mapped straight from disk
+pub enum Source {
+    Owned(Vec<u8>),
+    #[cfg(feature = "mmap")]
+    Mapped(memmap2::Mmap),
+}
+
+impl Deref for Source {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Source::Owned(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            Source::Mapped(map) => map,
+        }
+    }
+}
+
+impl Source {
+    // For readers that need to own their input
+    pub fn into_vec(self) -> Vec<u8> {
+        match self {
+            Source::Owned(bytes) => bytes,
+            #[cfg(feature = "mmap")]
+            Source::Mapped(map) => map.to_vec(),
+        }
+    }
+}
+
+// An `ssh://`, `sftp://`, `s3://` or `gs://` URL rather than a local path
+pub fn is_remote(input: &str) -> bool {
+    ["ssh://", "sftp://", "s3://", "gs://"]
+        .iter()
+        .any(|scheme| input.starts_with(scheme))
+}
+
+pub fn open(input: &str) -> io::Result<Source> {
+    if is_remote(input) {
+        return crate::remote::open(input);
+    }
+    if input == "-" {
+        let mut buffer = Vec::new();
+        io::stdin().read_to_end(&mut buffer)?;
+        return Ok(Source::Owned(buffer));
+    }
+    open_file(input)
+}
+
+#[cfg(feature = "mmap")]
+fn open_file(path: &str) -> io::Result<Source> {
+    let file = fs::File::open(path)?;
+    if file.metadata()?.len() < MMAP_MIN_LEN || !MAP_FILES.load(Ordering::Relaxed) {
+        return fs::read(path).map(Source::Owned);
+    }
+    // SAFETY: the map is only read while the file is parsed and its cells
+    // printed. A file truncated by another process during that time can
+    // still fault, as with any memory-mapped reader.
+    match unsafe { memmap2::Mmap::map(&file) } {
+        Ok(map) => Ok(Source::Mapped(map)),
+        // Some file systems can't be mapped
+        Err(_) => fs::read(path).map(Source::Owned),
+    }
+}
+
+#[cfg(not(feature = "mmap"))]
+fn open_file(path: &str) -> io::Result<Source> {
+    fs::read(path).map(Source::Owned)
+}
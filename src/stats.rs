@@ -0,0 +1,558 @@
+use crate::config::ColorScheme;
+use crate::input::ReadOptions;
+use crate::sort::date_key;
+use crate::store::Field;
+use crate::timeline::{create_timeline_table, TimeUnit, TimelineEntry};
+use crate::{detect_builtin_type, detect_data_type_cached, is_null, new_table, plain_amount, plain_number, DataType};
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, Table};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Serialize)]
+pub struct ColumnProfile {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: &'static str,
+    pub count: usize,
+    pub nulls: usize,
+    pub cardinality: usize,
+    pub min: Option<String>,
+    pub max: Option<String>,
+    pub mean: Option<f64>,
+    pub median: Option<f64>,
+    pub std_dev: Option<f64>,
+    pub earliest: Option<String>,
+    pub latest: Option<String>,
+    pub entropy: f64,
+    pub min_length: usize,
+    pub avg_length: f64,
+    pub max_length: usize,
+    pub likely_key: bool,
+}
+
+pub fn column_names(headers: Option<&[String]>, records: &[Vec<Field>]) -> Vec<String> {
+    match headers {
+        Some(h) => h.to_vec(),
+        None => {
+            let width = records.iter().map(|r| r.len()).max().unwrap_or(0);
+            (1..=width).map(|idx| format!("column {}", idx)).collect()
+        }
+    }
+}
+
+fn profile_column(name: &str, values: &[&str]) -> ColumnProfile {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for val in values {
+        *counts.entry(val).or_insert(0) += 1;
+    }
+
+    // Shannon entropy (bits) of the value distribution
+    let total = values.len() as f64;
+    let entropy = counts
+        .values()
+        .map(|&count| {
+            let p = count as f64 / total;
+            -p * p.log2()
+        })
+        .sum::<f64>()
+        .max(0.0);
+
+    let lengths: Vec<usize> = values.iter().map(|val| val.chars().count()).collect();
+    let nulls = values.iter().filter(|val| is_null(val)).count();
+    let data_type = dominant_type(values);
+    let present: Vec<&str> = values
+        .iter()
+        .copied()
+        .filter(|val| !is_null(val))
+        .collect();
+
+    let mut profile = ColumnProfile {
+        name: name.to_string(),
+        data_type: data_type.name(),
+        count: present.len(),
+        nulls,
+        cardinality: counts.len(),
+        min: None,
+        max: None,
+        mean: None,
+        median: None,
+        std_dev: None,
+        earliest: None,
+        latest: None,
+        entropy,
+        min_length: lengths.iter().copied().min().unwrap_or(0),
+        avg_length: if values.is_empty() {
+            0.0
+        } else {
+            lengths.iter().sum::<usize>() as f64 / total
+        },
+        max_length: lengths.iter().copied().max().unwrap_or(0),
+        // Every value present and unique: a candidate join key
+        likely_key: !values.is_empty() && nulls == 0 && counts.len() == values.len(),
+    };
+
+    match data_type {
+        DataType::IntNumber | DataType::FloatNumber | DataType::Currency => {
+            // Amounts are summed without their symbols
+            let mut numbers: Vec<(f64, &str)> = present
+                .iter()
+                .filter_map(|val| {
+                    let plain = match data_type {
+                        DataType::Currency => plain_amount(val)?,
+                        _ => plain_number(val).into_owned(),
+                    };
+                    plain.parse::<f64>().ok().map(|num| (num, *val))
+                })
+                .collect();
+            numbers.sort_by(|a, b| a.0.total_cmp(&b.0));
+            if let (Some(first), Some(last)) = (numbers.first(), numbers.last()) {
+                let n = numbers.len() as f64;
+                let mean = numbers.iter().map(|(num, _)| num).sum::<f64>() / n;
+                let mid = numbers.len() / 2;
+                let median = if numbers.len().is_multiple_of(2) {
+                    (numbers[mid - 1].0 + numbers[mid].0) / 2.0
+                } else {
+                    numbers[mid].0
+                };
+                // Sample standard deviation
+                let variance = if numbers.len() > 1 {
+                    numbers
+                        .iter()
+                        .map(|(num, _)| (num - mean).powi(2))
+                        .sum::<f64>()
+                        / (n - 1.0)
+                } else {
+                    0.0
+                };
+                profile.min = Some(first.1.to_string());
+                profile.max = Some(last.1.to_string());
+                profile.mean = Some(mean);
+                profile.median = Some(median);
+                profile.std_dev = Some(variance.sqrt());
+            }
+        }
+        DataType::Date => {
+            let dates = present
+                .iter()
+                .filter_map(|val| date_key(val).map(|key| (key, *val)));
+            profile.earliest = dates.clone().min().map(|(_, val)| val.to_string());
+            profile.latest = dates.max().map(|(_, val)| val.to_string());
+        }
+        _ => {}
+    }
+
+    profile
+}
+
+pub fn profile_columns(headers: Option<&[String]>, records: &[Vec<Field>]) -> Vec<ColumnProfile> {
+    column_names(headers, records)
+        .iter()
+        .enumerate()
+        .map(|(idx, name)| profile_column(name, &column_values(records, idx)))
+        .collect()
+}
+
+#[derive(Serialize)]
+pub struct SchemaEntry {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub data_type: &'static str,
+    pub nullable: bool,
+}
+
+#[derive(Serialize)]
+pub struct MissingEntry {
+    pub name: String,
+    pub missing: usize,
+    pub percent: f64,
+}
+
+pub fn column_values(records: &[Vec<Field>], idx: usize) -> Vec<&str> {
+    records
+        .iter()
+        .map(|record| record.get(idx).map_or("", |val| val.as_str()))
+        .collect()
+}
+
+// The narrowest type that covers every non-empty value
+pub fn dominant_type(values: &[&str]) -> DataType {
+    let mut dominant = DataType::Empty;
+    for val in values {
+        let ty = detect_builtin_type(val);
+        dominant = match (dominant, ty) {
+            (_, DataType::Empty) => dominant,
+            (DataType::Empty, _) => ty,
+            (a, b) if a == b => a,
+            (DataType::IntNumber, DataType::FloatNumber)
+            | (DataType::FloatNumber, DataType::IntNumber) => DataType::FloatNumber,
+            _ => return DataType::Text,
+        };
+    }
+    dominant
+}
+
+pub fn infer_schema(headers: Option<&[String]>, records: &[Vec<Field>]) -> Vec<SchemaEntry> {
+    column_names(headers, records)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let values = column_values(records, idx);
+            SchemaEntry {
+                name,
+                data_type: dominant_type(&values).name(),
+                nullable: values.iter().any(|val| is_null(val)),
+            }
+        })
+        .collect()
+}
+
+pub fn count_missing(headers: Option<&[String]>, records: &[Vec<Field>]) -> Vec<MissingEntry> {
+    column_names(headers, records)
+        .into_iter()
+        .enumerate()
+        .map(|(idx, name)| {
+            let missing = column_values(records, idx)
+                .iter()
+                .filter(|val| is_null(val))
+                .count();
+            MissingEntry {
+                name,
+                missing,
+                percent: if records.is_empty() {
+                    0.0
+                } else {
+                    missing as f64 * 100.0 / records.len() as f64
+                },
+            }
+        })
+        .collect()
+}
+
+#[derive(Serialize)]
+pub struct FrequencyEntry {
+    pub value: String,
+    pub count: usize,
+    pub percent: f64,
+}
+
+// Distinct values of a column, most frequent first
+pub fn count_frequencies(records: &[Vec<Field>], column: usize) -> Vec<FrequencyEntry> {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for val in column_values(records, column) {
+        *counts.entry(val).or_insert(0) += 1;
+    }
+
+    let mut entries: Vec<FrequencyEntry> = counts
+        .into_iter()
+        .map(|(value, count)| FrequencyEntry {
+            value: value.to_string(),
+            count,
+            percent: count as f64 * 100.0 / records.len() as f64,
+        })
+        .collect();
+    entries.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    entries
+}
+
+pub enum Report {
+    Stats(Vec<ColumnProfile>),
+    Schema(Vec<SchemaEntry>),
+    Missing(Vec<MissingEntry>),
+    Frequency {
+        entries: Vec<FrequencyEntry>,
+        bars: bool,
+    },
+    Timeline {
+        entries: Vec<TimelineEntry>,
+        unit: TimeUnit,
+    },
+}
+
+impl Report {
+    pub fn to_table(&self, scheme: &ColorScheme) -> Table {
+        match self {
+            Report::Stats(profiles) => create_stats_table(profiles, scheme),
+            Report::Schema(entries) => create_schema_table(entries, scheme),
+            Report::Missing(entries) => create_missing_table(entries, scheme),
+            Report::Frequency { entries, bars } => create_frequency_table(entries, *bars, scheme),
+            Report::Timeline { entries, unit } => create_timeline_table(entries, *unit, scheme),
+        }
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<String> {
+        match self {
+            Report::Stats(profiles) => serde_json::to_string_pretty(profiles),
+            Report::Schema(entries) => serde_json::to_string_pretty(entries),
+            Report::Missing(entries) => serde_json::to_string_pretty(entries),
+            Report::Frequency { entries, .. } => serde_json::to_string_pretty(entries),
+            Report::Timeline { entries, .. } => serde_json::to_string_pretty(entries),
+        }
+    }
+}
+
+pub fn report_table(columns: &[&str], scheme: &ColorScheme) -> Table {
+    let mut table = new_table();
+    table.load_preset(UTF8_FULL);
+    table.set_header(
+        columns
+            .iter()
+            .map(|name| Cell::new(name).fg(scheme.header_color())),
+    );
+    table
+}
+
+fn create_schema_table(entries: &[SchemaEntry], scheme: &ColorScheme) -> Table {
+    let mut table = report_table(&["Column", "Type", "Nullable"], scheme);
+    for entry in entries {
+        table.add_row(vec![
+            Cell::new(&entry.name).fg(scheme.header_color()),
+            Cell::new(entry.data_type).fg(scheme.cell_color(&DataType::Text)),
+            Cell::new(if entry.nullable { "yes" } else { "no" })
+                .fg(scheme.cell_color(&DataType::Boolean)),
+        ]);
+    }
+    table
+}
+
+fn create_missing_table(entries: &[MissingEntry], scheme: &ColorScheme) -> Table {
+    let mut table = report_table(&["Column", "Missing", "Percent"], scheme);
+    for entry in entries {
+        table.add_row(vec![
+            Cell::new(&entry.name).fg(scheme.header_color()),
+            Cell::new(entry.missing).fg(scheme.cell_color(&DataType::IntNumber)),
+            Cell::new(format!("{:.1}%", entry.percent))
+                .fg(scheme.cell_color(&DataType::FloatNumber)),
+        ]);
+    }
+    table
+}
+
+pub const BAR_WIDTH: usize = 30;
+
+fn create_frequency_table(entries: &[FrequencyEntry], bars: bool, scheme: &ColorScheme) -> Table {
+    let columns: &[&str] = if bars {
+        &["Value", "Count", "Percent", ""]
+    } else {
+        &["Value", "Count", "Percent"]
+    };
+    let mut table = report_table(columns, scheme);
+    let max_count = entries.first().map_or(1, |entry| entry.count.max(1));
+
+    for entry in entries {
+        let shown = if entry.value.trim().is_empty() {
+            "(empty)"
+        } else {
+            entry.value.as_str()
+        };
+        let mut row = vec![
+            Cell::new(shown).fg(scheme.cell_color(&detect_data_type_cached(&entry.value))),
+            Cell::new(entry.count).fg(scheme.cell_color(&DataType::IntNumber)),
+            Cell::new(format!("{:.1}%", entry.percent))
+                .fg(scheme.cell_color(&DataType::FloatNumber)),
+        ];
+        if bars {
+            let len = (entry.count * BAR_WIDTH).div_ceil(max_count);
+            row.push(Cell::new("█".repeat(len)).fg(scheme.header_color()));
+        }
+        table.add_row(row);
+    }
+    table
+}
+
+fn create_stats_table(profiles: &[ColumnProfile], scheme: &ColorScheme) -> Table {
+    let mut table = report_table(
+        &[
+            "Column",
+            "Type",
+            "Count",
+            "Nulls",
+            "Cardinality",
+            "Min / Earliest",
+            "Max / Latest",
+            "Mean",
+            "Median",
+            "Std dev",
+            "Entropy",
+            "Min len",
+            "Avg len",
+            "Max len",
+            "Likely key",
+        ],
+        scheme,
+    );
+
+    let int_color = scheme.cell_color(&DataType::IntNumber);
+    let float_color = scheme.cell_color(&DataType::FloatNumber);
+    // Values keep the color of their own type; missing stats are dimmed
+    let value_cell = |val: Option<&str>| match val {
+        Some(val) => Cell::new(val).fg(scheme.cell_color(&detect_data_type_cached(val))),
+        None => Cell::new("-").fg(scheme.cell_color(&DataType::Empty)),
+    };
+    let float_cell = |val: Option<f64>| match val {
+        Some(val) => Cell::new(format!("{:.3}", val)).fg(float_color),
+        None => Cell::new("-").fg(scheme.cell_color(&DataType::Empty)),
+    };
+    for profile in profiles {
+        let low = profile.min.as_deref().or(profile.earliest.as_deref());
+        let high = profile.max.as_deref().or(profile.latest.as_deref());
+        table.add_row(vec![
+            Cell::new(&profile.name).fg(scheme.header_color()),
+            Cell::new(profile.data_type).fg(scheme.cell_color(&DataType::Text)),
+            Cell::new(profile.count).fg(int_color),
+            Cell::new(profile.nulls).fg(int_color),
+            Cell::new(profile.cardinality).fg(int_color),
+            value_cell(low),
+            value_cell(high),
+            float_cell(profile.mean),
+            float_cell(profile.median),
+            float_cell(profile.std_dev),
+            Cell::new(format!("{:.3}", profile.entropy)).fg(float_color),
+            Cell::new(profile.min_length).fg(int_color),
+            Cell::new(format!("{:.1}", profile.avg_length)).fg(float_color),
+            Cell::new(profile.max_length).fg(int_color),
+            Cell::new(if profile.likely_key { "yes" } else { "no" })
+                .fg(scheme.cell_color(&DataType::Boolean)),
+        ]);
+    }
+
+    table
+}
+
+#[derive(clap::Args)]
+pub struct StatsArgs {
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+
+    #[arg(long)]
+    pub compare: bool,
+}
+
+struct FileProfile {
+    path: String,
+    rows: usize,
+    schema: Vec<SchemaEntry>,
+    missing: Vec<MissingEntry>,
+    profiles: Vec<ColumnProfile>,
+}
+
+fn profile_file(path: &str, options: &ReadOptions) -> Result<FileProfile, String> {
+    let (headers, records) =
+        crate::read_csv_data(path, options).map_err(|err| format!("{}: {}", path, err))?;
+    Ok(FileProfile {
+        path: path.to_string(),
+        rows: records.len(),
+        schema: infer_schema(headers.as_deref(), &records),
+        missing: count_missing(headers.as_deref(), &records),
+        profiles: profile_columns(headers.as_deref(), &records),
+    })
+}
+
+// Differences of a schema against the reference (first) file
+fn schema_drift(reference: &[SchemaEntry], schema: &[SchemaEntry]) -> Vec<String> {
+    let mut drift = Vec::new();
+    for entry in schema {
+        match reference.iter().find(|other| other.name == entry.name) {
+            None => drift.push(format!("+{}", entry.name)),
+            Some(other) if other.data_type != entry.data_type => drift.push(format!(
+                "{}: {} -> {}",
+                entry.name, other.data_type, entry.data_type
+            )),
+            Some(_) => {}
+        }
+    }
+    for entry in reference {
+        if !schema.iter().any(|other| other.name == entry.name) {
+            drift.push(format!("-{}", entry.name));
+        }
+    }
+    drift
+}
+
+fn create_comparison_tables(files: &[FileProfile], scheme: &ColorScheme) -> (Table, Table) {
+    let int_color = scheme.cell_color(&DataType::IntNumber);
+    let float_color = scheme.cell_color(&DataType::FloatNumber);
+    let empty_color = scheme.cell_color(&DataType::Empty);
+
+    let mut summary = report_table(
+        &["File", "Rows", "Columns", "Null rate", "Schema drift"],
+        scheme,
+    );
+    for file in files {
+        let cells = file.rows * file.schema.len();
+        let nulls: usize = file.missing.iter().map(|entry| entry.missing).sum();
+        let null_rate = if cells == 0 {
+            0.0
+        } else {
+            nulls as f64 * 100.0 / cells as f64
+        };
+        let drift = schema_drift(&files[0].schema, &file.schema);
+        summary.add_row(vec![
+            Cell::new(&file.path).fg(scheme.header_color()),
+            Cell::new(file.rows).fg(int_color),
+            Cell::new(file.schema.len()).fg(int_color),
+            Cell::new(format!("{:.1}%", null_rate)).fg(float_color),
+            if drift.is_empty() {
+                Cell::new("-").fg(empty_color)
+            } else {
+                Cell::new(drift.join("\n")).fg(scheme.cell_color(&DataType::Text))
+            },
+        ]);
+    }
+
+    // Null rate of every column (union over all files) per file
+    let mut columns: Vec<&str> = Vec::new();
+    for file in files {
+        for entry in &file.missing {
+            if !columns.contains(&entry.name.as_str()) {
+                columns.push(&entry.name);
+            }
+        }
+    }
+    let mut matrix = report_table(
+        &std::iter::once("File")
+            .chain(columns.iter().copied())
+            .collect::<Vec<_>>(),
+        scheme,
+    );
+    for file in files {
+        let mut row = vec![Cell::new(&file.path).fg(scheme.header_color())];
+        for column in &columns {
+            row.push(
+                match file.missing.iter().find(|entry| entry.name == *column) {
+                    Some(entry) => Cell::new(format!("{:.1}%", entry.percent)).fg(float_color),
+                    None => Cell::new("absent").fg(empty_color),
+                },
+            );
+        }
+        matrix.add_row(row);
+    }
+
+    (summary, matrix)
+}
+
+pub fn run(
+    args: &StatsArgs,
+    options: &ReadOptions,
+    scheme: &ColorScheme,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Profile the files on all cores, keeping the input order
+    let files = args
+        .inputs
+        .par_iter()
+        .map(|path| profile_file(path, options))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    if args.compare {
+        let (summary, matrix) = create_comparison_tables(&files, scheme);
+        println!("{}", summary);
+        println!("{}", matrix);
+    } else {
+        for file in &files {
+            println!("{}", file.path);
+            println!("{}", create_stats_table(&file.profiles, scheme));
+        }
+    }
+    Ok(())
+}
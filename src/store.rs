@@ -0,0 +1,284 @@
+use crate::source::Source;
+use std::borrow::Borrow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
+// The decoded text of a whole CSV file, which cells point into instead of
+// each holding a copy
+pub struct Text {
+    source: Source,
+    range: Range<usize>,
+}
+
+impl Text {
+    // `range` is the text within `source`, past any byte order mark
+    pub fn new(source: Source, range: Range<usize>) -> Result<Self, std::str::Utf8Error> {
+        std::str::from_utf8(&source[range.clone()])?;
+        Ok(Text { source, range })
+    }
+
+    pub fn as_str(&self) -> &str {
+        // SAFETY: `new` checked these bytes are UTF-8, and neither the source
+        // nor the range change afterwards
+        unsafe { std::str::from_utf8_unchecked(&self.source[self.range.clone()]) }
+    }
+}
+
+impl From<String> for Text {
+    fn from(text: String) -> Self {
+        let len = text.len();
+        Text {
+            source: Source::Owned(text.into_bytes()),
+            range: 0..len,
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Repr {
+    Shared(Arc<str>),
+    // Bytes `start..start + len` of a file's text
+    Slice {
+        text: Arc<Text>,
+        start: usize,
+        len: u32,
+    },
+}
+
+// One cell of a record, 24 bytes either way. Cells read from a CSV file
+// point into its text, so reading one doesn't allocate per cell. Values
+// repeated down a column (a status, a country code) are shared, and cells
+// that had to be unescaped or were built some other way are reference
+// counted strings.
+#[derive(Clone)]
+pub struct Field(Repr);
+
+impl Field {
+    pub fn as_str(&self) -> &str {
+        match &self.0 {
+            Repr::Shared(val) => val,
+            Repr::Slice { text, start, len } => &text.as_str()[*start..*start + *len as usize],
+        }
+    }
+
+    fn slice(text: &Arc<Text>, range: Range<usize>) -> Self {
+        Field(Repr::Slice {
+            text: Arc::clone(text),
+            start: range.start,
+            len: range.len() as u32,
+        })
+    }
+}
+
+impl Default for Field {
+    fn default() -> Self {
+        Field::from("")
+    }
+}
+
+impl Deref for Field {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Field {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<[u8]> for Field {
+    fn as_ref(&self) -> &[u8] {
+        self.as_str().as_bytes()
+    }
+}
+
+impl Borrow<str> for Field {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for Field {
+    fn from(val: &str) -> Self {
+        Field(Repr::Shared(Arc::from(val)))
+    }
+}
+
+impl From<String> for Field {
+    fn from(val: String) -> Self {
+        Field(Repr::Shared(Arc::from(val)))
+    }
+}
+
+// Compared and hashed by text, however the cell is stored, so that sets of
+// fields can be searched with a `&str`
+impl PartialEq for Field {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Field {}
+
+impl PartialEq<str> for Field {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Field {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialOrd for Field {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Field {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Hash for Field {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+// A column is no longer interned once it has this many distinct values;
+// IDs, amounts and free text would only grow the table
+const MAX_DISTINCT: usize = 4096;
+
+// Hands out one shared `Field` per distinct value of each column, for the
+// enum-ish columns where that pays off
+#[derive(Default)]
+pub struct Interner {
+    // `None` for columns that turned out to be mostly unique
+    columns: Vec<Option<HashSet<Field>>>,
+}
+
+impl Interner {
+    // The column's shared field for `val`, or failing that the one `make`
+    // builds
+    fn lookup(&mut self, column: usize, val: &str, make: impl FnOnce() -> Field) -> Field {
+        if self.columns.len() <= column {
+            self.columns
+                .resize_with(column + 1, || Some(HashSet::new()));
+        }
+        let Some(seen) = &mut self.columns[column] else {
+            return make();
+        };
+        if let Some(field) = seen.get(val) {
+            return field.clone();
+        }
+        let field = make();
+        if seen.len() < MAX_DISTINCT {
+            seen.insert(field.clone());
+        } else {
+            self.columns[column] = None;
+        }
+        field
+    }
+
+    pub fn field(&mut self, column: usize, val: &str) -> Field {
+        self.lookup(column, val, || Field::from(val))
+    }
+
+    pub fn record(&mut self, cells: impl IntoIterator<Item = impl AsRef<str>>) -> Vec<Field> {
+        cells
+            .into_iter()
+            .enumerate()
+            .map(|(column, val)| self.field(column, val.as_ref()))
+            .collect()
+    }
+
+    // A record a parser read from `text`, starting at byte `start`. Fields
+    // found there as they are become slices of the text; quoted ones, which
+    // the parser unescaped, are copied.
+    pub fn record_in<'a>(
+        &mut self,
+        text: &Arc<Text>,
+        start: usize,
+        fields: impl IntoIterator<Item = &'a [u8]>,
+        delimiter: u8,
+    ) -> Vec<Field> {
+        let bytes = text.as_str().as_bytes();
+        // Parsers skip blank lines before a record
+        let mut cursor = bytes
+            .get(start..)
+            .and_then(|rest| rest.iter().position(|&b| b != b'\n' && b != b'\r'))
+            .map(|skipped| start + skipped);
+        fields
+            .into_iter()
+            .enumerate()
+            .map(|(column, field)| {
+                let Some(at) = cursor.take() else {
+                    return self.field(column, &String::from_utf8_lossy(field));
+                };
+                let raw = &bytes[at..];
+                let ends_field = |len: usize| match raw.get(len) {
+                    None | Some(b'\n' | b'\r') => true,
+                    Some(&byte) => byte == delimiter,
+                };
+                let verbatim = raw.first() != Some(&b'"')
+                    && raw.starts_with(field)
+                    && ends_field(field.len())
+                    && field.len() <= u32::MAX as usize;
+                if verbatim {
+                    cursor = Some(at + field.len() + 1);
+                    let range = at..at + field.len();
+                    return self.lookup(column, &text.as_str()[range.clone()], || {
+                        Field::slice(text, range)
+                    });
+                }
+                // Anything but a quoted field loses track of where the rest
+                // of the record is, and it is copied
+                cursor = quoted_len(raw)
+                    .filter(|&len| ends_field(len))
+                    .map(|len| at + len + 1);
+                self.field(column, &String::from_utf8_lossy(field))
+            })
+            .collect()
+    }
+}
+
+// The length of the quoted field at the start of `raw`, quotes included
+fn quoted_len(raw: &[u8]) -> Option<usize> {
+    if raw.first() != Some(&b'"') {
+        return None;
+    }
+    let mut idx = 1;
+    while idx < raw.len() {
+        match (raw[idx], raw.get(idx + 1)) {
+            (b'"', Some(b'"')) => idx += 2,
+            (b'"', _) => return Some(idx + 1),
+            _ => idx += 1,
+        }
+    }
+    None
+}
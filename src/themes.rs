@@ -0,0 +1,134 @@
+use crate::config::{expand_home, user_config_dir, ColorScheme};
+use std::borrow::Cow;
+use std::fs;
+use std::path::PathBuf;
+
+// The color schemes built into pcsv, chosen with `--theme` or
+// `theme = "..."` in the config file. Each is written the way a config
+// file would set it, and the file's own colors go on top of it.
+pub const THEMES: &[(&str, &str)] = &[
+    ("catppuccin", CATPPUCCIN),
+    ("dracula", DRACULA),
+    ("gruvbox", GRUVBOX),
+    ("nord", NORD),
+    ("solarized-dark", SOLARIZED_DARK),
+    ("solarized-light", SOLARIZED_LIGHT),
+];
+
+// Catppuccin Mocha
+const CATPPUCCIN: &str = r##"
+header = "#CBA6F7"
+status_bar = "#45475A"
+background = "#1E1E2E"
+data_types = { text = "#CDD6F4", date = "#FAB387", float_number = "#89B4FA", int_number = "#A6E3A1", boolean = "#F9E2AF", empty = "#585B70" }
+diff = { added = "#A6E3A1", removed = "#F38BA8", changed = "#F9E2AF" }
+"##;
+
+const DRACULA: &str = r##"
+header = "#BD93F9"
+status_bar = "#44475A"
+background = "#282A36"
+data_types = { text = "#F8F8F2", date = "#FFB86C", float_number = "#8BE9FD", int_number = "#50FA7B", boolean = "#F1FA8C", empty = "#6272A4" }
+diff = { added = "#50FA7B", removed = "#FF5555", changed = "#F1FA8C" }
+"##;
+
+// Gruvbox dark
+const GRUVBOX: &str = r##"
+header = "#D3869B"
+status_bar = "#3C3836"
+background = "#282828"
+data_types = { text = "#EBDBB2", date = "#FE8019", float_number = "#B8BB26", int_number = "#83A598", boolean = "#FABD2F", empty = "#665C54" }
+diff = { added = "#B8BB26", removed = "#FB4934", changed = "#FABD2F" }
+"##;
+
+const NORD: &str = r##"
+header = "#81A1C1"
+status_bar = "#3B4252"
+background = "#2E3440"
+data_types = { text = "#D8DEE9", date = "#D08770", float_number = "#88C0D0", int_number = "#A3BE8C", boolean = "#EBCB8B", empty = "#4C566A" }
+diff = { added = "#A3BE8C", removed = "#BF616A", changed = "#EBCB8B" }
+"##;
+
+const SOLARIZED_DARK: &str = r##"
+header = "#6C71C4"
+status_bar = "#073642"
+background = "#002B36"
+data_types = { text = "#839496", date = "#CB4B16", float_number = "#268BD2", int_number = "#859900", boolean = "#B58900", empty = "#586E75" }
+diff = { added = "#859900", removed = "#DC322F", changed = "#B58900" }
+"##;
+
+const SOLARIZED_LIGHT: &str = r##"
+header = "#6C71C4"
+status_bar = "#EEE8D5"
+background = "#FDF6E3"
+data_types = { text = "#657B83", date = "#CB4B16", float_number = "#268BD2", int_number = "#859900", boolean = "#B58900", empty = "#93A1A1" }
+diff = { added = "#859900", removed = "#DC322F", changed = "#B58900" }
+"##;
+
+// Where the user's own themes are kept, one `NAME.toml` each, written
+// like a config file
+pub fn user_dir() -> PathBuf {
+    user_config_dir()
+        .map(|dir| dir.join("themes"))
+        .unwrap_or_else(|| PathBuf::from(expand_home("~/.config/pcsv/themes")))
+}
+
+// The user's themes by name, sorted
+pub fn user_themes() -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(user_dir()) else {
+        return Vec::new();
+    };
+    let mut themes: Vec<(String, PathBuf)> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            if path.extension()? != "toml" {
+                return None;
+            }
+            Some((path.file_stem()?.to_string_lossy().into_owned(), path))
+        })
+        .collect();
+    themes.sort();
+    themes
+}
+
+// The colors of a theme, as they would be written in a config file. The
+// user's own come first, so one can take the place of a built-in theme.
+pub fn source(name: &str) -> Option<Cow<'static, str>> {
+    let user = user_themes()
+        .into_iter()
+        .find(|(theme, _)| theme.eq_ignore_ascii_case(name))
+        .and_then(|(_, path)| fs::read_to_string(path).ok());
+    if let Some(source) = user {
+        return Some(Cow::Owned(source));
+    }
+    THEMES
+        .iter()
+        .find(|(theme, _)| theme.eq_ignore_ascii_case(name))
+        .map(|(_, source)| Cow::Borrowed(*source))
+}
+
+// A theme's colors, unless it is one of the user's that isn't a valid
+// config file or has a color that isn't #RRGGBB. Colors it leaves out are
+// pcsv's own.
+pub fn load(name: &str) -> Option<Result<ColorScheme, String>> {
+    source(name).map(|source| {
+        let scheme: ColorScheme = toml::from_str(&source).map_err(|e: toml::de::Error| e.message().to_string())?;
+        scheme.check_colors()?;
+        Ok(scheme)
+    })
+}
+
+pub fn theme(name: &str) -> Option<ColorScheme> {
+    load(name)?.ok()
+}
+
+pub fn parse_theme(name: &str) -> Result<String, String> {
+    match source(name) {
+        Some(_) => Ok(name.to_string()),
+        None => {
+            let mut names: Vec<String> = THEMES.iter().map(|(theme, _)| theme.to_string()).collect();
+            names.extend(user_themes().into_iter().map(|(theme, _)| theme));
+            Err(format!("unknown theme '{}', expected one of {}", name, names.join(", ")))
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::config::ColorScheme;
+use crate::sort::date_key;
+use crate::stats::{column_values, report_table, BAR_WIDTH};
+use crate::store::Field;
+use crate::{detect_builtin_type, DataType};
+use comfy_table::{Cell, Table};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+// More buckets than this is a wall of zeros, and a unit too fine
+const MAX_BUCKETS: i64 = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeUnit {
+    Hour,
+    Day,
+    // Starting on Monday
+    Week,
+    Month,
+    Year,
+}
+
+impl TimeUnit {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "hour" => Some(TimeUnit::Hour),
+            "day" => Some(TimeUnit::Day),
+            "week" => Some(TimeUnit::Week),
+            "month" => Some(TimeUnit::Month),
+            "year" => Some(TimeUnit::Year),
+            _ => None,
+        }
+    }
+
+    fn heading(self) -> &'static str {
+        match self {
+            TimeUnit::Hour => "Hour",
+            TimeUnit::Day => "Day",
+            TimeUnit::Week => "Week of",
+            TimeUnit::Month => "Month",
+            TimeUnit::Year => "Year",
+        }
+    }
+
+    fn coarser(self) -> Option<&'static str> {
+        match self {
+            TimeUnit::Hour => Some("day"),
+            TimeUnit::Day => Some("week"),
+            TimeUnit::Week => Some("month"),
+            TimeUnit::Month => Some("year"),
+            TimeUnit::Year => None,
+        }
+    }
+
+    // Consecutive buckets are this far apart
+    fn step(self) -> i64 {
+        match self {
+            TimeUnit::Week => 7,
+            _ => 1,
+        }
+    }
+
+    // The bucket holding a (year, month, day, hour, ...) date
+    fn bucket(self, date: [u32; 6]) -> i64 {
+        let [year, month, day, hour, ..] = date;
+        let days = days_from_civil(year as i64, month as i64, day as i64);
+        match self {
+            TimeUnit::Hour => days * 24 + hour as i64,
+            TimeUnit::Day => days,
+            // 1970-01-01 was a Thursday, three days after a Monday
+            TimeUnit::Week => days - (days + 3).rem_euclid(7),
+            TimeUnit::Month => year as i64 * 12 + month as i64 - 1,
+            TimeUnit::Year => year as i64,
+        }
+    }
+
+    fn label(self, bucket: i64) -> String {
+        match self {
+            TimeUnit::Hour => {
+                let (year, month, day) = civil_from_days(bucket.div_euclid(24));
+                format!("{:04}-{:02}-{:02} {:02}:00", year, month, day, bucket.rem_euclid(24))
+            }
+            TimeUnit::Day | TimeUnit::Week => {
+                let (year, month, day) = civil_from_days(bucket);
+                format!("{:04}-{:02}-{:02}", year, month, day)
+            }
+            TimeUnit::Month => format!("{:04}-{:02}", bucket.div_euclid(12), bucket.rem_euclid(12) + 1),
+            TimeUnit::Year => format!("{:04}", bucket),
+        }
+    }
+}
+
+// Days since 1970-01-01 in the proleptic Gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+// A `--timeline` value, `COLUMN` or `COLUMN:UNIT`
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    pub column: String,
+    pub unit: TimeUnit,
+}
+
+pub fn parse_timeline(arg: &str) -> Result<Timeline, String> {
+    // A colon only picks the unit when one follows, so names may hold colons
+    if let Some((column, unit)) = arg.rsplit_once(':') {
+        match TimeUnit::parse(unit) {
+            Some(unit) => {
+                return Ok(Timeline {
+                    column: column.to_string(),
+                    unit,
+                })
+            }
+            None if !unit.is_empty() && unit.chars().all(|c| c.is_ascii_lowercase()) => {
+                return Err(format!(
+                    "unknown unit '{}', expected hour, day, week, month or year",
+                    unit
+                ))
+            }
+            None => {}
+        }
+    }
+    Ok(Timeline {
+        column: arg.to_string(),
+        unit: TimeUnit::Day,
+    })
+}
+
+#[derive(Serialize)]
+pub struct TimelineEntry {
+    // `None` counts the rows without a date
+    pub bucket: Option<String>,
+    pub count: usize,
+    pub percent: f64,
+}
+
+// A date as the detection patterns know it, or an ISO timestamp such as
+// `2024-06-17T14:30:00Z`
+fn date_of(val: &str) -> Option<[u32; 6]> {
+    let val = val.trim();
+    let iso = val.as_bytes().get(10) == Some(&b'T')
+        && val.get(..10).is_some_and(|day| detect_builtin_type(day) == DataType::Date);
+    if detect_builtin_type(val) != DataType::Date && !iso {
+        return None;
+    }
+    date_key(val).filter(|[_, month, day, ..]| (1..=12).contains(month) && (1..=31).contains(day))
+}
+
+// Rows per bucket from the first date to the last, with the empty buckets
+// in between, and then the rows without a date if there are any
+pub fn count_timeline(
+    records: &[Vec<Field>],
+    column: usize,
+    unit: TimeUnit,
+    name: &str,
+) -> Result<Vec<TimelineEntry>, String> {
+    let mut counts: BTreeMap<i64, usize> = BTreeMap::new();
+    let mut undated = 0;
+    for val in column_values(records, column) {
+        match date_of(val) {
+            Some(date) => *counts.entry(unit.bucket(date)).or_insert(0) += 1,
+            None => undated += 1,
+        }
+    }
+    let percent = |count: usize| count as f64 * 100.0 / records.len().max(1) as f64;
+    let mut entries = Vec::new();
+    if let (Some((&first, _)), Some((&last, _))) = (counts.first_key_value(), counts.last_key_value()) {
+        let buckets = (last - first) / unit.step() + 1;
+        if buckets > MAX_BUCKETS {
+            let hint = match unit.coarser() {
+                Some(coarser) => format!(", try '{}:{}'", name, coarser),
+                None => String::new(),
+            };
+            return Err(format!("the dates in '{}' span {} buckets{}", name, buckets, hint));
+        }
+        let mut bucket = first;
+        while bucket <= last {
+            let count = counts.get(&bucket).copied().unwrap_or(0);
+            entries.push(TimelineEntry {
+                bucket: Some(unit.label(bucket)),
+                count,
+                percent: percent(count),
+            });
+            bucket += unit.step();
+        }
+    }
+    if undated > 0 {
+        entries.push(TimelineEntry {
+            bucket: None,
+            count: undated,
+            percent: percent(undated),
+        });
+    }
+    Ok(entries)
+}
+
+pub fn create_timeline_table(entries: &[TimelineEntry], unit: TimeUnit, scheme: &ColorScheme) -> Table {
+    let mut table = report_table(&[unit.heading(), "Count", "Percent", ""], scheme);
+    let max_count = entries.iter().map(|entry| entry.count).max().unwrap_or(0).max(1);
+    for entry in entries {
+        let (label, color) = match &entry.bucket {
+            Some(bucket) => (bucket.as_str(), scheme.cell_color(&DataType::Date)),
+            None => ("(no date)", scheme.cell_color(&DataType::Empty)),
+        };
+        // Empty buckets are dimmed so the gaps stand out
+        let count_color = if entry.count == 0 {
+            scheme.cell_color(&DataType::Empty)
+        } else {
+            scheme.cell_color(&DataType::IntNumber)
+        };
+        let len = (entry.count * BAR_WIDTH).div_ceil(max_count);
+        table.add_row(vec![
+            Cell::new(label).fg(color),
+            Cell::new(entry.count).fg(count_color),
+            Cell::new(format!("{:.1}%", entry.percent)).fg(scheme.cell_color(&DataType::FloatNumber)),
+            Cell::new("█".repeat(len)).fg(scheme.header_color()),
+        ]);
+    }
+    table
+}
@@ -0,0 +1,93 @@
+use crate::config::{TruncateConfig, TruncateMode};
+use std::borrow::Cow;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+// A `--truncate` value: a mode for every column, or `COLUMN=MODE` for one
+pub fn parse_truncate(arg: &str) -> Result<(Option<String>, TruncateMode), String> {
+    let (column, name) = match arg.rsplit_once('=') {
+        Some((column, name)) => (Some(column.to_string()), name),
+        None => (None, arg),
+    };
+    let mode = TruncateMode::parse(name)
+        .ok_or_else(|| format!("unknown truncation '{}', expected start, middle or end", name))?;
+    Ok((column, mode))
+}
+
+// The mode of every field of a file, and the width its values are cut to
+#[derive(Clone, Default)]
+pub struct Truncation {
+    width: Option<usize>,
+    default: TruncateMode,
+    modes: Vec<TruncateMode>,
+}
+
+impl Truncation {
+    pub fn new(config: &TruncateConfig, names: &[String]) -> Self {
+        Truncation {
+            width: config.max_width,
+            default: config.mode,
+            modes: names
+                .iter()
+                .map(|name| config.columns.get(name).copied().unwrap_or(config.mode))
+                .collect(),
+        }
+    }
+
+    // A value cut to the maximum width, each of its lines on its own
+    pub fn cut<'a>(&self, field: usize, val: Cow<'a, str>) -> Cow<'a, str> {
+        match self.width {
+            Some(width) => self.cut_to(field, val, width),
+            None => val,
+        }
+    }
+
+    // A value cut to a width of its own, the field's mode still deciding
+    // where, for a column narrowed to fit the screen
+    pub fn cut_to<'a>(&self, field: usize, val: Cow<'a, str>, width: usize) -> Cow<'a, str> {
+        let mode = self.modes.get(field).copied().unwrap_or(self.default);
+        cut_lines(val, width, mode)
+    }
+}
+
+// Every line of a value cut to `width`, if it is wider
+pub fn cut_lines(val: Cow<'_, str>, width: usize, mode: TruncateMode) -> Cow<'_, str> {
+    if val.split('\n').all(|line| line.width() <= width) {
+        return val;
+    }
+    let lines: Vec<String> = val.split('\n').map(|line| cut_line(line, width, mode)).collect();
+    Cow::Owned(lines.join("\n"))
+}
+
+// Characters from the front of `chars` that fit in `width` columns
+fn fitting(chars: impl Iterator<Item = char>, width: usize) -> Vec<char> {
+    let mut used = 0;
+    chars
+        .take_while(|c| {
+            used += c.width().unwrap_or(0);
+            used <= width
+        })
+        .collect()
+}
+
+fn cut_line(line: &str, width: usize, mode: TruncateMode) -> String {
+    if line.width() <= width {
+        return line.to_string();
+    }
+    // Room for the ellipsis
+    let room = width.saturating_sub(1);
+    match mode {
+        TruncateMode::End => {
+            let head: String = fitting(line.chars(), room).into_iter().collect();
+            format!("{}…", head)
+        }
+        TruncateMode::Start => {
+            let tail: String = fitting(line.chars().rev(), room).into_iter().rev().collect();
+            format!("…{}", tail)
+        }
+        TruncateMode::Middle => {
+            let head: String = fitting(line.chars(), room - room / 2).into_iter().collect();
+            let tail: String = fitting(line.chars().rev(), room / 2).into_iter().rev().collect();
+            format!("{}…{}", head, tail)
+        }
+    }
+}
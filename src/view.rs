@@ -0,0 +1,137 @@
+use crate::config::ColorScheme;
+use crate::detect_data_type_cached;
+use comfy_table::presets::UTF8_FULL;
+use comfy_table::{Cell, ContentArrangement, Table};
+
+// A table of any headers and rows drawn the way pcsv prints a file, with
+// every cell colored by its detected type
+#[derive(Debug, Clone)]
+pub struct TableView {
+    headers: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+    theme: ColorScheme,
+    max_width: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TableViewBuilder {
+    headers: Option<Vec<String>>,
+    rows: Vec<Vec<String>>,
+    theme: Option<ColorScheme>,
+    max_width: Option<u16>,
+}
+
+impl TableView {
+    pub fn builder() -> TableViewBuilder {
+        TableViewBuilder::default()
+    }
+
+    /// With colors as ANSI escapes, whether or not stdout is a terminal
+    ///
+    /// ```
+    /// let table = pcsv::TableView::builder().headers(["name", "qty"]).rows([["apple", "3"]]).build();
+    /// let ansi = table.to_ansi_string();
+    /// // The header in the default header color, #CBB6F7
+    /// assert!(ansi.contains("\x1b[38;2;203;182;247m name"));
+    /// assert_ne!(ansi, table.to_plain_string());
+    /// ```
+    pub fn to_ansi_string(&self) -> String {
+        let mut table = self.table();
+        table.enforce_styling();
+        table.to_string()
+    }
+
+    /// Without any escapes, for logs and files
+    ///
+    /// ```
+    /// let table = pcsv::TableView::builder().headers(["name", "qty"]).rows([["apple", "3"]]).build();
+    /// assert_eq!(
+    ///     table.to_plain_string(),
+    ///     "┌───────┬─────┐\n\
+    ///      │ name  ┆ qty │\n\
+    ///      ╞═══════╪═════╡\n\
+    ///      │ apple ┆ 3   │\n\
+    ///      └───────┴─────┘"
+    /// );
+    /// ```
+    pub fn to_plain_string(&self) -> String {
+        let mut table = self.table();
+        table.force_no_tty();
+        table.to_string()
+    }
+
+    fn table(&self) -> Table {
+        let mut table = Table::new();
+        table.load_preset(UTF8_FULL);
+        if let Some(width) = self.max_width {
+            // Columns wrap their text to fit
+            table.set_content_arrangement(ContentArrangement::Dynamic);
+            table.set_width(width);
+        }
+        if let Some(headers) = &self.headers {
+            let color = self.theme.header_color();
+            table.set_header(headers.iter().map(|name| Cell::new(name).fg(color)));
+        }
+        for row in &self.rows {
+            table.add_row(row.iter().map(|val| {
+                let color = self.theme.cell_color(&detect_data_type_cached(val));
+                Cell::new(val).fg(color)
+            }));
+        }
+        table
+    }
+}
+
+impl TableViewBuilder {
+    pub fn headers<I, S>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.headers = Some(headers.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn rows<R, I, S>(mut self, rows: R) -> Self
+    where
+        R: IntoIterator<Item = I>,
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.rows = rows
+            .into_iter()
+            .map(|row| row.into_iter().map(Into::into).collect())
+            .collect();
+        self
+    }
+
+    // The colors to use instead of pcsv's defaults, e.g. from
+    // `config::load_config`
+    pub fn theme(mut self, theme: ColorScheme) -> Self {
+        self.theme = Some(theme);
+        self
+    }
+
+    // The widest the table may be, in terminal columns
+    pub fn max_width(mut self, width: u16) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    pub fn build(self) -> TableView {
+        TableView {
+            headers: self.headers,
+            rows: self.rows,
+            theme: self.theme.unwrap_or_default(),
+            max_width: self.max_width,
+        }
+    }
+
+    pub fn to_ansi_string(self) -> String {
+        self.build().to_ansi_string()
+    }
+
+    pub fn to_plain_string(self) -> String {
+        self.build().to_plain_string()
+    }
+}
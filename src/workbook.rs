@@ -0,0 +1,275 @@
+use crate::config::ColorScheme;
+use crate::rules::CellRules;
+use crate::stats::column_names;
+use crate::store::{Field, Interner};
+use crate::CsvData;
+use calamine::{open_workbook_auto_from_rs, Data, Reader};
+use pcsv::{detect_builtin_type, detect_data_type_cached, is_url, plain_number, DataType};
+use rust_xlsxwriter::{Format, FormatBorder, Url, Workbook, Worksheet, XlsxError};
+use std::collections::HashMap;
+use std::io::Cursor;
+
+fn cell_text(cell: &Data) -> String {
+    match cell {
+        Data::Empty => String::new(),
+        Data::String(s) | Data::DateTimeIso(s) | Data::DurationIso(s) => s.clone(),
+        // Whole numbers are stored as floats, `3` reads better than `3.0`
+        Data::Float(f) if f.fract() == 0.0 && f.abs() < 1e15 => format!("{}", *f as i64),
+        Data::Float(f) => f.to_string(),
+        Data::Int(i) => i.to_string(),
+        Data::Bool(b) => b.to_string(),
+        Data::DateTime(dt) if dt.is_datetime() => {
+            // Spelled the way date detection expects
+            let (year, month, day, hour, minute, second, _) = dt.to_ymd_hms_milli();
+            if (hour, minute, second) == (0, 0, 0) {
+                format!("{:04}-{:02}-{:02}", year, month, day)
+            } else {
+                format!(
+                    "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+                    year, month, day, hour, minute, second
+                )
+            }
+        }
+        Data::DateTime(dt) => dt.to_string(),
+        Data::Error(e) => format!("#{:?}", e),
+    }
+}
+
+// Loads one worksheet (the first unless `sheet` names another). The first
+// row is the header.
+pub fn read_sheet(
+    bytes: Vec<u8>,
+    sheet: Option<&str>,
+) -> Result<CsvData, Box<dyn std::error::Error>> {
+    let mut workbook = open_workbook_auto_from_rs(Cursor::new(bytes))?;
+    let names = workbook.sheet_names();
+    let name = match sheet {
+        Some(sheet) => names
+            .iter()
+            .find(|name| name.as_str() == sheet)
+            .ok_or_else(|| {
+                format!(
+                    "sheet '{}' not found, available: {}",
+                    sheet,
+                    names.join(", ")
+                )
+            })?,
+        None => names.first().ok_or("the workbook has no sheets")?,
+    }
+    .clone();
+    let range = workbook.worksheet_range(&name)?;
+
+    let mut rows = range
+        .rows()
+        .map(|row| row.iter().map(cell_text).collect::<Vec<String>>());
+    let headers = rows.next();
+    let mut interner = Interner::default();
+    let records = rows
+        .map(|row| interner.record(row))
+        .collect();
+    Ok((headers, records))
+}
+
+// The grid lines of HTML output
+const BORDER: &str = "#45475A";
+// Columns autofit to their values, up to about 60 characters
+const MAX_COLUMN_PIXELS: u32 = 420;
+
+// Cell formats by font color and boldness, made once each, on the
+// scheme's background
+struct Formats {
+    background: String,
+    made: HashMap<(String, bool), Format>,
+}
+
+impl Formats {
+    fn get(&mut self, color: &str, bold: bool) -> &Format {
+        let background = self.background.as_str();
+        self.made.entry((color.to_string(), bold)).or_insert_with(|| {
+            let format = Format::new()
+                .set_font_color(color)
+                .set_background_color(background)
+                .set_border(FormatBorder::Thin)
+                .set_border_color(BORDER);
+            if bold {
+                format.set_bold()
+            } else {
+                format
+            }
+        })
+    }
+}
+
+// The number a spreadsheet should hold for a value, unless turning it into
+// one would lose something: the zeros of a padded code, or the digits of an
+// ID too long for a double
+fn number(val: &str) -> Option<f64> {
+    let plain = plain_number(val);
+    let digits = plain.trim().trim_start_matches('-');
+    if digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.") {
+        return None;
+    }
+    plain.trim().parse::<f64>().ok().filter(|num| num.is_finite() && num.abs() < 1e15)
+}
+
+fn write_value(sheet: &mut Worksheet, row: u32, col: u16, val: &str, format: &Format) -> Result<(), XlsxError> {
+    // A custom type may also be a number
+    let data_type = match detect_data_type_cached(val) {
+        DataType::Custom(_) => detect_builtin_type(val),
+        ty => ty,
+    };
+    match data_type {
+        DataType::Empty => sheet.write_blank(row, col, format)?,
+        DataType::IntNumber | DataType::FloatNumber => match number(val) {
+            Some(num) => sheet.write_number_with_format(row, col, num, format)?,
+            None => sheet.write_string_with_format(row, col, val, format)?,
+        },
+        // Only `true` and `false` themselves, Excel would show `y` or `no`
+        // as TRUE and FALSE and they'd read back that way
+        DataType::Boolean => match excel_boolean(val) {
+            Some(b) => sheet.write_boolean_with_format(row, col, b, format)?,
+            None => sheet.write_string_with_format(row, col, val, format)?,
+        },
+        // Too long an address can't be a link, but it is still kept
+        DataType::Url if is_url(val) => {
+            if sheet.write_url_with_format(row, col, Url::new(val.trim()), format).is_err() {
+                sheet.write_string_with_format(row, col, val, format)?;
+            }
+            sheet
+        }
+        // Amounts and dates as written, the way JSON output keeps them
+        _ => sheet.write_string_with_format(row, col, val, format)?,
+    };
+    Ok(())
+}
+
+fn excel_boolean(val: &str) -> Option<bool> {
+    if val.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if val.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+// The view as a one-sheet workbook colored like the terminal table: the
+// header in its color and bold, each cell in its type's color or that of
+// the first rule it matches, on the background of HTML output. Numbers
+// and `true` and `false` are stored as such, so they can be summed and
+// sorted.
+pub fn write_sheet(
+    headers: Option<&[String]>,
+    records: &[Vec<Field>],
+    group_labels: Option<&[Option<String>]>,
+    scheme: &ColorScheme,
+    show_row_numbers: bool,
+) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let names = column_names(headers, records);
+    // Checked when the config was loaded
+    let rules = CellRules::new(&scheme.rules, &names).unwrap_or_default();
+    let mut formats = Formats {
+        background: scheme.background.clone(),
+        made: HashMap::new(),
+    };
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    let first_col = u16::from(show_row_numbers);
+    let mut row = 0;
+
+    if let Some(labels) = group_labels {
+        let format = formats.get(&scheme.header, true);
+        if show_row_numbers {
+            sheet.write_blank(row, 0, format)?;
+        }
+        let mut idx = 0;
+        while idx < labels.len() {
+            let span = match &labels[idx] {
+                Some(label) => labels[idx..].iter().take_while(|other| other.as_ref() == Some(label)).count(),
+                None => 1,
+            };
+            let label = labels[idx].as_deref().unwrap_or("");
+            let (first, last) = (first_col + idx as u16, first_col + (idx + span) as u16 - 1);
+            if span > 1 {
+                sheet.merge_range(row, first, row, last, label, format)?;
+            } else {
+                sheet.write_string_with_format(row, first, label, format)?;
+            }
+            idx += span;
+        }
+        row += 1;
+    }
+
+    let header_row = row;
+    if headers.is_some() {
+        let format = formats.get(&scheme.header, true);
+        if show_row_numbers {
+            sheet.write_string_with_format(row, 0, "#", format)?;
+        }
+        for (col, name) in names.iter().enumerate() {
+            sheet.write_string_with_format(row, first_col + col as u16, name, format)?;
+        }
+        row += 1;
+    }
+
+    for (idx, record) in records.iter().enumerate() {
+        if show_row_numbers {
+            sheet.write_number_with_format(row, 0, (idx + 1) as f64, formats.get(&scheme.header, false))?;
+        }
+        for (field, val) in record.iter().enumerate() {
+            let data_type = detect_data_type_cached(val);
+            let style = rules.hex_style(field, val, &data_type);
+            let color = style.and_then(|(color, _)| color).unwrap_or_else(|| scheme.cell_hex(&data_type));
+            let bold = style.is_some_and(|(_, bold)| bold);
+            write_value(sheet, row, first_col + field as u16, val, formats.get(color, bold))?;
+        }
+        row += 1;
+    }
+
+    // The header stays in view and filters its columns, as in a table
+    // made by hand
+    if headers.is_some() {
+        sheet.set_freeze_panes(header_row + 1, 0)?;
+        let last_col = (first_col as usize + names.len()).saturating_sub(1) as u16;
+        sheet.autofilter(header_row, 0, row.saturating_sub(1).max(header_row), last_col)?;
+    }
+    sheet.set_autofit_max_width(MAX_COLUMN_PIXELS).autofit();
+    Ok(workbook.save_to_buffer()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workbook_of(record: &[&str]) -> Vec<u8> {
+        let headers: Vec<String> = (0..record.len()).map(|col| format!("c{}", col)).collect();
+        let record: Vec<Field> = record.iter().map(|&val| Field::from(val)).collect();
+        write_sheet(Some(&headers), &[record], None, &ColorScheme::default(), false).unwrap()
+    }
+
+    #[test]
+    fn only_true_and_false_become_booleans() {
+        let bytes = workbook_of(&["true", "FALSE", "y", "no", "Yes"]);
+        let mut workbook = open_workbook_auto_from_rs(Cursor::new(bytes)).unwrap();
+        let range = workbook.worksheet_range_at(0).unwrap().unwrap();
+        let cells: Vec<Data> = range.rows().nth(1).unwrap().to_vec();
+        assert_eq!(
+            cells,
+            [
+                Data::Bool(true),
+                Data::Bool(false),
+                Data::String("y".to_string()),
+                Data::String("no".to_string()),
+                Data::String("Yes".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn values_read_back_as_written() {
+        let written = ["42", "3.5", "007", "y", "n", "2024-01-31", "https://example.com", ""];
+        let (_, records) = read_sheet(workbook_of(&written), None).unwrap();
+        let read: Vec<&str> = records[0].iter().map(|field| field.as_str()).collect();
+        assert_eq!(read, written);
+    }
+}
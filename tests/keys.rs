@@ -0,0 +1,73 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+const LOG: &str = "id,level,message\n1,info,started\n2,error,disk full\n3,info,retry\n4,ERROR,disk gone\n5,info,done\n";
+
+// The screen the keys end on, drawn without a terminal
+fn screen(dir: &Path, keys: &str) -> Vec<String> {
+    screen_at(dir, "100x12", keys)
+}
+
+fn screen_at(dir: &Path, size: &str, keys: &str) -> Vec<String> {
+    let output = Command::new(env!("CARGO_BIN_EXE_pcsv"))
+        .current_dir(dir)
+        .args([
+            "--plain",
+            "--no-tty",
+            "--render-size",
+            size,
+            "--keys",
+            keys,
+            "log.csv",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn searches_and_exports_from_a_script() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("log.csv"), LOG).unwrap();
+
+    let lines = screen(dir.path(), "G /error n n :export out.csv q");
+    assert_eq!(lines.len(), 12);
+    assert_eq!(fs::read_to_string(dir.path().join("out.csv")).unwrap(), LOG);
+
+    // Lower case matches either case, and the search wraps around
+    let status = screen(dir.path(), "/error n").pop().unwrap();
+    assert!(status.starts_with(" 'error' in row 4, match 2 of 2 "), "{}", status);
+    let status = screen(dir.path(), "/error n n").pop().unwrap();
+    assert!(
+        status.starts_with(" 'error' in row 2, match 1 of 2 (from the top)"),
+        "{}",
+        status
+    );
+    let status = screen(dir.path(), "/Error").pop().unwrap();
+    assert!(status.starts_with(" No row has 'Error'"), "{}", status);
+
+    // The rows picked with `V`, in the format the extension names
+    screen(dir.path(), "&level=info V :export 'info rows.md'");
+    let saved = fs::read_to_string(dir.path().join("info rows.md")).unwrap();
+    assert_eq!(
+        saved,
+        "| id | level | message |\n| ---: | --- | --- |\n| 1 | info | started |\n"
+    );
+}
+
+#[test]
+fn keeps_the_position_apart_from_a_long_message() {
+    let dir = tempfile::tempdir().unwrap();
+    fs::write(dir.path().join("log.csv"), LOG).unwrap();
+
+    let status = screen_at(dir.path(), "70x12", ":99").pop().unwrap();
+    assert!(status.starts_with(" Row 99 is out of range, showing row 5 of "), "{}", status);
+    assert!(status.contains(" Row 5/5 100%  q:quit ?:help"), "{}", status);
+    assert_eq!(status.chars().count(), 70);
+}